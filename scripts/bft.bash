@@ -1,11 +1,20 @@
 _fzf_bash_completion_rust() {
     local output
-    output=$(bft "$READLINE_LINE" "$READLINE_POINT")
+    output=$(BFT_PWD="$PWD" bft "$READLINE_LINE" "$READLINE_POINT")
     local exit_code=$?
 
+    # Exit code 3: bft bypassed this command, let readline complete natively
+    if [ $exit_code -eq 3 ]; then
+        bind '"\e[9999~": complete'
+    else
+        bind '"\e[9999~": redraw-current-line'
+    fi
+
     if [ $exit_code -eq 0 ] && [ -n "$output" ]; then
         eval "$output"
     fi
 }
 
-bind -x '"\t": _fzf_bash_completion_rust'
+bind -x '"\e[9998~": _fzf_bash_completion_rust'
+bind '"\e[9999~": redraw-current-line'
+bind '"\t": "\e[9998~\e[9999~"'
@@ -1,9 +1,10 @@
 use log::debug;
 use std::collections::HashSet;
 use std::env;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub use crate::config::HistoryFormat;
 
 #[derive(Debug, Clone)]
 pub struct HistoryEntry {
@@ -33,59 +34,154 @@ pub fn get_history_file() -> Option<PathBuf> {
 }
 
 pub fn read_history(limit: Option<usize>) -> Vec<HistoryEntry> {
+    read_history_with_format(limit, HistoryFormat::Auto, true)
+}
+
+/// Every history entry, repeats included (e.g. for frequency ranking)
+pub fn read_history_raw(limit: Option<usize>) -> Vec<HistoryEntry> {
+    read_history_with_format(limit, HistoryFormat::Auto, false)
+}
+
+pub fn read_history_with_format(
+    limit: Option<usize>,
+    format: HistoryFormat,
+    dedup: bool,
+) -> Vec<HistoryEntry> {
+    match get_history_file() {
+        Some(histfile) => read_history_file(&histfile, limit, format, dedup),
+        None => {
+            debug!("[history] No history file available");
+            Vec::new()
+        }
+    }
+}
+
+/// Read history entries from `histfile`, parsing it as `format`; with `dedup`
+/// only the first occurrence of each command is kept
+pub fn read_history_file(
+    histfile: &Path,
+    limit: Option<usize>,
+    format: HistoryFormat,
+    dedup: bool,
+) -> Vec<HistoryEntry> {
     let mut entries = Vec::new();
     let mut seen = HashSet::new();
 
-    if let Some(histfile) = get_history_file() {
-        debug!("[history] Checking history file: {}", histfile.display());
+    debug!("[history] Checking history file: {}", histfile.display());
 
-        if !histfile.exists() {
-            debug!("[history] History file does not exist");
+    let content = match fs::read(histfile) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+        Err(e) => {
+            debug!("[history] Cannot read history file: {}", e);
             return entries;
         }
+    };
 
-        if let Ok(file) = File::open(&histfile) {
-            let reader = BufReader::new(file);
-            #[allow(clippy::lines_filter_map_ok)]
-            let total_lines: usize = reader.lines().map_while(Result::ok).count();
-            debug!("[history] Total lines in history file: {}", total_lines);
-
-            // Re-open file for reading
-            if let Ok(file) = File::open(&histfile) {
-                let reader = BufReader::new(file);
-                #[allow(clippy::lines_filter_map_ok)]
-                for line in reader.lines().map_while(Result::ok) {
-                    let trimmed = line.trim();
-                    if !trimmed.is_empty() {
-                        // Skip duplicates and entries starting with space (ignored by bash)
-                        if !trimmed.starts_with(' ') && seen.insert(trimmed.to_string()) {
-                            entries.push(HistoryEntry {
-                                command: trimmed.to_string(),
-                                timestamp: None,
-                            });
-                            if let Some(limit) = limit
-                                && entries.len() >= limit
-                            {
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
+    let format = match format {
+        HistoryFormat::Auto => detect_format(&content),
+        format => format,
+    };
+    let commands = parse_history(&content, format);
+    debug!(
+        "[history] Parsed {} commands as {:?}",
+        commands.len(),
+        format
+    );
 
-            debug!(
-                "[history] Read {} unique entries (limit: {:?})",
-                entries.len(),
-                limit
-            );
+    for command in commands {
+        let trimmed = command.trim();
+        // Skip duplicates and entries starting with space (ignored by bash)
+        if !trimmed.is_empty()
+            && !trimmed.starts_with(' ')
+            && (!dedup || seen.insert(trimmed.to_string()))
+        {
+            entries.push(HistoryEntry {
+                command: trimmed.to_string(),
+                timestamp: None,
+            });
+            if let Some(limit) = limit
+                && entries.len() >= limit
+            {
+                break;
+            }
         }
-    } else {
-        debug!("[history] No history file available");
     }
 
+    debug!(
+        "[history] Read {} entries (dedup: {}, limit: {:?})",
+        entries.len(),
+        dedup,
+        limit
+    );
+
     entries
 }
 
+/// Guess the history format from the first non-empty line
+pub fn detect_format(content: &str) -> HistoryFormat {
+    let first = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    if first.starts_with("- cmd: ") {
+        HistoryFormat::Fish
+    } else if parse_zsh_line(first).is_some() {
+        HistoryFormat::Zsh
+    } else {
+        HistoryFormat::Bash
+    }
+}
+
+/// Extract commands, in file order, from history content of a known format
+pub fn parse_history(content: &str, format: HistoryFormat) -> Vec<String> {
+    match format {
+        HistoryFormat::Fish => content
+            .lines()
+            .filter_map(|line| line.strip_prefix("- cmd: "))
+            .map(unescape_fish)
+            .collect(),
+        HistoryFormat::Zsh => content
+            .lines()
+            .map(|line| parse_zsh_line(line).unwrap_or(line).to_string())
+            .collect(),
+        HistoryFormat::Bash | HistoryFormat::Auto => content
+            .lines()
+            .filter(|line| !is_bash_timestamp(line))
+            .map(|line| line.to_string())
+            .collect(),
+    }
+}
+
+/// Zsh extended history: `: <start>:<elapsed>;<command>`
+fn parse_zsh_line(line: &str) -> Option<&str> {
+    let rest = line.strip_prefix(": ")?;
+    let (meta, command) = rest.split_once(';')?;
+    let (start, elapsed) = meta.split_once(':')?;
+    let is_num = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    (is_num(start) && is_num(elapsed)).then_some(command)
+}
+
+/// `HISTTIMEFORMAT` writes `#<epoch>` lines before each command
+fn is_bash_timestamp(line: &str) -> bool {
+    line.strip_prefix('#')
+        .is_some_and(|ts| !ts.is_empty() && ts.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Fish escapes backslashes and newlines inside `cmd:` values
+fn unescape_fish(cmd: &str) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push(c),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
 /// Get unique command names from history (first word of each command)
 pub fn get_history_commands(limit: Option<usize>) -> Vec<String> {
     let history = read_history(limit);
@@ -151,12 +247,16 @@ pub fn get_matching_history_commands(prefix: &str, limit: Option<usize>) -> Vec<
 
 /// Get full command lines from history that contain the substring, take the last [limit] entries.
 /// If limit is none, all history entries will be returned
-pub fn get_history_commands_by_prefix(substr: &str, limit: Option<usize>) -> Vec<String> {
+pub fn get_history_commands_by_prefix(
+    substr: &str,
+    limit: Option<usize>,
+    format: HistoryFormat,
+) -> Vec<String> {
     if substr.is_empty() {
         return Vec::new();
     }
 
-    let history = read_history(None);
+    let history = read_history_with_format(None, format, true);
     let history_len = history.len();
 
     let filtered: Vec<String> = history
@@ -301,6 +401,70 @@ mod tests {
         unsafe { env::remove_var("HISTFILE") };
     }
 
+    #[test]
+    fn test_read_fish_history() {
+        let mut temp = NamedTempFile::new().unwrap();
+        writeln!(temp, "- cmd: git status").unwrap();
+        writeln!(temp, "  when: 1700000000").unwrap();
+        writeln!(temp, "- cmd: echo a\\\\b").unwrap();
+        writeln!(temp, "  when: 1700000001").unwrap();
+        writeln!(temp, "  paths:").unwrap();
+        writeln!(temp, "    - foo.txt").unwrap();
+        writeln!(temp, "- cmd: git status").unwrap();
+        writeln!(temp, "  when: 1700000002").unwrap();
+        temp.flush().unwrap();
+
+        let content = fs::read_to_string(temp.path()).unwrap();
+        assert_eq!(detect_format(&content), HistoryFormat::Fish);
+
+        let entries = read_history_file(temp.path(), None, HistoryFormat::Auto, true);
+        let commands: Vec<&str> = entries.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(commands, vec!["git status", "echo a\\b"]);
+    }
+
+    #[test]
+    fn test_read_history_raw_keeps_duplicates() {
+        let _guard = TEST_MUTEX.lock().unwrap();
+        let mut temp = NamedTempFile::new().unwrap();
+        writeln!(temp, "make test").unwrap();
+        writeln!(temp, "git status").unwrap();
+        writeln!(temp, "make test").unwrap();
+        temp.flush().unwrap();
+
+        unsafe { env::set_var("HISTFILE", temp.path()) };
+
+        let commands = |entries: Vec<HistoryEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.command).collect()
+        };
+        assert_eq!(
+            commands(read_history_raw(None)),
+            vec!["make test", "git status", "make test"]
+        );
+        assert_eq!(
+            commands(read_history(None)),
+            vec!["make test", "git status"]
+        );
+
+        unsafe { env::remove_var("HISTFILE") };
+    }
+
+    #[test]
+    fn test_parse_zsh_and_bash_timestamps() {
+        let zsh = ": 1700000000:0;ls -la\n: 1700000005:2;make test\n";
+        assert_eq!(detect_format(zsh), HistoryFormat::Zsh);
+        assert_eq!(
+            parse_history(zsh, HistoryFormat::Zsh),
+            vec!["ls -la", "make test"]
+        );
+
+        let bash = "#1700000000\nls -la\n#1700000005\nmake test\n";
+        assert_eq!(detect_format(bash), HistoryFormat::Bash);
+        assert_eq!(
+            parse_history(bash, HistoryFormat::Bash),
+            vec!["ls -la", "make test"]
+        );
+    }
+
     #[test]
     fn test_get_history_subcommands() {
         let _guard = TEST_MUTEX.lock().unwrap();
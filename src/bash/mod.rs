@@ -1,7 +1,16 @@
 pub mod history;
 
-use crate::completion::{CompletionOptions, CompletionSpec};
-use std::process::Command;
+use crate::completion::process::run_with_timeout;
+use crate::completion::{CompletionOptions, CompletionSpec, files};
+use log::debug;
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{self, Child, ChildStdin, Command, Stdio};
+use std::str;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -11,16 +20,175 @@ pub enum BashError {
     #[error("Failed to parse completion spec: {0}")]
     ParseError(String),
     #[error("IO error: {0}")]
-    IoError(#[from] std::io::Error),
+    IoError(#[from] io::Error),
+    #[error("Bash timed out after {0:?}")]
+    Timeout(Duration),
     #[error("Other error: {0}")]
     Other(String),
 }
 
-pub fn query_complete(command: &str) -> Result<Option<CompletionSpec>, BashError> {
-    let quoted_cmd = shlex::try_quote(command).map_err(|e| BashError::Other(e.to_string()))?;
-    let output = Command::new("bash")
-        .args(["-c", &format!("complete -p -- {}", quoted_cmd)])
-        .output()?;
+thread_local! {
+    static SESSION: RefCell<Option<BashSession>> = const { RefCell::new(None) };
+}
+
+/// A long-lived `bash` fed scripts over a pipe, saving a fork/exec per query.
+/// Each script runs in a subshell so no state leaks into the next one; the end
+/// of its output is marked by a sentinel line carrying the exit status.
+///
+/// bft runs once per Tab and the session lives in a thread local, so it only
+/// saves the startups of the queries within one completion, not across them
+pub struct BashSession {
+    child: Child,
+    stdin: ChildStdin,
+    /// Output lines, read on their own thread so a reply can be waited for
+    /// with a deadline
+    lines: Receiver<io::Result<Vec<u8>>>,
+    sentinel: String,
+}
+
+impl BashSession {
+    pub fn spawn() -> Result<Self, BashError> {
+        let mut child = Command::new("bash")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(BashError::Other("bash session has no pipes".to_string()));
+        };
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let mut line = Vec::new();
+                match stdout.read_until(b'\n', &mut line) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        if sender.send(Ok(line)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        let _ = sender.send(Err(e));
+                        break;
+                    }
+                }
+            }
+        });
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        Ok(Self {
+            child,
+            stdin,
+            lines,
+            sentinel: format!("__bft_done_{}_{}__", process::id(), nanos),
+        })
+    }
+
+    /// Run `script` in `dir` as `bash -c` would; its output when it exited
+    /// successfully, or `BashError::Timeout` when it has not after `timeout`
+    pub fn run(
+        &mut self,
+        script: &str,
+        dir: &Path,
+        timeout: Duration,
+    ) -> Result<Option<String>, BashError> {
+        let quote = |s: &str| {
+            shlex::try_quote(s)
+                .map(|q| q.into_owned())
+                .map_err(|e| BashError::Other(e.to_string()))
+        };
+        writeln!(
+            self.stdin,
+            "(cd -- {} && eval {}) </dev/null; printf '%s %d\\n' {} \"$?\"",
+            quote(&dir.to_string_lossy())?,
+            quote(script)?,
+            self.sentinel
+        )?;
+        self.stdin.flush()?;
+
+        let deadline = Instant::now() + timeout;
+        let mut output = String::new();
+        loop {
+            let line = match self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => return Err(BashError::Timeout(timeout)),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(BashError::ExecutionError("bash session exited".to_string()));
+                }
+            };
+            let line = str::from_utf8(&line).map_err(|e| {
+                BashError::Other(format!("Failed to decode stdout as UTF-8: {}", e))
+            })?;
+            // Output without a trailing newline shares its last line with the sentinel
+            if let Some(pos) = line.find(&self.sentinel) {
+                output.push_str(&line[..pos]);
+                let status = line[pos + self.sentinel.len()..].trim();
+                return Ok((status == "0").then_some(output));
+            }
+            output.push_str(line);
+        }
+    }
+}
+
+impl Drop for BashSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Run `script` in the working directory through the shared session, or a
+/// one-shot `bash -c` when the session cannot be used; its output when it
+/// exited successfully within `timeout`
+fn run_script(script: &str, timeout: Duration) -> Result<Option<String>, BashError> {
+    let dir = files::working_dir();
+    let from_session = SESSION.with(|cell| {
+        let mut session = cell.borrow_mut();
+        if session.is_none() {
+            *session = BashSession::spawn()
+                .inspect_err(|e| debug!("[bash] cannot start session: {}", e))
+                .ok();
+        }
+        match session.as_mut()?.run(script, &dir, timeout) {
+            Ok(output) => Some(output),
+            Err(BashError::Timeout(timeout)) => {
+                // The session is still busy with the script; start afresh next time
+                debug!("[bash] killing session after {:?}", timeout);
+                *session = None;
+                Some(None)
+            }
+            Err(e) => {
+                debug!("[bash] session failed, running one-shot: {}", e);
+                *session = None;
+                None
+            }
+        }
+    });
+    match from_session {
+        Some(output) => Ok(output),
+        None => run_one_shot(script, false, timeout),
+    }
+}
+
+fn run_one_shot(
+    script: &str,
+    interactive: bool,
+    timeout: Duration,
+) -> Result<Option<String>, BashError> {
+    let mut command = Command::new("bash");
+    command
+        .current_dir(files::working_dir())
+        .arg(if interactive { "-ic" } else { "-c" })
+        .arg(script);
+    let Some(output) = run_with_timeout(&mut command, None, timeout) else {
+        return Ok(None);
+    };
 
     if !output.status.success() {
         return Ok(None);
@@ -28,10 +196,50 @@ pub fn query_complete(command: &str) -> Result<Option<CompletionSpec>, BashError
 
     let stdout = String::from_utf8(output.stdout)
         .map_err(|e| BashError::Other(format!("Failed to decode stdout as UTF-8: {}", e)))?;
-    parse_compspec_output(&stdout)
+    Ok(Some(stdout))
+}
+
+pub fn query_complete(
+    command: &str,
+    timeout: Duration,
+) -> Result<Option<CompletionSpec>, BashError> {
+    let quoted_cmd = shlex::try_quote(command).map_err(|e| BashError::Other(e.to_string()))?;
+    query_complete_script(&format!("complete -p -- {}", quoted_cmd), timeout)
+}
+
+/// Query the compspec bash applies to an empty command line (`complete -E`)
+pub fn query_complete_empty(timeout: Duration) -> Result<Option<CompletionSpec>, BashError> {
+    query_complete_script("complete -p -E", timeout)
 }
 
-pub fn execute_compgen(args: &[String]) -> Result<Vec<String>, BashError> {
+fn query_complete_script(
+    script: &str,
+    timeout: Duration,
+) -> Result<Option<CompletionSpec>, BashError> {
+    match run_script(script, timeout)? {
+        Some(stdout) => parse_compspec_output(&stdout),
+        None => Ok(None),
+    }
+}
+
+pub fn execute_compgen(args: &[String], timeout: Duration) -> Result<Vec<String>, BashError> {
+    run_compgen(args, false, timeout)
+}
+
+/// Run `compgen` in an interactive shell so rc-defined state (aliases) is
+/// loaded; always one-shot, as the shared session does not read the rc files
+pub fn execute_compgen_interactive(
+    args: &[String],
+    timeout: Duration,
+) -> Result<Vec<String>, BashError> {
+    run_compgen(args, true, timeout)
+}
+
+fn run_compgen(
+    args: &[String],
+    interactive: bool,
+    timeout: Duration,
+) -> Result<Vec<String>, BashError> {
     let quoted_args: Vec<String> = args
         .iter()
         .map(|a| {
@@ -41,28 +249,23 @@ pub fn execute_compgen(args: &[String]) -> Result<Vec<String>, BashError> {
         })
         .collect();
 
-    let output = Command::new("bash")
-        .arg("-c")
-        .arg(format!("compgen {}", quoted_args.join(" ")))
-        .output()?;
-
-    if !output.status.success() {
-        return Ok(Vec::new());
-    }
-
-    let stdout = String::from_utf8(output.stdout)
-        .map_err(|e| BashError::Other(format!("Failed to decode stdout as UTF-8: {}", e)))?;
-    Ok(stdout.lines().map(|s| s.to_string()).collect())
+    let script = format!("compgen {}", quoted_args.join(" "));
+    let stdout = if interactive {
+        run_one_shot(&script, true, timeout)?
+    } else {
+        run_script(&script, timeout)?
+    };
+    Ok(stdout.map(|s| split_candidates(&s)).unwrap_or_default())
 }
 
+/// Run the compspec `function` for the command line `words`, with the cursor
+/// at `point` of `line`; the `COMPREPLY` it fills
 pub fn execute_completion_function(
     function: &str,
-    _command: &str,
-    _word: &str,
-    _previous_word: Option<&str>,
     words: &[String],
     line: &str,
     point: usize,
+    timeout: Duration,
 ) -> Result<Vec<String>, BashError> {
     let words_str = words
         .iter()
@@ -93,15 +296,18 @@ done
         function
     );
 
-    let output = Command::new("bash").arg("-c").arg(&script).output()?;
-
-    if !output.status.success() {
-        return Ok(Vec::new());
-    }
+    Ok(run_script(&script, timeout)?
+        .map(|s| split_candidates(&s))
+        .unwrap_or_default())
+}
 
-    let stdout = String::from_utf8(output.stdout)
-        .map_err(|e| BashError::Other(format!("Failed to decode stdout as UTF-8: {}", e)))?;
-    Ok(stdout.lines().map(|s| s.to_string()).collect())
+/// Split completion output into candidates, dropping blank lines
+pub fn split_candidates(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
 }
 
 fn parse_compspec_output(output: &str) -> Result<Option<CompletionSpec>, BashError> {
@@ -180,3 +386,57 @@ fn parse_option(opt: &str, options: &mut CompletionOptions) {
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::process::DEFAULT_TIMEOUT;
+
+    #[test]
+    fn test_parse_compspec_empty_line() {
+        let spec = parse_compspec_output("complete -W 'start stop' -E\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(spec.wordlist.as_deref(), Some("start stop"));
+        assert!(spec.function.is_none());
+    }
+
+    #[test]
+    fn test_session_matches_one_shot() {
+        let timeout = DEFAULT_TIMEOUT;
+        let script = "compgen -W 'alpha beta alps' -- al";
+        let one_shot = run_one_shot(script, false, timeout).unwrap();
+        assert_eq!(one_shot.as_deref(), Some("alpha\nalps\n"));
+
+        let dir = files::working_dir();
+        let mut session = BashSession::spawn().unwrap();
+        assert_eq!(session.run(script, &dir, timeout).unwrap(), one_shot);
+        // Reused, without state leaking between scripts
+        assert_eq!(session.run("x=1; false", &dir, timeout).unwrap(), None);
+        assert_eq!(
+            session
+                .run("printf '%s' \"${x:-unset}\"", &dir, timeout)
+                .unwrap(),
+            Some("unset".to_string())
+        );
+        assert_eq!(session.run(script, &dir, timeout).unwrap(), one_shot);
+    }
+
+    #[test]
+    fn test_slow_script_times_out() {
+        let timeout = Duration::from_millis(100);
+        let started = Instant::now();
+        assert_eq!(run_one_shot("sleep 5", false, timeout).unwrap(), None);
+
+        let mut session = BashSession::spawn().unwrap();
+        let result = session.run("sleep 5", &files::working_dir(), timeout);
+        assert!(matches!(result, Err(BashError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_split_candidates_blank_and_whitespace() {
+        let output = "foo\n\n   \nbar baz  \n  qux\n";
+        assert_eq!(split_candidates(output), vec!["foo", "bar baz  ", "  qux"]);
+    }
+}
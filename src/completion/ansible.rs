@@ -0,0 +1,396 @@
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files,
+};
+
+const ANSIBLE: &str = "ansible";
+const ANSIBLE_PLAYBOOK: &str = "ansible-playbook";
+
+/// Inventory used when none is given with `-i`
+const DEFAULT_INVENTORY: &str = "/etc/ansible/hosts";
+
+const INVENTORY_FLAGS: &[&str] = &["-i", "--inventory", "--inventory-file"];
+const LIMIT_FLAGS: &[&str] = &["-l", "--limit"];
+
+/// Options of `ansible`/`ansible-playbook` that take a value
+const VALUE_FLAGS: &[&str] = &[
+    "-i",
+    "--inventory",
+    "--inventory-file",
+    "-l",
+    "--limit",
+    "-m",
+    "--module-name",
+    "-a",
+    "--args",
+    "-u",
+    "--user",
+    "-e",
+    "--extra-vars",
+    "-f",
+    "--forks",
+    "-t",
+    "--tags",
+    "--skip-tags",
+    "-c",
+    "--connection",
+    "-T",
+    "--timeout",
+    "-M",
+    "--module-path",
+    "--become-user",
+    "--become-method",
+    "--private-key",
+    "--vault-password-file",
+    "--vault-id",
+];
+
+/// What the cursor position expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsibleValue {
+    /// Host and group names from the inventory
+    Hosts,
+    Playbooks,
+}
+
+/// Completes inventory hosts/groups and playbook files for `ansible` and `ansible-playbook`
+pub struct AnsibleProvider;
+
+impl Default for AnsibleProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnsibleProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CompletionProvider for AnsibleProvider {
+    fn name(&self) -> &'static str {
+        "ansible"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ansible
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0 && (ctx.command == ANSIBLE || ctx.command == ANSIBLE_PLAYBOOK)
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let entries = match value_kind(ctx) {
+            Some(AnsibleValue::Hosts) => {
+                let (hosts, groups) = match inventory_source(&ctx.words[..ctx.current_word_idx]) {
+                    InventorySource::Hosts(hosts) => (hosts, Vec::new()),
+                    InventorySource::Path(path) => read_inventory(&path),
+                };
+                let groups = groups.into_iter().map(|g| (g, "group"));
+                hosts
+                    .into_iter()
+                    .map(|h| (h, "host"))
+                    .chain(groups)
+                    .filter(|(name, _)| name.starts_with(&ctx.current_word))
+                    .map(|(name, description)| {
+                        CompletionEntry::new(name, ProviderKind::Ansible)
+                            .with_type(CandidateType::Value)
+                            .with_description(description.to_string())
+                    })
+                    .collect()
+            }
+            Some(AnsibleValue::Playbooks) => {
+                let dirs = files::complete_paths(&ctx.current_word, true);
+                files::complete_paths(&ctx.current_word, false)
+                    .into_iter()
+                    .filter_map(|path| {
+                        // Directories stay, with a `/` to descend into them
+                        let (value, candidate_type) = if dirs.contains(&path) {
+                            (format!("{}/", path), CandidateType::Dir)
+                        } else if is_playbook(&path) {
+                            (path, CandidateType::File)
+                        } else {
+                            return None;
+                        };
+                        Some(
+                            CompletionEntry::new(value, ProviderKind::Ansible)
+                                .with_type(candidate_type),
+                        )
+                    })
+                    .collect()
+            }
+            None => return Ok(None),
+        };
+        Ok(Some(entries))
+    }
+}
+
+/// Hosts after `-l`/`--limit` and as the pattern of `ansible`, playbooks as
+/// the arguments of `ansible-playbook`
+pub fn value_kind(ctx: &CompletionContext) -> Option<AnsibleValue> {
+    if ctx.current_word.starts_with('-') {
+        return None;
+    }
+    if let Some(previous) = ctx.previous_word.as_deref()
+        && VALUE_FLAGS.contains(&previous)
+    {
+        return LIMIT_FLAGS
+            .contains(&previous)
+            .then_some(AnsibleValue::Hosts);
+    }
+    if ctx.command == ANSIBLE_PLAYBOOK {
+        return Some(AnsibleValue::Playbooks);
+    }
+    // `ansible <pattern> -m ping`: only the first argument is a host pattern
+    let mut words = ctx.words[1..ctx.current_word_idx].iter();
+    while let Some(word) = words.next() {
+        if VALUE_FLAGS.contains(&word.as_str()) {
+            words.next();
+        } else if !word.starts_with('-') {
+            return None;
+        }
+    }
+    Some(AnsibleValue::Hosts)
+}
+
+fn is_playbook(path: &str) -> bool {
+    path.ends_with(".yml") || path.ends_with(".yaml")
+}
+
+/// Where the inventory comes from
+#[derive(Debug, PartialEq, Eq)]
+enum InventorySource {
+    Path(PathBuf),
+    /// `-i host1,host2,` lists hosts inline
+    Hosts(Vec<String>),
+}
+
+/// The inventory given with `-i` (the last one wins), or the default one
+fn inventory_source(words: &[String]) -> InventorySource {
+    let mut inventory = None;
+    let mut iter = words.iter();
+    while let Some(word) = iter.next() {
+        if INVENTORY_FLAGS.contains(&word.as_str()) {
+            inventory = iter.next().cloned();
+        } else if let Some(value) = word.strip_prefix("--inventory=") {
+            inventory = Some(value.to_string());
+        }
+    }
+
+    match inventory {
+        Some(list) if list.contains(',') => InventorySource::Hosts(
+            list.split(',')
+                .filter(|h| !h.is_empty())
+                .map(|h| h.to_string())
+                .collect(),
+        ),
+        Some(path) => InventorySource::Path(files::resolve_path(&shellexpand::tilde(&path))),
+        None => InventorySource::Path(PathBuf::from(DEFAULT_INVENTORY)),
+    }
+}
+
+/// Hosts and groups of an inventory file, or of every file in an inventory directory
+fn read_inventory(path: &Path) -> (Vec<String>, Vec<String>) {
+    let paths = if path.is_dir() {
+        match fs::read_dir(path) {
+            Ok(entries) => {
+                let mut paths: Vec<PathBuf> = entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| p.is_file())
+                    .collect();
+                paths.sort();
+                paths
+            }
+            Err(e) => {
+                debug!("[ansible] cannot list {}: {}", path.display(), e);
+                return (Vec::new(), Vec::new());
+            }
+        }
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut inventory = Inventory::default();
+    for path in paths {
+        match fs::read_to_string(&path) {
+            Ok(content) if is_yaml_inventory(&content) => inventory.parse_yaml(&content),
+            Ok(content) => inventory.parse_ini(&content),
+            Err(e) => debug!("[ansible] cannot read {}: {}", path.display(), e),
+        }
+    }
+    (inventory.hosts, inventory.groups)
+}
+
+/// YAML inventories open with `---` or a top-level `group:` key
+fn is_yaml_inventory(content: &str) -> bool {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'))
+        .is_some_and(|line| line == "---" || (line.ends_with(':') && !line.starts_with('[')))
+}
+
+/// Host and group names of an inventory, in file order without duplicates
+#[derive(Debug, Default)]
+pub struct Inventory {
+    pub hosts: Vec<String>,
+    pub groups: Vec<String>,
+}
+
+impl Inventory {
+    fn add_host(&mut self, host: &str) {
+        // Ranges like `web[01:20]` are patterns, not names
+        if !host.contains('[') && !self.hosts.iter().any(|h| h == host) {
+            self.hosts.push(host.to_string());
+        }
+    }
+
+    fn add_group(&mut self, group: &str) {
+        if !self.groups.iter().any(|g| g == group) {
+            self.groups.push(group.to_string());
+        }
+    }
+
+    /// `[group]` sections listing hosts, `[group:children]` listing groups
+    pub fn parse_ini(&mut self, content: &str) {
+        let mut section = "";
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let (group, kind) = header.split_once(':').unwrap_or((header, ""));
+                self.add_group(group);
+                section = kind;
+                continue;
+            }
+            let Some(name) = line.split_whitespace().next() else {
+                continue;
+            };
+            match section {
+                "" => self.add_host(name),
+                "children" => self.add_group(name),
+                // `[group:vars]` holds variables
+                _ => {}
+            }
+        }
+    }
+
+    /// Keys under `hosts:` are hosts; top-level keys and keys under `children:` are groups
+    pub fn parse_yaml(&mut self, content: &str) {
+        // (indent, key) of the enclosing mapping keys
+        let mut parents: Vec<(usize, String)> = Vec::new();
+        for line in content.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with(['#', '-']) {
+                continue;
+            }
+            let Some((key, _)) = trimmed.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().trim_matches(['"', '\'']);
+            let indent = line.len() - trimmed.len();
+            while parents.last().is_some_and(|(i, _)| *i >= indent) {
+                parents.pop();
+            }
+            match parents.last().map(|(_, k)| k.as_str()) {
+                Some("hosts") => self.add_host(key),
+                None | Some("children") => self.add_group(key),
+                _ => {}
+            }
+            parents.push((indent, key.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{ctx_for, values_for};
+
+    #[test]
+    fn test_hosts_and_playbooks() {
+        let provider = AnsibleProvider::new();
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(
+            root.join("hosts.ini"),
+            "bastion ansible_host=10.0.0.1\n\n[web]\nweb1.example.com\nweb[02:09].example.com\n\n\
+             [db]\ndb1 ansible_port=2222\n\n[prod:children]\nweb\ndb\n\n[prod:vars]\nenv=prod\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("hosts.yml"),
+            "---\nall:\n  hosts:\n    mail.example.com:\n  children:\n    cache:\n      hosts:\n\
+             \x20       redis1:\n          ansible_host: 10.0.0.9\n      vars:\n        ttl: 60\n",
+        )
+        .unwrap();
+        fs::write(root.join("site.yml"), "- hosts: all\n").unwrap();
+        fs::write(root.join("deploy.yaml"), "").unwrap();
+        fs::write(root.join("README.md"), "").unwrap();
+        fs::create_dir(root.join("playbooks")).unwrap();
+
+        let base = format!("{}/", root.display());
+        let ini = format!("{}hosts.ini", base);
+        assert_eq!(
+            values_for(&provider, &format!("ansible -i {} ", ini)).unwrap(),
+            vec!["bastion", "web1.example.com", "db1", "web", "db", "prod"]
+        );
+        assert_eq!(
+            values_for(
+                &provider,
+                &format!("ansible-playbook --inventory={}hosts.yml -l ", base)
+            )
+            .unwrap(),
+            vec!["mail.example.com", "redis1", "all", "cache"]
+        );
+        assert_eq!(
+            values_for(&provider, "ansible -i web1,web2, w").unwrap(),
+            vec!["web1", "web2"]
+        );
+
+        let mut playbooks =
+            values_for(&provider, &format!("ansible-playbook -i {} {}", ini, base)).unwrap();
+        playbooks.sort();
+        assert_eq!(
+            playbooks,
+            vec![
+                format!("{}deploy.yaml", base),
+                format!("{}hosts.yml", base),
+                format!("{}playbooks/", base),
+                format!("{}site.yml", base)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_positions() {
+        let kind = |line: &str| value_kind(&ctx_for(line));
+        assert_eq!(kind("ansible we"), Some(AnsibleValue::Hosts));
+        assert_eq!(kind("ansible -i inv "), Some(AnsibleValue::Hosts));
+        assert_eq!(kind("ansible web -m "), None);
+        assert_eq!(kind("ansible web -a "), None);
+        assert_eq!(kind("ansible web "), None);
+        assert_eq!(kind("ansible-playbook -l "), Some(AnsibleValue::Hosts));
+        assert_eq!(
+            kind("ansible-playbook -i inv "),
+            Some(AnsibleValue::Playbooks)
+        );
+        assert_eq!(kind("ansible-playbook -i "), None);
+        assert_eq!(
+            kind("ansible-playbook -e x=1 s"),
+            Some(AnsibleValue::Playbooks)
+        );
+    }
+}
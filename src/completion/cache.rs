@@ -0,0 +1,174 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::completion::{CompletionContext, CompletionResult, files};
+
+const CACHE_FILE: &str = "candidates.json";
+
+/// Completion position whose candidates can be reused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub command: String,
+    /// The words before the one completed, so `git checkout` and `git merge`
+    /// are different positions
+    pub words: Vec<String>,
+    pub dir: PathBuf,
+}
+
+impl CacheKey {
+    pub fn for_context(ctx: &CompletionContext) -> Self {
+        let cword = ctx.current_word_idx.min(ctx.words.len());
+        Self {
+            command: ctx.command.clone(),
+            words: ctx.words[..cword].to_vec(),
+            dir: files::working_dir(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheRecord {
+    key: CacheKey,
+    word: String,
+    saved_at_ms: u128,
+    result: CompletionResult,
+}
+
+/// Candidates of the last completion, reused when completion is triggered
+/// again at the same position shortly after (e.g. once a common prefix was
+/// inserted)
+pub struct CandidateCache {
+    path: PathBuf,
+    ttl: Duration,
+}
+
+impl CandidateCache {
+    pub fn new(dir: PathBuf, ttl: Duration) -> Self {
+        Self {
+            path: dir.join(CACHE_FILE),
+            ttl,
+        }
+    }
+
+    /// The cached result for `key` when it is younger than the TTL and `word`
+    /// extends the word it was computed for, narrowed to candidates still
+    /// starting with `word`
+    pub fn lookup(&self, key: &CacheKey, word: &str, now: SystemTime) -> Option<CompletionResult> {
+        let content = fs::read_to_string(&self.path).ok()?;
+        let record: CacheRecord = match serde_json::from_str(&content) {
+            Ok(record) => record,
+            Err(e) => {
+                debug!("[cache] ignoring unreadable {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        let age = millis_since_epoch(now).saturating_sub(record.saved_at_ms);
+        if record.key != *key || !word.starts_with(&record.word) || age >= self.ttl.as_millis() {
+            return None;
+        }
+
+        let mut result = record.result;
+        result.candidates.retain(|c| c.value.starts_with(word));
+        Some(result)
+    }
+
+    pub fn store(&self, key: &CacheKey, word: &str, result: &CompletionResult, now: SystemTime) {
+        let record = CacheRecord {
+            key: key.clone(),
+            word: word.to_string(),
+            saved_at_ms: millis_since_epoch(now),
+            result: result.clone(),
+        };
+        let content = match serde_json::to_string(&record) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("[cache] cannot serialize candidates: {}", e);
+                return;
+            }
+        };
+        if let Some(dir) = self.path.parent()
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            debug!("[cache] cannot create {}: {}", dir.display(), e);
+            return;
+        }
+        if let Err(e) = fs::write(&self.path, content) {
+            debug!("[cache] cannot write {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn millis_since_epoch(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+/// `$XDG_CACHE_HOME/bft`, or `~/.cache/bft`
+pub fn default_cache_dir() -> Option<PathBuf> {
+    match env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("bft")),
+        _ => env::var("HOME")
+            .ok()
+            .map(|home| PathBuf::from(home).join(".cache/bft")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{CompletionEntry, ProviderKind, ctx_for};
+
+    #[test]
+    fn test_retrigger_reuses_candidates() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = CandidateCache::new(dir.path().to_path_buf(), Duration::from_secs(2));
+        let key = CacheKey {
+            command: "git".to_string(),
+            words: vec!["git".to_string()],
+            dir: PathBuf::from("/repo"),
+        };
+        let result = CompletionResult {
+            candidates: ["checkout", "cherry-pick", "commit"]
+                .iter()
+                .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Carapace))
+                .collect(),
+            used_provider: ProviderKind::Carapace,
+            spec: Default::default(),
+        };
+        let start = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        cache.store(&key, "c", &result, start);
+
+        let later = start + Duration::from_millis(500);
+        let reused = cache.lookup(&key, "che", later).unwrap();
+        let values: Vec<&str> = reused.candidates.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(values, vec!["checkout", "cherry-pick"]);
+        assert_eq!(reused.used_provider, ProviderKind::Carapace);
+
+        // Expired, another position, or a word not extending the cached one
+        assert!(
+            cache
+                .lookup(&key, "che", start + Duration::from_secs(2))
+                .is_none()
+        );
+        let other = CacheKey {
+            words: vec!["git".to_string(), "log".to_string()],
+            ..key.clone()
+        };
+        assert!(cache.lookup(&other, "che", later).is_none());
+        assert!(cache.lookup(&key, "s", later).is_none());
+    }
+
+    #[test]
+    fn test_key_tells_subcommands_apart() {
+        let key_for = |line: &str| CacheKey::for_context(&ctx_for(line));
+        let checkout = key_for("git checkout ");
+        assert_eq!(checkout.words, vec!["git", "checkout"]);
+        assert_ne!(checkout, key_for("git merge "));
+        assert_eq!(checkout, key_for("git checkout ma"));
+    }
+}
@@ -1,7 +1,11 @@
 use anyhow::Result;
-use log::debug;
+use log::{debug, info};
 use serde::Deserialize;
+use std::path::Path;
 use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{files, process};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct CarapaceItem {
@@ -21,22 +25,56 @@ struct CarapaceOutput {
     pub values: Vec<CarapaceItem>,
 }
 
+/// Binary run unless another path is configured
+pub const CARAPACE: &str = "carapace";
+
 pub struct CarapaceProvider;
 
 impl CarapaceProvider {
-    pub fn fetch_suggestions(cmd_name: &str, args: &[String]) -> Result<Option<Vec<CarapaceItem>>> {
-        let mut command = Command::new("carapace");
+    /// `<program> <cmd_name> export <args[0]> <extra_args...> <args[1..]...>`:
+    /// the extra args go after the command word, where carapace parses flags
+    pub fn export_command(
+        program: &Path,
+        cmd_name: &str,
+        extra_args: &[String],
+        args: &[String],
+    ) -> Command {
+        let mut command = Command::new(program);
+        command.current_dir(files::working_dir());
         command.arg(cmd_name).arg("export");
+        let (first, rest) = args
+            .split_first()
+            .map_or((None, args), |(f, r)| (Some(f), r));
+        command.args(first).args(extra_args).args(rest);
+        command
+    }
 
-        debug!("cmd_name: {cmd_name}, args: {:?}", args);
-
-        for arg in args {
-            command.arg(arg);
+    /// Suggestions from carapace, or `None` (leaving the word to bash) when it
+    /// fails, prints no valid JSON or is killed after `timeout`
+    pub fn fetch_suggestions(
+        program: &Path,
+        cmd_name: &str,
+        extra_args: &[String],
+        args: &[String],
+        timeout: Duration,
+    ) -> Result<Option<Vec<CarapaceItem>>> {
+        if process::find_program(program).is_none() {
+            info!(
+                "carapace not found at {}, leaving completion to bash",
+                program.display()
+            );
+            return Ok(None);
         }
+        let mut command = Self::export_command(program, cmd_name, extra_args, args);
 
-        let output = match command.output() {
-            Ok(o) => o,
-            Err(_) => return Ok(None),
+        debug!(
+            "cmd_name: {cmd_name}, extra_args: {:?}, args: {:?}",
+            extra_args, args
+        );
+
+        let output = match process::run_with_timeout(&mut command, None, timeout) {
+            Some(o) => o,
+            None => return Ok(None),
         };
 
         if !output.status.success() {
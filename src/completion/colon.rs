@@ -0,0 +1,159 @@
+use std::fs;
+
+use crate::completion::{CompletionContext, ssh};
+
+/// What one `:`-separated segment of an operand names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    User,
+    Group,
+    Host,
+    Path,
+}
+
+/// Commands with colon-separated operands
+struct ColonOperand {
+    command: &'static str,
+    /// Segment kinds in order; the last one also covers any further segments
+    segments: &'static [Segment],
+    /// Only the first operand has segments, so it is routed even before a `:`
+    /// is typed; otherwise only words containing a `:` are
+    first_only: bool,
+}
+
+const COLON_OPERANDS: &[ColonOperand] = &[
+    ColonOperand {
+        command: "chown",
+        segments: &[Segment::User, Segment::Group],
+        first_only: true,
+    },
+    ColonOperand {
+        command: "rsync",
+        segments: &[Segment::Host, Segment::Path],
+        first_only: false,
+    },
+    ColonOperand {
+        command: "scp",
+        segments: &[Segment::Host, Segment::Path],
+        first_only: false,
+    },
+];
+
+/// Split `word` on `:`; the last segment is the one being typed
+pub fn split_segments(word: &str) -> Vec<&str> {
+    word.split(':').collect()
+}
+
+/// Segment kind at `index` in an operand of `command`
+pub fn segment_at(command: &str, index: usize) -> Option<Segment> {
+    let operand = COLON_OPERANDS.iter().find(|o| o.command == command)?;
+    operand
+        .segments
+        .get(index)
+        .or(operand.segments.last())
+        .copied()
+}
+
+/// The segment at the cursor when the current word is a colon-separated
+/// operand, with the part of the word before it (kept on insertion)
+pub fn current_segment(ctx: &CompletionContext) -> Option<(Segment, &str)> {
+    let operand = COLON_OPERANDS.iter().find(|o| o.command == ctx.command)?;
+    let word = ctx.current_word.as_str();
+    if ctx.current_word_idx == 0 || word.starts_with('-') {
+        return None;
+    }
+    let routed = if operand.first_only {
+        ctx.words[1..ctx.current_word_idx]
+            .iter()
+            .all(|w| w.starts_with('-'))
+    } else {
+        word.contains(':')
+    };
+    if !routed {
+        return None;
+    }
+
+    let segments = split_segments(word);
+    let current = segments.last().copied().unwrap_or_default();
+    let segment = segment_at(&ctx.command, segments.len() - 1)?;
+    Some((segment, &word[..word.len() - current.len()]))
+}
+
+/// Candidates for the segment at the cursor, the earlier segments kept, with
+/// the values of each segment kind from `list`. `None` when the cursor is not
+/// on a segment `list` has values for (paths are left to the path completers)
+pub fn segment_candidates<F>(ctx: &CompletionContext, list: F) -> Option<Vec<String>>
+where
+    F: FnOnce(Segment) -> Option<Vec<String>>,
+{
+    let (segment, kept) = current_segment(ctx)?;
+    let current = &ctx.current_word[kept.len()..];
+    let values = list(segment)?;
+    Some(
+        values
+            .into_iter()
+            .filter(|v| v.starts_with(current))
+            .map(|v| format!("{}{}", kept, v))
+            .collect(),
+    )
+}
+
+/// Values of a segment kind from the local system
+pub fn segment_values(segment: Segment) -> Option<Vec<String>> {
+    match segment {
+        Segment::User => fs::read_to_string("/etc/passwd")
+            .ok()
+            .map(|content| ssh::parse_passwd_users(&content)),
+        // `/etc/group` also starts each entry with its name
+        Segment::Group => fs::read_to_string("/etc/group")
+            .ok()
+            .map(|content| ssh::parse_passwd_users(&content)),
+        Segment::Host => ssh::ssh_config_path().map(|path| ssh::read_config_hosts(&path)),
+        Segment::Path => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_split_segments() {
+        assert_eq!(split_segments("alice:staff"), vec!["alice", "staff"]);
+        assert_eq!(split_segments("alice:"), vec!["alice", ""]);
+        assert_eq!(
+            split_segments("10.0.0.1:8080:80"),
+            vec!["10.0.0.1", "8080", "80"]
+        );
+        assert_eq!(split_segments("plain"), vec!["plain"]);
+
+        assert_eq!(segment_at("chown", 0), Some(Segment::User));
+        assert_eq!(segment_at("chown", 1), Some(Segment::Group));
+        assert_eq!(segment_at("scp", 3), Some(Segment::Path));
+        assert_eq!(segment_at("ls", 0), None);
+    }
+
+    #[test]
+    fn test_segment_routing() {
+        let stub = |segment: Segment| match segment {
+            Segment::User => Some(vec!["alice".to_string(), "bob".to_string()]),
+            Segment::Group => Some(vec!["staff".to_string(), "wheel".to_string()]),
+            Segment::Host => Some(vec!["web1".to_string()]),
+            Segment::Path => None,
+        };
+        let values = |line: &str| segment_candidates(&ctx_for(line), stub);
+
+        assert_eq!(values("chown al"), Some(vec!["alice".to_string()]));
+        assert_eq!(
+            values("chown -R alice:wh"),
+            Some(vec!["alice:wheel".to_string()])
+        );
+        // Later chown operands are files
+        assert_eq!(values("chown alice fi"), None);
+        // A bare scp operand is a local path; after the `:` comes the remote path
+        assert_eq!(values("scp we"), None);
+        assert_eq!(values("scp web1:/et"), None);
+        assert_eq!(values("ls al"), None);
+    }
+}
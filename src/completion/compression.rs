@@ -0,0 +1,201 @@
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind,
+};
+
+/// Level flags of a compressor: `-min`..`-max`, the default level and extra
+/// level-related flags with their hints
+struct LevelTable {
+    commands: &'static [&'static str],
+    min: u8,
+    max: u8,
+    default: u8,
+    extra: &'static [(&'static str, &'static str)],
+}
+
+const LEVEL_TABLES: &[LevelTable] = &[
+    LevelTable {
+        commands: &["zstd", "zstdmt"],
+        min: 1,
+        max: 19,
+        default: 3,
+        extra: &[
+            ("--ultra", "unlock levels 20-22"),
+            ("--fast", "negative levels, faster than -1"),
+        ],
+    },
+    LevelTable {
+        commands: &["gzip", "pigz", "bzip2", "pbzip2", "lbzip2"],
+        min: 1,
+        max: 9,
+        default: 6,
+        extra: &[("--fast", "same as -1"), ("--best", "same as -9")],
+    },
+    LevelTable {
+        commands: &["xz", "lzma"],
+        min: 0,
+        max: 9,
+        default: 6,
+        extra: &[("--extreme", "slower, slightly smaller")],
+    },
+];
+
+/// `zstd` levels only accepted after `--ultra`
+const ZSTD_ULTRA_MAX: u8 = 22;
+
+/// `tar` options naming the compression program
+const TAR_PROGRAM_FLAGS: &[&str] = &["-I", "--use-compress-program"];
+
+/// Compression programs (with levels) offered to `tar -I`
+const TAR_PROGRAMS: &[(&str, &str)] = &[
+    ("gzip -9", "best gzip"),
+    ("pigz -9", "parallel gzip"),
+    ("xz -9", "best xz"),
+    ("xz -T0 -9", "multi-threaded xz"),
+    ("zstd -19", "best zstd"),
+    ("zstd -T0 --ultra -22", "max multi-threaded zstd"),
+];
+
+/// Completes compression level flags of `zstd`/`gzip`/`xz`/... and the
+/// compression program of `tar -I`
+pub struct CompressionProvider;
+
+impl Default for CompressionProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CompressionProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CompletionProvider for CompressionProvider {
+    fn name(&self) -> &'static str {
+        "compression"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Compression
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0 && (level_table(&ctx.command).is_some() || ctx.command == "tar")
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let values = if ctx.command == "tar" {
+            match ctx.previous_word.as_deref() {
+                Some(flag) if TAR_PROGRAM_FLAGS.contains(&flag) => TAR_PROGRAMS
+                    .iter()
+                    .map(|(program, hint)| (program.to_string(), hint.to_string()))
+                    .collect(),
+                _ => return Ok(None),
+            }
+        } else {
+            let Some(table) = level_table(&ctx.command) else {
+                return Ok(None);
+            };
+            if !ctx.current_word.starts_with('-') {
+                return Ok(None);
+            }
+            let ultra = ctx.words[1..ctx.current_word_idx]
+                .iter()
+                .any(|w| w == "--ultra");
+            level_flags(table, ultra)
+        };
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|(value, _)| value.starts_with(&ctx.current_word))
+                .map(|(value, hint)| {
+                    let candidate_type = if value.starts_with('-') {
+                        CandidateType::Flag
+                    } else {
+                        CandidateType::Value
+                    };
+                    let entry = CompletionEntry::new(value, ProviderKind::Compression)
+                        .with_type(candidate_type);
+                    if hint.is_empty() {
+                        entry
+                    } else {
+                        entry.with_description(hint)
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn level_table(command: &str) -> Option<&'static LevelTable> {
+    LEVEL_TABLES.iter().find(|t| t.commands.contains(&command))
+}
+
+/// `-N` level flags (plus the ultra levels once `--ultra` was given) and the extra flags
+fn level_flags(table: &LevelTable, ultra: bool) -> Vec<(String, String)> {
+    let max = if ultra && table.commands.contains(&"zstd") {
+        ZSTD_ULTRA_MAX
+    } else {
+        table.max
+    };
+    let levels = (table.min..=max).map(|level| {
+        let hint = if level == table.min {
+            "fastest"
+        } else if level == table.default {
+            "default"
+        } else if level == max {
+            "smallest"
+        } else {
+            ""
+        };
+        (format!("-{}", level), hint.to_string())
+    });
+    let extra = table
+        .extra
+        .iter()
+        .map(|(flag, hint)| (flag.to_string(), hint.to_string()));
+    levels.chain(extra).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::values_for;
+
+    #[test]
+    fn test_zstd_level_flags() {
+        let provider = CompressionProvider::new();
+        let flags = values_for(&provider, "zstd -").unwrap();
+        assert_eq!(flags.len(), 19 + 2);
+        assert_eq!(flags[0], "-1");
+        assert_eq!(flags[18], "-19");
+        assert!(flags.contains(&"--ultra".to_string()));
+
+        assert_eq!(
+            values_for(&provider, "zstd -1").unwrap(),
+            vec![
+                "-1", "-10", "-11", "-12", "-13", "-14", "-15", "-16", "-17", "-18", "-19"
+            ]
+        );
+        assert_eq!(
+            values_for(&provider, "zstd --ultra -2").unwrap(),
+            vec!["-2", "-20", "-21", "-22"]
+        );
+        assert_eq!(values_for(&provider, "xz -").unwrap()[0], "-0");
+        assert_eq!(values_for(&provider, "gzip --b").unwrap(), vec!["--best"]);
+        assert_eq!(values_for(&provider, "zstd file"), None);
+
+        assert_eq!(
+            values_for(&provider, "tar -cf out.tar.zst -I zstd").unwrap(),
+            vec!["zstd -19", "zstd -T0 --ultra -22"]
+        );
+        assert_eq!(values_for(&provider, "tar -x"), None);
+        assert_eq!(values_for(&provider, "ls -"), None);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind,
+};
+
+const CRONTAB: &str = "crontab";
+
+/// Schedule macros replacing the five time fields
+const CRON_MACROS: &[(&str, &str)] = &[
+    ("@reboot", "once at startup"),
+    ("@yearly", "0 0 1 1 *"),
+    ("@annually", "0 0 1 1 *"),
+    ("@monthly", "0 0 1 * *"),
+    ("@weekly", "0 0 * * 0"),
+    ("@daily", "0 0 * * *"),
+    ("@midnight", "0 0 * * *"),
+    ("@hourly", "0 * * * *"),
+];
+
+/// Value hints of the five time fields, in order
+const FIELD_HINTS: &[&[(&str, &str)]] = &[
+    &[
+        ("*", "every minute"),
+        ("*/5", "every 5 minutes"),
+        ("*/15", "every 15 minutes"),
+        ("0", "on the hour"),
+        ("0-59", "minute range"),
+    ],
+    &[
+        ("*", "every hour"),
+        ("*/2", "every 2 hours"),
+        ("0", "midnight"),
+        ("9-17", "working hours"),
+        ("0-23", "hour range"),
+    ],
+    &[
+        ("*", "every day of the month"),
+        ("1", "first of the month"),
+        ("1,15", "1st and 15th"),
+        ("1-31", "day of month range"),
+    ],
+    &[
+        ("*", "every month"),
+        ("*/3", "every quarter"),
+        ("1-12", "month range"),
+        ("jan-dec", "month names"),
+    ],
+    &[
+        ("*", "every day of the week"),
+        ("1-5", "Monday to Friday"),
+        ("0,6", "weekends"),
+        ("0-6", "day of week range, 0 is Sunday"),
+        ("mon-fri", "day names"),
+    ],
+];
+
+/// Completes the schedule of a crontab entry: `@daily`-style macros and hints
+/// for the five time fields, for `crontab` or a line that is itself an entry
+pub struct CronProvider;
+
+impl Default for CronProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CronProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CompletionProvider for CronProvider {
+    fn name(&self) -> &'static str {
+        "cron"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Cron
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        (ctx.command == CRONTAB && ctx.current_word.starts_with('@'))
+            || schedule_field(ctx).is_some()
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let values: &[(&str, &str)] = if ctx.current_word.starts_with('@') {
+            CRON_MACROS
+        } else {
+            match schedule_field(ctx) {
+                Some(field) => FIELD_HINTS[field],
+                None => return Ok(None),
+            }
+        };
+
+        Ok(Some(
+            values
+                .iter()
+                .filter(|(value, _)| value.starts_with(&ctx.current_word))
+                .map(|(value, hint)| {
+                    CompletionEntry::new(value.to_string(), ProviderKind::Cron)
+                        .with_type(CandidateType::Value)
+                        .with_description(hint.to_string())
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Index of the time field at the cursor when the line is a crontab entry:
+/// every word before it is a time field, and the first word is being typed as
+/// a macro or `*`-field
+pub fn schedule_field(ctx: &CompletionContext) -> Option<usize> {
+    let idx = ctx.current_word_idx;
+    let is_entry_so_far = ctx.words[..idx]
+        .iter()
+        .enumerate()
+        .all(|(field, w)| is_time_field(w, field));
+    if idx >= FIELD_HINTS.len() || !is_entry_so_far {
+        return None;
+    }
+    let word = ctx.current_word.as_str();
+    let is_entry = if idx == 0 {
+        word.starts_with('@') || word.starts_with('*')
+    } else {
+        // Macros replace all five fields
+        !word.starts_with('@')
+    };
+    is_entry.then_some(idx)
+}
+
+/// `word` can be time field `field` (0 for minutes): `*`, `*/5`, `1-5`,
+/// `1,15`, ..., or month and day names (`jan`, `mon-fri`) in the last two
+fn is_time_field(word: &str, field: usize) -> bool {
+    let names = field >= 3;
+    let allowed = |c: char| c.is_ascii_digit() || names && c.is_ascii_alphabetic();
+    word.starts_with(|c: char| c == '*' || allowed(c))
+        && word.chars().all(|c| allowed(c) || "*/,-".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::values_for;
+
+    #[test]
+    fn test_cron_macros() {
+        let provider = CronProvider::new();
+        let macros = values_for(&provider, "@").unwrap();
+        assert_eq!(macros.len(), CRON_MACROS.len());
+        assert!(macros.contains(&"@reboot".to_string()));
+        assert_eq!(
+            values_for(&provider, "@d").unwrap(),
+            vec!["@daily".to_string()]
+        );
+        assert_eq!(
+            values_for(&provider, "crontab @h").unwrap(),
+            vec!["@hourly".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_time_field_hints() {
+        let provider = CronProvider::new();
+        assert_eq!(
+            values_for(&provider, "*/").unwrap(),
+            vec!["*/5".to_string(), "*/15".to_string()]
+        );
+        assert_eq!(
+            values_for(&provider, "*/5 * * * mon").unwrap(),
+            vec!["mon-fri".to_string()]
+        );
+        assert_eq!(
+            values_for(&provider, "0 9-17 ").unwrap().len(),
+            FIELD_HINTS[2].len()
+        );
+        // The command after the five fields, and ordinary lines, are left alone
+        assert_eq!(values_for(&provider, "0 0 * * * ba"), None);
+        assert_eq!(values_for(&provider, "ls "), None);
+        assert_eq!(values_for(&provider, ""), None);
+    }
+
+    #[test]
+    fn test_commands_with_digits_are_not_entries() {
+        let provider = CronProvider::new();
+        assert_eq!(values_for(&provider, "python3 "), None);
+        assert_eq!(values_for(&provider, "base64 "), None);
+        assert_eq!(values_for(&provider, "sha256sum "), None);
+        assert_eq!(values_for(&provider, "0 9 jan "), None);
+        assert_eq!(
+            values_for(&provider, "0 9 1 jan ").unwrap().len(),
+            FIELD_HINTS[4].len()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use log::debug;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files, process,
+};
+
+const CONTAINER_TOOLS: &[&str] = &["docker", "podman"];
+
+/// Global options that consume the following word
+const GLOBAL_VALUE_FLAGS: &[&str] = &[
+    "-c",
+    "--context",
+    "-H",
+    "--host",
+    "--config",
+    "-l",
+    "--log-level",
+];
+
+const VOLUME_FLAGS: &[&str] = &["-v", "--volume"];
+const NETWORK_FLAGS: &[&str] = &["--network", "--net"];
+
+/// Subcommands of `volume` whose arguments are volume names
+const VOLUME_VERBS: &[&str] = &["inspect", "rm", "remove"];
+/// Subcommands of `network` whose arguments are network names
+const NETWORK_VERBS: &[&str] = &["inspect", "rm", "remove"];
+
+/// Named objects the daemon can list
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DockerObject {
+    Volume,
+    Network,
+}
+
+impl DockerObject {
+    fn list_args(&self) -> &'static [&'static str] {
+        match self {
+            DockerObject::Volume => &["volume", "ls", "-q"],
+            DockerObject::Network => &["network", "ls", "--format", "{{.Name}}"],
+        }
+    }
+}
+
+/// Completes volume and network names for `docker`/`podman`
+pub struct DockerProvider {
+    timeout: Duration,
+}
+
+impl Default for DockerProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DockerProvider {
+    pub fn new() -> Self {
+        Self {
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for DockerProvider {
+    fn name(&self) -> &'static str {
+        "docker"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Docker
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0 && CONTAINER_TOOLS.contains(&ctx.command.as_str())
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some((object, kept, typed)) = object_at(ctx) else {
+            return Ok(None);
+        };
+        // A stopped daemon yields no names rather than an error
+        let Some(output) = run_tool(&ctx.command, object.list_args(), self.timeout) else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            parse_names(&output)
+                .into_iter()
+                .filter(|name| name.starts_with(typed))
+                .map(|name| {
+                    CompletionEntry::new(format!("{}{}", kept, name), ProviderKind::Docker)
+                        .with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// The object named at the cursor, the text kept before the name (e.g.
+/// `--network=`) and the typed part of the name
+pub fn object_at(ctx: &CompletionContext) -> Option<(DockerObject, &str, &str)> {
+    let word = ctx.current_word.as_str();
+    if let Some((flag, value)) = word.split_once('=') {
+        let kept = &word[..flag.len() + 1];
+        if VOLUME_FLAGS.contains(&flag) && !value.contains(':') {
+            return Some((DockerObject::Volume, kept, value));
+        }
+        if NETWORK_FLAGS.contains(&flag) {
+            return Some((DockerObject::Network, kept, value));
+        }
+    }
+    if word.starts_with('-') {
+        return None;
+    }
+
+    match ctx.previous_word.as_deref() {
+        // Only the source part of `-v name:/path` is a volume
+        Some(flag) if VOLUME_FLAGS.contains(&flag) && !word.contains(':') => {
+            return Some((DockerObject::Volume, "", word));
+        }
+        Some(flag) if NETWORK_FLAGS.contains(&flag) => {
+            return Some((DockerObject::Network, "", word));
+        }
+        _ => {}
+    }
+
+    let sub = subcommand_index(&ctx.words)?;
+    let verb = positional_after(&ctx.words, sub, ctx.current_word_idx)?;
+    let args_before = ctx.words[verb + 1..ctx.current_word_idx]
+        .iter()
+        .filter(|w| !w.starts_with('-'))
+        .count();
+    let object = match (ctx.words[sub].as_str(), ctx.words[verb].as_str()) {
+        ("volume", v) if VOLUME_VERBS.contains(&v) => DockerObject::Volume,
+        // `network connect <network> <container>`: only the first argument
+        ("network", "connect" | "disconnect") if args_before == 0 => DockerObject::Network,
+        ("network", v) if NETWORK_VERBS.contains(&v) => DockerObject::Network,
+        _ => return None,
+    };
+    Some((object, "", word))
+}
+
+/// Index of the management command, skipping global options
+fn subcommand_index(words: &[String]) -> Option<usize> {
+    let mut idx = 1;
+    while let Some(word) = words.get(idx) {
+        match word.as_str() {
+            w if GLOBAL_VALUE_FLAGS.contains(&w) => idx += 2,
+            w if w.starts_with('-') => idx += 1,
+            _ => return Some(idx),
+        }
+    }
+    None
+}
+
+/// First non-option word after `start` and before the cursor
+fn positional_after(words: &[String], start: usize, cursor: usize) -> Option<usize> {
+    (start + 1..cursor).find(|&i| !words[i].starts_with('-'))
+}
+
+/// One name per line, as printed by `volume ls -q` / `network ls --format`
+pub fn parse_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// Run the container tool, returning `None` if it is missing or the daemon is unreachable
+fn run_tool(tool: &str, args: &[&str], timeout: Duration) -> Option<String> {
+    let mut command = Command::new(tool);
+    command.current_dir(files::working_dir()).args(args);
+    let Some(output) = process::run_with_timeout(&mut command, None, timeout) else {
+        debug!("[docker] {} {:?} failed to run or timed out", tool, args);
+        return None;
+    };
+    if !output.status.success() {
+        debug!(
+            "[docker] {} {:?} failed: {}",
+            tool,
+            args,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    String::from_utf8(output.stdout).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    fn object(line: &str) -> Option<(DockerObject, String, String)> {
+        let ctx = ctx_for(line);
+        object_at(&ctx).map(|(o, kept, typed)| (o, kept.to_string(), typed.to_string()))
+    }
+
+    #[test]
+    fn test_volume_positions() {
+        let volume =
+            |kept: &str, typed: &str| Some((DockerObject::Volume, kept.into(), typed.into()));
+        assert_eq!(object("docker run --volume da"), volume("", "da"));
+        assert_eq!(object("podman run -v "), volume("", ""));
+        assert_eq!(object("docker run --volume=da"), volume("--volume=", "da"));
+        assert_eq!(object("docker volume rm old "), volume("", ""));
+        assert_eq!(object("docker run -v data:/srv"), None);
+        assert_eq!(object("docker volume create "), None);
+
+        let volumes = "pgdata\n  cache \n\n";
+        assert_eq!(parse_names(volumes), vec!["pgdata", "cache"]);
+    }
+
+    #[test]
+    fn test_network_positions() {
+        let network =
+            |kept: &str, typed: &str| Some((DockerObject::Network, kept.into(), typed.into()));
+        assert_eq!(object("docker run --network "), network("", ""));
+        assert_eq!(object("docker run --net=br"), network("--net=", "br"));
+        assert_eq!(object("docker network connect fr"), network("", "fr"));
+        assert_eq!(
+            object("docker -H tcp://x network inspect "),
+            network("", "")
+        );
+        // The second argument of `network connect` is a container
+        assert_eq!(object("docker network connect frontend "), None);
+
+        let networks = "bridge\nhost\nnone\nfrontend\n";
+        assert_eq!(
+            parse_names(networks),
+            vec!["bridge", "host", "none", "frontend"]
+        );
+    }
+}
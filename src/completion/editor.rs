@@ -0,0 +1,145 @@
+use log::debug;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files,
+};
+
+const EDITORS: &[&str] = &["vim", "vi", "view", "gvim", "nvim"];
+
+/// Completes recently edited files (from `~/.viminfo`) for vim-like editors
+pub struct EditorRecentProvider {
+    viminfo: Option<PathBuf>,
+}
+
+impl Default for EditorRecentProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EditorRecentProvider {
+    pub fn new() -> Self {
+        Self {
+            viminfo: env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".viminfo")),
+        }
+    }
+
+    /// Read recent files from `path` instead of `~/.viminfo`
+    pub fn with_viminfo(mut self, path: Option<PathBuf>) -> Self {
+        if path.is_some() {
+            self.viminfo = path;
+        }
+        self
+    }
+}
+
+impl CompletionProvider for EditorRecentProvider {
+    fn name(&self) -> &'static str {
+        "editor_recent"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::EditorRecent
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0
+            && !ctx.current_word.starts_with('-')
+            && EDITORS.contains(&ctx.command.as_str())
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some(path) = &self.viminfo else {
+            return Ok(None);
+        };
+        let Ok(content) = fs::read(path) else {
+            debug!("[editor] cannot read {}", path.display());
+            return Ok(None);
+        };
+
+        Ok(Some(
+            parse_viminfo_files(&String::from_utf8_lossy(&content))
+                .into_iter()
+                .filter(|file| file.starts_with(&ctx.current_word))
+                .filter(|file| exists(file))
+                .map(|file| {
+                    CompletionEntry::new(file, ProviderKind::EditorRecent)
+                        .with_type(CandidateType::File)
+                        .with_description("recent".to_string())
+                })
+                .collect(),
+        ))
+    }
+}
+
+fn exists(file: &str) -> bool {
+    files::resolve_path(&shellexpand::tilde(file)).is_file()
+}
+
+/// Files of the `> path` file-mark entries of a viminfo file, most recent first
+pub fn parse_viminfo_files(content: &str) -> Vec<String> {
+    let mut seen = Vec::new();
+    for line in content.lines() {
+        if let Some(file) = line.strip_prefix('>') {
+            let file = file.trim();
+            if !file.is_empty() && !seen.iter().any(|f| f == file) {
+                seen.push(file.to_string());
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_recent_files_from_viminfo() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = dir.path().join("notes.md");
+        let main = dir.path().join("main.rs");
+        fs::write(&notes, "").unwrap();
+        fs::write(&main, "").unwrap();
+
+        let viminfo = dir.path().join("viminfo");
+        fs::write(
+            &viminfo,
+            format!(
+                "# File marks:\n'0  12  0  {notes}\n\n# History of marks within files (newest to oldest):\n\n\
+                 > {notes}\n\t\"\t12\t0\n\n> {gone}\n\t\"\t1\t0\n\n> {main}\n\t^\t3\t4\n",
+                notes = notes.display(),
+                main = main.display(),
+                gone = dir.path().join("deleted.txt").display(),
+            ),
+        )
+        .unwrap();
+
+        let ctx = ctx_for("vim ");
+        let provider = EditorRecentProvider::new().with_viminfo(Some(viminfo));
+        assert!(provider.should_try(&ctx));
+
+        let entries = provider.try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec![notes.to_string_lossy(), main.to_string_lossy()]
+        );
+    }
+
+    #[test]
+    fn test_parse_viminfo_files() {
+        let content = "> ~/a.txt\n\t\"\t1\t0\n> /tmp/b\n> ~/a.txt\n";
+        assert_eq!(parse_viminfo_files(content), vec!["~/a.txt", "/tmp/b"]);
+    }
+}
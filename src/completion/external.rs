@@ -0,0 +1,160 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CompletionContext, CompletionEntry, CompletionError, CompletionProvider, ProviderKind, files,
+    process,
+};
+
+#[derive(Debug, Serialize)]
+struct ExternalRequest<'a> {
+    command: &'a str,
+    words: &'a [String],
+    current_word: &'a str,
+    cword: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ExternalCandidate {
+    value: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+/// Runs a user command that reads the context as JSON on stdin and prints a
+/// JSON array of `{ value, description }` candidates
+pub struct ExternalProvider {
+    cmd: String,
+    timeout: Duration,
+}
+
+impl ExternalProvider {
+    pub fn new(cmd: impl Into<String>) -> Self {
+        Self {
+            cmd: cmd.into(),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for ExternalProvider {
+    fn name(&self) -> &'static str {
+        "external"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::External
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let request = ExternalRequest {
+            command: &ctx.command,
+            words: &ctx.words,
+            current_word: &ctx.current_word,
+            cword: ctx.current_word_idx,
+        };
+        let input =
+            serde_json::to_vec(&request).map_err(|e| CompletionError::Other(e.to_string()))?;
+
+        let mut command = Command::new("sh");
+        command
+            .current_dir(files::working_dir())
+            .arg("-c")
+            .arg(&self.cmd);
+        let Some(output) = process::run_with_timeout(&mut command, Some(&input), self.timeout)
+        else {
+            return Ok(None);
+        };
+        if !output.status.success() {
+            debug!("[external] '{}' exited with {}", self.cmd, output.status);
+            return Ok(None);
+        }
+
+        let candidates: Vec<ExternalCandidate> = match serde_json::from_slice(&output.stdout) {
+            Ok(c) => c,
+            Err(e) => {
+                debug!("[external] invalid output from '{}': {}", self.cmd, e);
+                return Ok(None);
+            }
+        };
+        Ok(Some(
+            candidates
+                .into_iter()
+                .map(|c| {
+                    let entry = CompletionEntry::new(c.value, ProviderKind::External);
+                    match c.description {
+                        Some(description) => entry.with_description(description),
+                        None => entry,
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn test_stub_script_protocol() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("complete-deploy");
+        // Answers only when it was given the expected context
+        fs::write(
+            &script,
+            r#"input=$(cat)
+case "$input" in
+  *'"command":"deploy"'*'"current_word":"st"'*'"cword":1'*)
+    echo '[{"value":"staging","description":"eu-west-1"},{"value":"stable"}]' ;;
+  *) exit 1 ;;
+esac
+"#,
+        )
+        .unwrap();
+
+        let provider = ExternalProvider::new(format!("sh {}", script.display()));
+        let entries = provider
+            .try_complete(&ctx_for("deploy st"))
+            .unwrap()
+            .unwrap();
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["staging", "stable"]);
+        assert_eq!(entries[0].description.as_deref(), Some("eu-west-1"));
+
+        // Non-zero exit
+        assert!(provider.try_complete(&ctx_for("other ")).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_timeout_and_invalid_output() {
+        let slow = ExternalProvider::new("sleep 5").with_timeout(Duration::from_millis(50));
+        let started = Instant::now();
+        assert!(slow.try_complete(&ctx_for("x ")).unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // The same command finishes within a longer timeout
+        let answer = "sleep 0.2; echo '[{\"value\":\"late\"}]'";
+        let short = ExternalProvider::new(answer).with_timeout(Duration::from_millis(20));
+        let long = ExternalProvider::new(answer).with_timeout(Duration::from_secs(5));
+        assert!(short.try_complete(&ctx_for("x ")).unwrap().is_none());
+        let entries = long.try_complete(&ctx_for("x ")).unwrap().unwrap();
+        assert_eq!(entries[0].value, "late");
+
+        let garbage = ExternalProvider::new("echo not json");
+        assert!(garbage.try_complete(&ctx_for("x ")).unwrap().is_none());
+    }
+}
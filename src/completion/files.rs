@@ -0,0 +1,372 @@
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::completion::cache;
+
+const LISTINGS_FILE: &str = "listings.json";
+/// How long a listing is reused by later invocations (while its mtime holds)
+pub const DIR_LISTING_TTL: Duration = Duration::from_secs(5);
+/// Directories kept in the on-disk store, most recently read first
+const MAX_STORED_LISTINGS: usize = 32;
+
+/// Directory the shell was in when it invoked bft, set by the init script
+pub const ENV_PWD: &str = "BFT_PWD";
+
+/// Directory relative paths are completed against: `$BFT_PWD`, falling back
+/// to the process working directory
+pub fn working_dir() -> PathBuf {
+    resolve_working_dir(env::var(ENV_PWD).ok())
+}
+
+fn resolve_working_dir(pwd: Option<String>) -> PathBuf {
+    match pwd {
+        Some(pwd) if Path::new(&pwd).is_dir() => PathBuf::from(pwd),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Resolve `path` against the working directory unless it is absolute
+pub fn resolve_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        working_dir().join(path)
+    }
+}
+
+thread_local! {
+    static CACHE: RefCell<DirListingCache> = RefCell::new(DirListingCache::new());
+}
+
+/// Share the listings of `complete_paths` with later invocations through the
+/// cache directory (`share_dir_listings`)
+pub fn share_listings() {
+    if let Some(dir) = cache::default_cache_dir() {
+        CACHE.set(DirListingCache::new().with_store(dir.join(LISTINGS_FILE), DIR_LISTING_TTL));
+    }
+}
+
+/// A directory's entries (name and whether it is a directory) as of its mtime
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Listing {
+    mtime_ms: u128,
+    read_at_ms: u128,
+    entries: Vec<(String, bool)>,
+}
+
+/// Directory listings keyed by path, reused while the directory's mtime is
+/// unchanged. With a store they are also shared with the invocations (one
+/// per Tab press) that follow within the TTL.
+#[derive(Default)]
+pub struct DirListingCache {
+    listings: RefCell<HashMap<String, Listing>>,
+    store: Option<(PathBuf, Duration)>,
+    loaded: Cell<bool>,
+    reads: Cell<usize>,
+}
+
+impl DirListingCache {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Share listings younger than `ttl` through `file`
+    pub fn with_store(mut self, file: PathBuf, ttl: Duration) -> Self {
+        self.store = Some((file, ttl));
+        self
+    }
+
+    /// Number of directories actually read from disk
+    pub fn reads(&self) -> usize {
+        self.reads.get()
+    }
+
+    fn list(&self, dir: &Path) -> io::Result<Vec<(String, bool)>> {
+        let mtime_ms = millis_since_epoch(fs::metadata(dir)?.modified()?);
+        let now_ms = millis_since_epoch(SystemTime::now());
+        let key = dir.to_string_lossy().into_owned();
+        self.load();
+        if let Some(listing) = self.listings.borrow().get(&key)
+            && listing.mtime_ms == mtime_ms
+            && self.is_fresh(listing, now_ms)
+        {
+            return Ok(listing.entries.clone());
+        }
+
+        debug!("[files] reading {}", dir.display());
+        self.reads.set(self.reads.get() + 1);
+        let mut entries: Vec<(String, bool)> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| {
+                let is_dir = entry.path().is_dir();
+                (entry.file_name().to_string_lossy().into_owned(), is_dir)
+            })
+            .collect();
+        entries.sort();
+
+        self.listings.borrow_mut().insert(
+            key,
+            Listing {
+                mtime_ms,
+                read_at_ms: now_ms,
+                entries: entries.clone(),
+            },
+        );
+        self.save(now_ms);
+        Ok(entries)
+    }
+
+    /// Listings of this invocation never expire; stored ones last the TTL
+    fn is_fresh(&self, listing: &Listing, now_ms: u128) -> bool {
+        match &self.store {
+            Some((_, ttl)) => now_ms.saturating_sub(listing.read_at_ms) < ttl.as_millis(),
+            None => true,
+        }
+    }
+
+    /// Read the stored listings once per invocation
+    fn load(&self) {
+        if self.loaded.replace(true) {
+            return;
+        }
+        let Some((file, _)) = &self.store else {
+            return;
+        };
+        let Ok(content) = fs::read_to_string(file) else {
+            return;
+        };
+        match serde_json::from_str::<HashMap<String, Listing>>(&content) {
+            Ok(stored) => self.listings.borrow_mut().extend(stored),
+            Err(e) => debug!("[files] ignoring unreadable {}: {}", file.display(), e),
+        }
+    }
+
+    /// Write the fresh listings, most recent first, through a temp file and
+    /// a rename so a concurrent invocation never reads half a file
+    fn save(&self, now_ms: u128) {
+        let Some((file, _)) = &self.store else {
+            return;
+        };
+        let mut fresh: Vec<(String, Listing)> = self
+            .listings
+            .borrow()
+            .iter()
+            .filter(|(_, listing)| self.is_fresh(listing, now_ms))
+            .map(|(dir, listing)| (dir.clone(), listing.clone()))
+            .collect();
+        fresh.sort_by_key(|(_, listing)| Reverse(listing.read_at_ms));
+        fresh.truncate(MAX_STORED_LISTINGS);
+        let stored: HashMap<String, Listing> = fresh.into_iter().collect();
+
+        let content = match serde_json::to_string(&stored) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("[files] cannot serialize listings: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = write_atomically(file, &content) {
+            debug!("[files] cannot write {}: {}", file.display(), e);
+        }
+    }
+
+    /// Paths starting with `word`, like `compgen -f` (or `compgen -d` with `dirs_only`)
+    pub fn complete(&self, word: &str, dirs_only: bool) -> Vec<String> {
+        self.complete_in(&working_dir(), word, dirs_only)
+    }
+
+    /// Like `complete`, with relative words resolved against `base`
+    pub fn complete_in(&self, base: &Path, word: &str, dirs_only: bool) -> Vec<String> {
+        let (dir_part, name_prefix) = match word.rfind('/') {
+            Some(idx) => word.split_at(idx + 1),
+            None => ("", word),
+        };
+        let dir = base.join(shellexpand::tilde(dir_part).as_ref());
+
+        let listing = match self.list(&dir) {
+            Ok(listing) => listing,
+            Err(e) => {
+                debug!("[files] cannot list {}: {}", dir.display(), e);
+                return Vec::new();
+            }
+        };
+
+        listing
+            .iter()
+            .filter(|(name, is_dir)| name.starts_with(name_prefix) && (*is_dir || !dirs_only))
+            .map(|(name, _)| format!("{}{}", dir_part, name))
+            .collect()
+    }
+}
+
+/// Write `content` to `file` through a temporary file renamed over it, so a
+/// concurrent reader never sees it half written
+pub fn write_atomically(file: &Path, content: &str) -> io::Result<()> {
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp = file.with_extension(format!("tmp.{}", process::id()));
+    fs::write(&temp, content)?;
+    fs::rename(&temp, file)
+}
+
+fn millis_since_epoch(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
+
+/// Complete paths for `word` through the listing cache
+pub fn complete_paths(word: &str, dirs_only: bool) -> Vec<String> {
+    CACHE.with_borrow(|cache| cache.complete(word, dirs_only))
+}
+
+/// Complete a relative `word` in each directory of a `PATH`-like list
+/// (`a:b:c`), like `complete_paths` run from each of them
+pub fn complete_in_search_path(search_path: &str, word: &str) -> Vec<String> {
+    if word.starts_with('/') || word.starts_with('~') {
+        return Vec::new();
+    }
+    CACHE.with_borrow(|cache| {
+        env::split_paths(search_path)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .flat_map(|dir| cache.complete_in(&dir, word, false))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_listing_read_once_per_directory() {
+        let dir = tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir(&src).unwrap();
+        fs::write(src.join("foo.rs"), "").unwrap();
+        fs::write(src.join("bar.rs"), "").unwrap();
+        fs::create_dir(src.join("baz")).unwrap();
+
+        let cache = DirListingCache::new();
+        let base = format!("{}/", src.display());
+
+        assert_eq!(
+            cache.complete(&format!("{}f", base), false),
+            vec![format!("{}foo.rs", base)]
+        );
+        assert_eq!(
+            cache.complete(&format!("{}ba", base), false),
+            vec![format!("{}bar.rs", base), format!("{}baz", base)]
+        );
+        assert_eq!(
+            cache.complete(&format!("{}ba", base), true),
+            vec![format!("{}baz", base)]
+        );
+        assert_eq!(cache.reads(), 1);
+    }
+
+    #[test]
+    fn test_listing_shared_between_invocations() {
+        let dir = tempdir().unwrap();
+        let store = tempdir().unwrap();
+        let file = store.path().join(LISTINGS_FILE);
+        fs::write(dir.path().join("foo.rs"), "").unwrap();
+        fs::write(dir.path().join("bar.rs"), "").unwrap();
+        let base = format!("{}/", dir.path().display());
+
+        // Each Tab press is a new process with its own cache
+        let first = DirListingCache::new().with_store(file.clone(), Duration::from_secs(60));
+        assert_eq!(
+            first.complete(&format!("{}f", base), false),
+            vec![format!("{}foo.rs", base)]
+        );
+        assert_eq!(first.reads(), 1);
+
+        let second = DirListingCache::new().with_store(file.clone(), Duration::from_secs(60));
+        assert_eq!(
+            second.complete(&format!("{}b", base), false),
+            vec![format!("{}bar.rs", base)]
+        );
+        assert_eq!(second.reads(), 0);
+
+        // Expired listings are read again
+        let expired = DirListingCache::new().with_store(file, Duration::ZERO);
+        expired.complete(&base, false);
+        assert_eq!(expired.reads(), 1);
+    }
+
+    #[test]
+    fn test_complete_in_search_path() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        fs::create_dir(first.path().join("mylib")).unwrap();
+        fs::write(first.path().join("mylib/core.py"), "").unwrap();
+        fs::write(second.path().join("mytool.py"), "").unwrap();
+        let search_path = format!("{}::{}", first.path().display(), second.path().display());
+
+        assert_eq!(
+            complete_in_search_path(&search_path, "my"),
+            vec!["mylib", "mytool.py"]
+        );
+        assert_eq!(
+            complete_in_search_path(&search_path, "mylib/c"),
+            vec!["mylib/core.py"]
+        );
+        assert!(complete_in_search_path(&search_path, "/my").is_empty());
+    }
+
+    #[test]
+    fn test_bft_pwd_redirects_completion() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("elsewhere.txt"), "").unwrap();
+        let pwd = dir.path().display().to_string();
+
+        let base = resolve_working_dir(Some(pwd.clone()));
+        assert_eq!(base, dir.path());
+        let cache = DirListingCache::new();
+        assert_eq!(
+            cache.complete_in(&base, "else", false),
+            vec!["elsewhere.txt"]
+        );
+
+        assert_eq!(
+            resolve_working_dir(Some(format!("{}/missing", pwd))),
+            PathBuf::from(".")
+        );
+        assert_eq!(resolve_working_dir(None), PathBuf::from("."));
+    }
+
+    #[test]
+    fn test_listing_invalidated_on_mtime_change() {
+        let dir = tempdir().unwrap();
+        let cache = DirListingCache::new();
+        let base = format!("{}/", dir.path().display());
+
+        assert!(cache.complete(&base, false).is_empty());
+
+        fs::write(dir.path().join("new.txt"), "").unwrap();
+        let mtime = SystemTime::now() + std::time::Duration::from_secs(5);
+        fs::File::open(dir.path())
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        assert_eq!(
+            cache.complete(&base, false),
+            vec![format!("{}new.txt", base)]
+        );
+        assert_eq!(cache.reads(), 2);
+    }
+}
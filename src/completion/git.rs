@@ -0,0 +1,849 @@
+use log::debug;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files, process,
+};
+
+const GIT: &str = "git";
+
+/// Porcelain subcommands offered alongside user-defined aliases
+const SUBCOMMANDS: &[&str] = &[
+    "add",
+    "bisect",
+    "blame",
+    "branch",
+    "checkout",
+    "cherry-pick",
+    "clean",
+    "clone",
+    "commit",
+    "config",
+    "diff",
+    "fetch",
+    "grep",
+    "init",
+    "log",
+    "merge",
+    "mv",
+    "pull",
+    "push",
+    "rebase",
+    "reflog",
+    "remote",
+    "reset",
+    "restore",
+    "revert",
+    "rm",
+    "show",
+    "stash",
+    "status",
+    "submodule",
+    "switch",
+    "tag",
+    "worktree",
+];
+
+const BOOL: &[&str] = &["true", "false"];
+const MERGE_TOOLS: &[&str] = &[
+    "bc", "emerge", "kdiff3", "meld", "nvimdiff", "opendiff", "p4merge", "vimdiff",
+];
+
+/// Known `git config` keys (lowercase) and their enumerated values
+const CONFIG_VALUES: &[(&str, &[&str])] = &[
+    ("color.ui", &["auto", "always", "never"]),
+    ("color.diff", &["auto", "always", "never"]),
+    ("color.status", &["auto", "always", "never"]),
+    ("commit.gpgsign", BOOL),
+    ("core.autocrlf", &["true", "false", "input"]),
+    ("core.ignorecase", BOOL),
+    ("diff.tool", MERGE_TOOLS),
+    ("fetch.prune", BOOL),
+    ("init.defaultbranch", &["main", "master"]),
+    ("merge.conflictstyle", &["merge", "diff3", "zdiff3"]),
+    ("merge.ff", &["true", "false", "only"]),
+    ("merge.tool", MERGE_TOOLS),
+    ("pull.ff", &["true", "false", "only"]),
+    ("pull.rebase", &["true", "false", "merges", "interactive"]),
+    ("push.autosetupremote", BOOL),
+    (
+        "push.default",
+        &["nothing", "current", "upstream", "simple", "matching"],
+    ),
+    ("rebase.autosquash", BOOL),
+    ("rebase.autostash", BOOL),
+    ("rerere.enabled", BOOL),
+];
+
+/// Options of `git config` that take a value
+const CONFIG_VALUE_FLAGS: &[&str] = &["-f", "--file", "--blob", "--type", "--default"];
+
+/// Merge strategies accepted by `-s`/`--strategy`
+const MERGE_STRATEGIES: &[(&str, &str)] = &[
+    ("ort", "default for two heads"),
+    ("recursive", "older default, now an alias of ort"),
+    ("resolve", "three-way merge with one common ancestor"),
+    ("octopus", "default for more than two heads"),
+    ("ours", "keep our tree, ignore the other heads"),
+    ("subtree", "ort adjusted for a subtree"),
+];
+/// Subcommands whose `-s` is the merge strategy (`restore -s` is a ref)
+const STRATEGY_SUBCOMMANDS: &[&str] = &["cherry-pick", "merge", "pull", "rebase", "revert"];
+/// Options taking a ref as their value
+const REF_VALUE_FLAGS: &[&str] = &["--onto", "--fork-point"];
+
+/// What kind of value an option at the cursor expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagValue {
+    Strategy,
+    Ref,
+}
+
+/// Git-aware completions that complement the bash/carapace git completers
+pub struct GitProvider {
+    timeout: Duration,
+}
+
+impl Default for GitProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GitProvider {
+    pub fn new() -> Self {
+        Self {
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Revisions the shorthand can expand to, with a description each
+    fn shorthand_candidates(&self, shorthand: &RefShorthand) -> Vec<(String, Option<String>)> {
+        match shorthand {
+            RefShorthand::Ancestor { base, op: '^' } => {
+                let parents = run_git(&["log", "-n", "1", "--format=%P", base], self.timeout)
+                    .unwrap_or_default();
+                parent_candidates(base, &parents)
+            }
+            RefShorthand::Ancestor { base, .. } => {
+                let count = (MAX_ANCESTORS + 1).to_string();
+                let subjects = run_git(
+                    &["log", "--first-parent", "-n", &count, "--format=%s", base],
+                    self.timeout,
+                )
+                .unwrap_or_default();
+                ancestor_candidates(base, &subjects)
+            }
+            RefShorthand::Reflog { base } => {
+                let reflog = if base.is_empty() {
+                    run_git(&["reflog", "-n", "200", "--format=%gs"], self.timeout)
+                        .unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                reflog_candidates(base, &previous_branches(&reflog))
+            }
+        }
+    }
+
+    fn complete_subcommand(&self, ctx: &CompletionContext) -> Option<Vec<CompletionEntry>> {
+        let aliases = parse_aliases(&run_git(
+            &["config", "--get-regexp", r"^alias\."],
+            self.timeout,
+        )?);
+
+        let mut entries: Vec<CompletionEntry> = aliases
+            .into_iter()
+            .map(|(name, expansion)| {
+                CompletionEntry::new(name, ProviderKind::Git)
+                    .with_type(CandidateType::Command)
+                    .with_description(expansion)
+            })
+            .collect();
+        entries.extend(SUBCOMMANDS.iter().map(|s| {
+            CompletionEntry::new(s.to_string(), ProviderKind::Git).with_type(CandidateType::Command)
+        }));
+
+        Some(
+            entries
+                .into_iter()
+                .filter(|e| e.value.starts_with(&ctx.current_word))
+                .collect(),
+        )
+    }
+}
+
+impl CompletionProvider for GitProvider {
+    fn name(&self) -> &'static str {
+        "git"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Git
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == GIT && ctx.current_word_idx > 0
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        if subcommand_index(&ctx.words).is_none_or(|idx| idx == ctx.current_word_idx)
+            && !ctx.current_word.starts_with('-')
+        {
+            return Ok(self.complete_subcommand(ctx));
+        }
+
+        if let Some(reference) = pathspec_ref(ctx) {
+            let Some(output) = run_git(&["ls-tree", "-r", "--name-only", reference], self.timeout)
+            else {
+                return Ok(None);
+            };
+            return Ok(Some(
+                parse_ls_tree(&output, &ctx.current_word)
+                    .into_iter()
+                    .map(|path| {
+                        CompletionEntry::new(path, ProviderKind::Git).with_type(CandidateType::File)
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(shorthand) = RefShorthand::parse(&ctx.current_word) {
+            return Ok(Some(
+                self.shorthand_candidates(&shorthand)
+                    .into_iter()
+                    .filter(|(value, _)| value.starts_with(&ctx.current_word))
+                    .map(|(value, description)| {
+                        let entry = CompletionEntry::new(value, ProviderKind::Git)
+                            .with_type(CandidateType::Value);
+                        match description {
+                            Some(description) => entry.with_description(description),
+                            None => entry,
+                        }
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some((value, kept, typed)) = flag_value(ctx) {
+            let candidates: Vec<(String, Option<String>)> = match value {
+                FlagValue::Strategy => MERGE_STRATEGIES
+                    .iter()
+                    .map(|(name, hint)| (name.to_string(), Some(hint.to_string())))
+                    .collect(),
+                FlagValue::Ref => {
+                    let Some(output) = run_git(
+                        &[
+                            "for-each-ref",
+                            "--format=%(refname:short)%09%(symref)",
+                            "refs/heads",
+                            "refs/remotes",
+                            "refs/tags",
+                        ],
+                        self.timeout,
+                    ) else {
+                        return Ok(None);
+                    };
+                    parse_refs(&output).into_iter().map(|r| (r, None)).collect()
+                }
+            };
+            return Ok(Some(
+                candidates
+                    .into_iter()
+                    .filter(|(candidate, _)| candidate.starts_with(typed))
+                    .map(|(candidate, description)| {
+                        let entry = CompletionEntry::new(
+                            format!("{}{}", kept, candidate),
+                            ProviderKind::Git,
+                        )
+                        .with_type(CandidateType::Value);
+                        match description {
+                            Some(description) => entry.with_description(description),
+                            None => entry,
+                        }
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(key) = config_key(ctx) {
+            // Values already set in any scope (including includeIf'd files)
+            let stored = run_git(&["config", "--get-all", key], self.timeout).unwrap_or_default();
+            let values = merge_config_values(config_values(ctx).unwrap_or_default(), &stored);
+            if values.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(
+                values
+                    .into_iter()
+                    .filter(|v| v.starts_with(&ctx.current_word))
+                    .map(|v| {
+                        CompletionEntry::new(v, ProviderKind::Git).with_type(CandidateType::Value)
+                    })
+                    .collect(),
+            ));
+        }
+
+        Ok(None)
+    }
+}
+
+/// Ancestors offered for `<ref>~`
+const MAX_ANCESTORS: usize = 10;
+/// `@{-N}` previous branches offered for `@{`
+const MAX_PREVIOUS_BRANCHES: usize = 5;
+
+/// A revision written with a suffix operator, e.g. `HEAD~2`, `main^` or `@{-1}`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefShorthand {
+    /// `<base>~N` (first-parent ancestors) or `<base>^N` (parents)
+    Ancestor { base: String, op: char },
+    /// `<base>@{...}`: upstream/push, or a previous branch when `base` is empty
+    Reflog { base: String },
+}
+
+impl RefShorthand {
+    /// Recognize the shorthand being typed; plain names and malformed
+    /// suffixes (`HEAD~x`) are left to the ordinary ref completion
+    pub fn parse(word: &str) -> Option<Self> {
+        if word.starts_with('-') {
+            return None;
+        }
+        let idx = word.find(['~', '^', '@'])?;
+        let (base, suffix) = word.split_at(idx);
+        if suffix.starts_with("@{") {
+            return Some(RefShorthand::Reflog {
+                base: base.to_string(),
+            });
+        }
+        let op = suffix.chars().next()?;
+        if base.is_empty() || op == '@' || !suffix[1..].chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        Some(RefShorthand::Ancestor {
+            base: base.to_string(),
+            op,
+        })
+    }
+}
+
+/// `<base>~N` for each ancestor in `git log --first-parent --format=%s` output,
+/// described by its subject (the first line is `base` itself)
+pub fn ancestor_candidates(base: &str, subjects: &str) -> Vec<(String, Option<String>)> {
+    subjects
+        .lines()
+        .enumerate()
+        .skip(1)
+        .map(|(n, subject)| (format!("{}~{}", base, n), Some(subject.to_string())))
+        .collect()
+}
+
+/// `<base>^N` for each parent hash in `git log --format=%P` output
+pub fn parent_candidates(base: &str, parents: &str) -> Vec<(String, Option<String>)> {
+    parents
+        .split_whitespace()
+        .enumerate()
+        .map(|(i, hash)| {
+            let short = &hash[..hash.len().min(7)];
+            (format!("{}^{}", base, i + 1), Some(short.to_string()))
+        })
+        .collect()
+}
+
+/// Branches left by the most recent checkouts, newest first, from
+/// `git reflog --format=%gs` output; `@{-N}` is the Nth entry
+pub fn previous_branches(reflog: &str) -> Vec<String> {
+    reflog
+        .lines()
+        .filter_map(|line| line.strip_prefix("checkout: moving from "))
+        .filter_map(|moved| moved.split_once(" to ").map(|(from, _)| from.to_string()))
+        .take(MAX_PREVIOUS_BRANCHES)
+        .collect()
+}
+
+/// `@{upstream}`/`@{push}` of `base`, and `@{-N}` for a bare `@{`
+pub fn reflog_candidates(base: &str, previous: &[String]) -> Vec<(String, Option<String>)> {
+    let mut candidates: Vec<(String, Option<String>)> = ["upstream", "push"]
+        .iter()
+        .map(|name| (format!("{}@{{{}}}", base, name), None))
+        .collect();
+    if base.is_empty() {
+        candidates.extend(
+            previous
+                .iter()
+                .enumerate()
+                .map(|(i, branch)| (format!("@{{-{}}}", i + 1), Some(branch.clone()))),
+        );
+    }
+    candidates
+}
+
+/// Index of the git subcommand, skipping global options (and the values of `-C`/`-c`)
+pub fn subcommand_index(words: &[String]) -> Option<usize> {
+    let mut idx = 1;
+    while let Some(word) = words.get(idx) {
+        match word.as_str() {
+            "-C" | "-c" | "--git-dir" | "--work-tree" | "--namespace" => idx += 2,
+            w if w.starts_with('-') => idx += 1,
+            _ => return Some(idx),
+        }
+    }
+    None
+}
+
+/// The value expected at the cursor after `-s`/`--strategy` or `--onto`/`--fork-point`,
+/// the text kept before it (e.g. `--strategy=`) and the typed part of the value
+pub fn flag_value(ctx: &CompletionContext) -> Option<(FlagValue, &str, &str)> {
+    let sub = subcommand_index(&ctx.words)?;
+    if sub >= ctx.current_word_idx {
+        return None;
+    }
+    let takes_strategy = STRATEGY_SUBCOMMANDS.contains(&ctx.words[sub].as_str());
+    let kind = |flag: &str| match flag {
+        "-s" | "--strategy" if takes_strategy => Some(FlagValue::Strategy),
+        f if REF_VALUE_FLAGS.contains(&f) => Some(FlagValue::Ref),
+        _ => None,
+    };
+
+    let word = ctx.current_word.as_str();
+    if let Some((flag, value)) = word.split_once('=') {
+        let kept = &word[..flag.len() + 1];
+        return kind(flag).map(|k| (k, kept, value));
+    }
+    if word.starts_with('-') {
+        return None;
+    }
+    let previous = ctx.words.get(ctx.current_word_idx - 1)?;
+    kind(previous).map(|k| (k, "", word))
+}
+
+/// Ref names from `git for-each-ref --format=%(refname:short)%09%(symref)`
+/// output, without symbolic refs such as `origin/HEAD`
+pub fn parse_refs(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| match line.split_once('\t') {
+            Some((name, "")) => Some(name),
+            Some(_) => None,
+            None => Some(line),
+        })
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Enumerated values when the cursor is on the value of `git config <key> <value>`
+pub fn config_values(ctx: &CompletionContext) -> Option<&'static [&'static str]> {
+    let key = config_key(ctx)?;
+    CONFIG_VALUES
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, values)| *values)
+}
+
+/// The key when the cursor is on the value of `git config <key> <value>`
+pub fn config_key(ctx: &CompletionContext) -> Option<&str> {
+    let sub = subcommand_index(&ctx.words)?;
+    if ctx.words[sub] != "config" || ctx.current_word.starts_with('-') {
+        return None;
+    }
+
+    let mut positionals = Vec::new();
+    let mut idx = sub + 1;
+    while idx < ctx.current_word_idx {
+        let word = ctx.words[idx].as_str();
+        if CONFIG_VALUE_FLAGS.contains(&word) {
+            idx += 2;
+            continue;
+        }
+        if !word.starts_with('-') {
+            positionals.push(word);
+        }
+        idx += 1;
+    }
+    if idx > ctx.current_word_idx {
+        return None;
+    }
+
+    match positionals.as_slice() {
+        [key] => Some(key),
+        _ => None,
+    }
+}
+
+/// Enumerated values followed by the values stored in git config
+/// (`git config --get-all` output, one per line), without duplicates
+pub fn merge_config_values(known: &[&str], stored: &str) -> Vec<String> {
+    let mut values: Vec<String> = known.iter().map(|v| v.to_string()).collect();
+    for value in stored.lines().map(str::trim).filter(|v| !v.is_empty()) {
+        if !values.iter().any(|v| v == value) {
+            values.push(value.to_string());
+        }
+    }
+    values
+}
+
+/// The ref whose tree holds the paths after `--` in `git checkout <ref> -- <path>`
+/// or `git restore --source <ref> -- <path>`
+pub fn pathspec_ref(ctx: &CompletionContext) -> Option<&str> {
+    let sub = subcommand_index(&ctx.words)?;
+    let before = ctx.words.get(sub + 1..ctx.current_word_idx)?;
+    let separator = before.iter().position(|w| w == "--")?;
+    let options = &before[..separator];
+
+    match ctx.words[sub].as_str() {
+        "checkout" => options
+            .iter()
+            .rev()
+            .find(|w| !w.starts_with('-'))
+            .map(|w| w.as_str()),
+        "restore" => {
+            options
+                .iter()
+                .enumerate()
+                .find_map(|(i, w)| match w.strip_prefix("--source=") {
+                    Some(reference) => Some(reference),
+                    None if w == "-s" || w == "--source" => options.get(i + 1).map(|r| r.as_str()),
+                    None => None,
+                })
+        }
+        _ => None,
+    }
+}
+
+/// Paths from `git ls-tree -r --name-only` output that start with `prefix`
+pub fn parse_ls_tree(output: &str, prefix: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|path| !path.is_empty() && path.starts_with(prefix))
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Submodule and worktree directories of the repository at the working
+/// directory, labelled with what they are. Submodules are read from the
+/// `.gitmodules` of the top level and given relative to the working directory
+pub fn repo_directories(timeout: Duration) -> Vec<(String, &'static str)> {
+    let Some(output) = run_git(&["rev-parse", "--show-toplevel", "--show-prefix"], timeout) else {
+        return Vec::new();
+    };
+    let mut lines = output.lines();
+    let (Some(toplevel), prefix) = (lines.next(), lines.next().unwrap_or_default()) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<(String, &'static str)> = run_git(
+        &[
+            "-C",
+            toplevel,
+            "config",
+            "--file",
+            ".gitmodules",
+            "--get-regexp",
+            r"\.path$",
+        ],
+        timeout,
+    )
+    .map(|output| parse_submodule_paths(&output))
+    .unwrap_or_default()
+    .into_iter()
+    .map(|path| (relative_to_prefix(&path, prefix), "submodule"))
+    .collect();
+    if let Some(output) = run_git(&["worktree", "list", "--porcelain"], timeout) {
+        // The first entry is the main worktree, i.e. the repository itself
+        dirs.extend(
+            parse_worktree_paths(&output)
+                .into_iter()
+                .skip(1)
+                .map(|path| (path, "worktree")),
+        );
+    }
+    dirs
+}
+
+/// `path`, relative to the top level, made relative to the subdirectory
+/// `prefix` (as printed by `git rev-parse --show-prefix`, e.g. `src/`)
+pub fn relative_to_prefix(path: &str, prefix: &str) -> String {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.to_string(),
+        None => format!("{}{}", "../".repeat(prefix.matches('/').count()), path),
+    }
+}
+
+/// Paths from `git config --file .gitmodules --get-regexp '\.path$'` output
+pub fn parse_submodule_paths(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(_, path)| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .collect()
+}
+
+/// Paths from `git worktree list --porcelain` output, main worktree first
+pub fn parse_worktree_paths(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.strip_prefix("worktree "))
+        .map(|path| path.to_string())
+        .collect()
+}
+
+/// Run git and return its stdout, or `None` if git is missing or the command failed
+pub fn run_git(args: &[&str], timeout: Duration) -> Option<String> {
+    let mut command = Command::new(GIT);
+    command.current_dir(files::working_dir()).args(args);
+    let Some(output) = process::run_with_timeout(&mut command, None, timeout) else {
+        debug!("[git] git {:?} failed to run or timed out", args);
+        return None;
+    };
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8(output.stdout).ok()
+}
+
+/// Parse `git config --get-regexp '^alias\.'` output into (name, expansion) pairs
+pub fn parse_aliases(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, expansion) = line.split_once(' ').unwrap_or((line, ""));
+            let name = key.strip_prefix("alias.")?;
+            Some((name.to_string(), expansion.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{ctx_for, values_for};
+
+    fn words(ws: &[&str]) -> Vec<String> {
+        ws.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_aliases() {
+        let output = "alias.co checkout\nalias.lg log --graph --oneline\nalias.st status\n";
+        assert_eq!(
+            parse_aliases(output),
+            vec![
+                ("co".to_string(), "checkout".to_string()),
+                ("lg".to_string(), "log --graph --oneline".to_string()),
+                ("st".to_string(), "status".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subcommand_index() {
+        assert_eq!(subcommand_index(&words(&["git", "co"])), Some(1));
+        assert_eq!(
+            subcommand_index(&words(&["git", "-C", "repo", "--no-pager", "log"])),
+            Some(4)
+        );
+        assert_eq!(subcommand_index(&words(&["git", "--bare"])), None);
+    }
+
+    #[test]
+    fn test_paths_at_ref_after_separator() {
+        let ref_of = |line: &str| pathspec_ref(&ctx_for(line)).map(|r| r.to_string());
+        assert_eq!(ref_of("git checkout main -- src/"), Some("main".into()));
+        assert_eq!(ref_of("git checkout -f v1.2 -- "), Some("v1.2".into()));
+        assert_eq!(
+            ref_of("git restore --source=HEAD~2 -- "),
+            Some("HEAD~2".into())
+        );
+        assert_eq!(
+            ref_of("git restore -s dev --staged -- "),
+            Some("dev".into())
+        );
+        // Before the separator the word is still a ref
+        assert_eq!(ref_of("git checkout ma"), None);
+        assert_eq!(ref_of("git restore -- "), None);
+
+        let ls_tree = "Cargo.toml\nsrc/lib.rs\nsrc/main.rs\n";
+        assert_eq!(
+            parse_ls_tree(ls_tree, "src/m"),
+            vec!["src/main.rs".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_ref_shorthands() {
+        let ancestor = |base: &str, op| {
+            Some(RefShorthand::Ancestor {
+                base: base.to_string(),
+                op,
+            })
+        };
+        assert_eq!(RefShorthand::parse("HEAD~"), ancestor("HEAD", '~'));
+        assert_eq!(RefShorthand::parse("main^2"), ancestor("main", '^'));
+        assert_eq!(
+            RefShorthand::parse("@{"),
+            Some(RefShorthand::Reflog {
+                base: String::new()
+            })
+        );
+        assert_eq!(ctx_for("git log HEAD~").current_word, "HEAD~");
+        assert_eq!(ctx_for("git checkout @{").current_word, "@{");
+        assert_eq!(RefShorthand::parse("HEAD"), None);
+        assert_eq!(RefShorthand::parse("HEAD~x"), None);
+        assert_eq!(RefShorthand::parse("~"), None);
+
+        let subjects = "Merge branch 'dev'\nFix parser\nAdd tests\n";
+        assert_eq!(
+            ancestor_candidates("HEAD", subjects),
+            vec![
+                ("HEAD~1".to_string(), Some("Fix parser".to_string())),
+                ("HEAD~2".to_string(), Some("Add tests".to_string())),
+            ]
+        );
+        assert_eq!(
+            parent_candidates("HEAD", "0123456789ab fedcba987654\n"),
+            vec![
+                ("HEAD^1".to_string(), Some("0123456".to_string())),
+                ("HEAD^2".to_string(), Some("fedcba9".to_string())),
+            ]
+        );
+
+        let reflog = "commit: wip\ncheckout: moving from feature/x to main\n\
+                      checkout: moving from main to feature/x\n";
+        let previous = previous_branches(reflog);
+        assert_eq!(previous, vec!["feature/x", "main"]);
+        let values: Vec<String> = reflog_candidates("", &previous)
+            .into_iter()
+            .map(|(v, _)| v)
+            .collect();
+        assert_eq!(values, vec!["@{upstream}", "@{push}", "@{-1}", "@{-2}"]);
+        assert_eq!(reflog_candidates("main", &previous)[0].0, "main@{upstream}");
+    }
+
+    #[test]
+    fn test_parse_repo_directories() {
+        let modules = "submodule.vendor/lib.path vendor/lib\nsubmodule.docs.path docs\n";
+        assert_eq!(parse_submodule_paths(modules), vec!["vendor/lib", "docs"]);
+        // From a subdirectory of the repository
+        assert_eq!(relative_to_prefix("vendor/lib", ""), "vendor/lib");
+        assert_eq!(relative_to_prefix("vendor/lib", "vendor/"), "lib");
+        assert_eq!(relative_to_prefix("docs", "src/app/"), "../../docs");
+
+        let worktrees = "worktree /src/app\nHEAD 1234abcd\nbranch refs/heads/main\n\n\
+                         worktree /src/app-hotfix\nHEAD 5678ef01\ndetached\n";
+        assert_eq!(
+            parse_worktree_paths(worktrees),
+            vec!["/src/app", "/src/app-hotfix"]
+        );
+    }
+
+    #[test]
+    fn test_merge_strategy_values() {
+        let provider = GitProvider::new();
+        assert_eq!(
+            flag_value(&ctx_for("git merge -s ")),
+            Some((FlagValue::Strategy, "", ""))
+        );
+        let strategies = values_for(&provider, "git merge -s ").unwrap_or_default();
+        assert_eq!(strategies.len(), MERGE_STRATEGIES.len());
+        assert!(strategies.contains(&"ort".to_string()));
+        assert_eq!(
+            values_for(&provider, "git rebase -s o").unwrap_or_default(),
+            vec!["ort", "octopus", "ours"]
+        );
+        assert_eq!(
+            values_for(&provider, "git pull --strategy=re").unwrap_or_default(),
+            vec!["--strategy=recursive", "--strategy=resolve"]
+        );
+        // `restore -s` names a source tree, not a strategy
+        assert_eq!(flag_value(&ctx_for("git restore -s ")), None);
+        assert_eq!(flag_value(&ctx_for("git merge -s ort ")), None);
+    }
+
+    #[test]
+    fn test_onto_refs() {
+        assert_eq!(
+            flag_value(&ctx_for("git rebase --onto ")),
+            Some((FlagValue::Ref, "", ""))
+        );
+        assert_eq!(
+            flag_value(&ctx_for("git rebase -i --onto=ma")),
+            Some((FlagValue::Ref, "--onto=", "ma"))
+        );
+        assert_eq!(
+            flag_value(&ctx_for("git merge-base --fork-point o")),
+            Some((FlagValue::Ref, "", "o"))
+        );
+
+        let stubbed = "main\t\nfeature/x\t\norigin\trefs/remotes/origin/main\n\
+                       origin/main\t\nv1.0\t\n";
+        assert_eq!(
+            parse_refs(stubbed),
+            vec!["main", "feature/x", "origin/main", "v1.0"]
+        );
+    }
+
+    #[test]
+    fn test_config_values_color_ui() {
+        let provider = GitProvider::new();
+        assert_eq!(
+            values_for(&provider, "git config color.ui ").unwrap_or_default(),
+            vec!["auto", "always", "never"]
+        );
+        assert_eq!(
+            values_for(&provider, "git config --global color.ui a").unwrap_or_default(),
+            vec!["auto", "always"]
+        );
+    }
+
+    #[test]
+    fn test_config_values_from_all_scopes() {
+        assert_eq!(
+            config_key(&ctx_for("git config user.email ")),
+            Some("user.email")
+        );
+        assert_eq!(
+            config_key(&ctx_for("git config --global user.email a")),
+            Some("user.email")
+        );
+
+        let get_all = "me@home.example\nme@work.example\nme@home.example\n";
+        assert_eq!(
+            merge_config_values(&[], get_all),
+            vec!["me@home.example", "me@work.example"]
+        );
+        assert_eq!(
+            merge_config_values(&["true", "false", "only"], "only\n"),
+            vec!["true", "false", "only"]
+        );
+    }
+
+    #[test]
+    fn test_config_values_boolean() {
+        let provider = GitProvider::new();
+        assert_eq!(
+            values_for(&provider, "git config rerere.enabled ").unwrap_or_default(),
+            vec!["true", "false"]
+        );
+        assert_eq!(
+            values_for(&provider, "git config -f x.cfg Rebase.autoStash t").unwrap_or_default(),
+            vec!["true"]
+        );
+        // Unknown keys and the key position itself are left to other providers
+        assert!(config_values(&ctx_for("git config user.name ")).is_none());
+        assert!(config_values(&ctx_for("git config --file ")).is_none());
+    }
+}
@@ -0,0 +1,209 @@
+use log::debug;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files, process,
+};
+
+const GO: &str = "go";
+
+/// Upper bound on package directories collected from the working tree
+const MAX_PACKAGES: usize = 1000;
+
+/// Upper bound on directories read while looking for packages
+const MAX_DIRS_VISITED: usize = 5000;
+
+/// How many levels below the working directory packages are looked for
+const MAX_DEPTH: usize = 8;
+
+/// Directories that never hold packages of the module: the go tool's own
+/// exclusions and JavaScript dependencies
+const SKIPPED_DIRS: &[&str] = &["testdata", "vendor", "node_modules"];
+
+/// `go` subcommands whose arguments are package paths
+const PACKAGE_SUBCOMMANDS: &[&str] = &[
+    "build", "doc", "fmt", "generate", "install", "list", "run", "test", "vet",
+];
+
+/// Completes package paths and `go tool` names for `go`
+pub struct GoProvider {
+    timeout: Duration,
+}
+
+impl Default for GoProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GoProvider {
+    pub fn new() -> Self {
+        Self {
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for GoProvider {
+    fn name(&self) -> &'static str {
+        "go"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Go
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == GO && ctx.current_word_idx > 1 && !ctx.current_word.starts_with('-')
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let subcommand = ctx.words.get(1).map(String::as_str).unwrap_or_default();
+        let values = if subcommand == "tool" && ctx.current_word_idx == 2 {
+            match run_go(&["tool"], self.timeout) {
+                Some(output) => parse_tool_list(&output),
+                None => return Ok(None),
+            }
+        } else if PACKAGE_SUBCOMMANDS.contains(&subcommand) {
+            discover_packages(&files::working_dir(), MAX_PACKAGES)
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|v| v.starts_with(&ctx.current_word))
+                .map(|v| CompletionEntry::new(v, ProviderKind::Go).with_type(CandidateType::Value))
+                .collect(),
+        ))
+    }
+}
+
+fn run_go(args: &[&str], timeout: Duration) -> Option<String> {
+    let mut command = Command::new(GO);
+    command.current_dir(files::working_dir()).args(args);
+    match process::run_with_timeout(&mut command, None, timeout) {
+        Some(o) if o.status.success() => String::from_utf8(o.stdout).ok(),
+        Some(_) => None,
+        None => {
+            debug!("[go] go {:?} failed to run or timed out", args);
+            None
+        }
+    }
+}
+
+/// Tool names from `go tool` output (one per line)
+pub fn parse_tool_list(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `./...` followed by the directories under `root` that hold `.go` files, as
+/// `./`-relative package paths, `limit` entries at most. Hidden, `_`-prefixed,
+/// `testdata`, `vendor` and `node_modules` directories are skipped, and the
+/// walk stops at `MAX_DEPTH` levels or after `MAX_DIRS_VISITED` directories.
+pub fn discover_packages(root: &Path, limit: usize) -> Vec<String> {
+    let mut packages = vec!["./...".to_string()];
+    let mut pending = vec![(root.to_path_buf(), 0)];
+    let mut visited = 0;
+
+    while let Some((dir, depth)) = pending.pop() {
+        if visited >= MAX_DIRS_VISITED {
+            debug!("[go] stopped after {} directories", visited);
+            break;
+        }
+        visited += 1;
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut has_go = false;
+        let mut subdirs = Vec::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if path.is_dir() {
+                let skipped = name.starts_with(['.', '_']) || SKIPPED_DIRS.contains(&name.as_str());
+                if !skipped && depth < MAX_DEPTH {
+                    subdirs.push(path);
+                }
+            } else if name.ends_with(".go") {
+                has_go = true;
+            }
+        }
+
+        if has_go && let Ok(rel) = dir.strip_prefix(root) {
+            packages.push(if rel.as_os_str().is_empty() {
+                ".".to_string()
+            } else {
+                format!("./{}", rel.display())
+            });
+            if packages.len() >= limit {
+                break;
+            }
+        }
+        subdirs.sort();
+        pending.extend(subdirs.into_iter().rev().map(|d| (d, depth + 1)));
+    }
+
+    packages[1..].sort();
+    packages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_packages() {
+        let root = tempdir().unwrap();
+        let touch = |rel: &str| {
+            let path = root.path().join(rel);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        };
+        touch("main.go");
+        touch("cmd/server/main.go");
+        touch("internal/db/db.go");
+        touch("internal/README.md");
+        touch("internal/db/testdata/fixture.go");
+        touch("vendor/github.com/x/y.go");
+        touch(".git/hooks/hook.go");
+        touch("web/node_modules/flatted/golang/pkg/flatted/flatted.go");
+        touch("docs/index.md");
+        touch("a/b/c/d/e/f/g/h/i/deep.go");
+
+        assert_eq!(
+            discover_packages(root.path(), 100),
+            vec!["./...", ".", "./cmd/server", "./internal/db"]
+        );
+        // The limit counts every entry returned
+        assert_eq!(discover_packages(root.path(), 3).len(), 3);
+    }
+
+    #[test]
+    fn test_parse_tool_list() {
+        assert_eq!(
+            parse_tool_list("addr2line\nasm\ncgo\ncompile\n\n"),
+            vec!["addr2line", "asm", "cgo", "compile"]
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use log::debug;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files, process,
+};
+
+const JUST: &str = "just";
+
+/// Options of `just` selecting the justfile, passed on to `just --summary`
+const JUSTFILE_FLAGS: &[&str] = &["-f", "--justfile", "-d", "--working-directory"];
+
+/// Completes recipe names for `just` from `just --summary`
+pub struct JustProvider {
+    timeout: Duration,
+}
+
+impl Default for JustProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JustProvider {
+    pub fn new() -> Self {
+        Self {
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill `just --summary` after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for JustProvider {
+    fn name(&self) -> &'static str {
+        "just"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Just
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == JUST
+            && ctx.current_word_idx > 0
+            && !ctx.current_word.starts_with('-')
+            && !ctx.current_word.contains('=')
+            && !ctx
+                .previous_word
+                .as_deref()
+                .is_some_and(|w| JUSTFILE_FLAGS.contains(&w))
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let mut command = Command::new(JUST);
+        command
+            .current_dir(files::working_dir())
+            .args(justfile_args(&ctx.words[..ctx.current_word_idx]))
+            .arg("--summary");
+        // No `just` binary or no justfile: leave the word to the other providers
+        let Some(output) = process::run_with_timeout(&mut command, None, self.timeout) else {
+            return Ok(None);
+        };
+        if !output.status.success() {
+            debug!(
+                "[just] --summary failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Ok(None);
+        }
+
+        Ok(Some(
+            parse_summary(&String::from_utf8_lossy(&output.stdout))
+                .into_iter()
+                .filter(|recipe| recipe.starts_with(&ctx.current_word))
+                .map(|recipe| {
+                    CompletionEntry::new(recipe, ProviderKind::Just)
+                        .with_type(CandidateType::Command)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// The justfile-selecting options among `words`, with their values
+fn justfile_args(words: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut iter = words.iter().skip(1);
+    while let Some(word) = iter.next() {
+        if JUSTFILE_FLAGS.contains(&word.as_str()) {
+            if let Some(value) = iter.next() {
+                args.push(word.clone());
+                args.push(value.clone());
+            }
+        } else if word.starts_with("--justfile=") || word.starts_with("--working-directory=") {
+            args.push(word.clone());
+        }
+    }
+    args
+}
+
+/// Recipe names from `just --summary`, which prints them space-separated
+pub fn parse_summary(output: &str) -> Vec<String> {
+    output.split_whitespace().map(str::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(ws: &[&str]) -> Vec<String> {
+        ws.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_summary() {
+        let stubbed = "build check deploy-prod deploy-staging docs::serve\n";
+        assert_eq!(
+            parse_summary(stubbed),
+            vec![
+                "build",
+                "check",
+                "deploy-prod",
+                "deploy-staging",
+                "docs::serve"
+            ]
+        );
+        assert!(parse_summary("").is_empty());
+
+        assert_eq!(
+            justfile_args(&strings(&["just", "-f", "ci.just", "--dry-run"])),
+            vec!["-f", "ci.just"]
+        );
+        assert_eq!(
+            justfile_args(&strings(&["just", "--justfile=ci.just", "build"])),
+            vec!["--justfile=ci.just"]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files,
+};
+
+const MAKE: &str = "make";
+
+/// Default makefile names, in the order GNU make looks for them
+const MAKEFILE_NAMES: &[&str] = &["GNUmakefile", "makefile", "Makefile"];
+
+/// Options of `make` that take a value
+const VALUE_FLAGS: &[&str] = &[
+    "-C",
+    "--directory",
+    "-f",
+    "--file",
+    "--makefile",
+    "-I",
+    "--include-dir",
+    "-o",
+    "-W",
+];
+
+/// Completes targets and `VAR=` assignments from the Makefile
+pub struct MakeProvider;
+
+impl Default for MakeProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MakeProvider {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl CompletionProvider for MakeProvider {
+    fn name(&self) -> &'static str {
+        "make"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Make
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == MAKE
+            && ctx.current_word_idx > 0
+            && !ctx.current_word.starts_with('-')
+            && !ctx.current_word.contains(['/', '='])
+            && !ctx
+                .previous_word
+                .as_deref()
+                .is_some_and(|w| VALUE_FLAGS.contains(&w))
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some(path) = makefile_path(&ctx.words) else {
+            return Ok(None);
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) => {
+                debug!("[make] cannot read {}: {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+
+        let targets = parse_targets(&content)
+            .into_iter()
+            .map(|t| CompletionEntry::new(t, ProviderKind::Make).with_type(CandidateType::Command));
+        let variables = parse_variables(&content).into_iter().map(|v| {
+            CompletionEntry::new(format!("{}=", v), ProviderKind::Make)
+                .with_type(CandidateType::Value)
+        });
+
+        Ok(Some(
+            targets
+                .chain(variables)
+                .filter(|e| e.value.starts_with(&ctx.current_word))
+                .collect(),
+        ))
+    }
+}
+
+/// Makefile selected by `-C`/`-f` on the command line, or the default one
+fn makefile_path(words: &[String]) -> Option<PathBuf> {
+    let mut dir = files::working_dir();
+    let mut file = None;
+    let mut iter = words.iter().skip(1);
+    while let Some(word) = iter.next() {
+        match word.as_str() {
+            "-C" | "--directory" => dir = dir.join(iter.next()?),
+            "-f" | "--file" | "--makefile" => file = Some(PathBuf::from(iter.next()?)),
+            w => {
+                if let Some(d) = w.strip_prefix("--directory=") {
+                    dir = dir.join(d);
+                } else if let Some(f) = w.strip_prefix("--file=") {
+                    file = Some(PathBuf::from(f));
+                }
+            }
+        }
+    }
+
+    match file {
+        Some(file) => Some(dir.join(file)),
+        None => MAKEFILE_NAMES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.is_file()),
+    }
+}
+
+/// Explicit targets of a Makefile (no special `.TARGETS`, pattern rules or variables)
+pub fn parse_targets(content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with(['\t', '#', ' ']) {
+            continue;
+        }
+        let Some((names, rest)) = line.split_once(':') else {
+            continue;
+        };
+        // `VAR := value` and `VAR ::= value` are assignments, not rules
+        if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            if name.starts_with('.') || name.contains(['%', '$']) {
+                continue;
+            }
+            if !targets.iter().any(|t| t == name) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// Names of variables assigned in a Makefile (`=`, `:=`, `::=`, `?=`, `+=`, `!=`)
+pub fn parse_variables(content: &str) -> Vec<String> {
+    let mut variables: Vec<String> = Vec::new();
+    for line in content.lines() {
+        if line.starts_with('\t') {
+            continue;
+        }
+        let mut line = line.trim();
+        for keyword in ["export ", "override "] {
+            line = line.strip_prefix(keyword).unwrap_or(line).trim_start();
+        }
+
+        let Some(eq) = line.find('=') else {
+            continue;
+        };
+        let name = line[..eq].trim_end_matches([':', '?', '+', '!']).trim();
+        let is_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-');
+        if is_name && !variables.iter().any(|v| v == name) {
+            variables.push(name.to_string());
+        }
+    }
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAKEFILE: &str = "\
+CC = gcc
+CFLAGS := -O2 -Wall
+PREFIX ?= /usr/local
+LDLIBS += -lm
+export V = 0
+GIT_REV != git rev-parse HEAD
+
+.PHONY: all clean
+
+all: build
+build: main.o util.o
+\t$(CC) $(CFLAGS) -o app $^ $(LDLIBS)
+%.o: %.c
+\t$(CC) -c $<
+clean:
+\trm -f app *.o
+";
+
+    #[test]
+    fn test_parse_variables() {
+        assert_eq!(
+            parse_variables(MAKEFILE),
+            vec!["CC", "CFLAGS", "PREFIX", "LDLIBS", "V", "GIT_REV"]
+        );
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        assert_eq!(parse_targets(MAKEFILE), vec!["all", "build", "clean"]);
+    }
+
+    #[test]
+    fn test_makefile_path_from_args() {
+        let words: Vec<String> = ["make", "-C", "sub", "-f", "build.mk", ""]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(
+            makefile_path(&words),
+            Some(files::working_dir().join("sub").join("build.mk"))
+        );
+    }
+}
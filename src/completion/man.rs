@@ -0,0 +1,200 @@
+use log::debug;
+use std::cell::OnceCell;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind,
+};
+
+const MAN: &str = "man";
+
+/// Searched when `MANPATH` is unset
+const DEFAULT_MAN_DIRS: &[&str] = &["/usr/share/man", "/usr/local/share/man"];
+
+/// Suffixes of compressed man pages
+const COMPRESSION_SUFFIXES: &[&str] = &[".gz", ".bz2", ".xz", ".zst", ".Z"];
+
+/// Completes manual page names for `man`, optionally restricted to a section
+pub struct ManProvider {
+    man_dirs: Vec<PathBuf>,
+    /// (name, section) pairs, listed at most once
+    pages: OnceCell<Vec<(String, String)>>,
+}
+
+impl Default for ManProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManProvider {
+    pub fn new() -> Self {
+        let man_dirs = match env::var("MANPATH") {
+            Ok(path) if !path.is_empty() => env::split_paths(&path)
+                .filter(|p| !p.as_os_str().is_empty())
+                .collect(),
+            _ => DEFAULT_MAN_DIRS.iter().map(PathBuf::from).collect(),
+        };
+        Self {
+            man_dirs,
+            pages: OnceCell::new(),
+        }
+    }
+
+    /// Roots containing `man<section>/` directories
+    pub fn with_man_dirs(mut self, dirs: Vec<PathBuf>) -> Self {
+        self.man_dirs = dirs;
+        self
+    }
+
+    fn pages(&self) -> &Vec<(String, String)> {
+        self.pages.get_or_init(|| {
+            let pages = list_pages(&self.man_dirs);
+            debug!("[man] {} pages in {:?}", pages.len(), self.man_dirs);
+            pages
+        })
+    }
+}
+
+impl CompletionProvider for ManProvider {
+    fn name(&self) -> &'static str {
+        "man"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Man
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == MAN && ctx.current_word_idx > 0 && !ctx.current_word.starts_with('-')
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let section = requested_section(ctx);
+        let mut seen = HashSet::new();
+
+        Ok(Some(
+            self.pages()
+                .iter()
+                .filter(|(name, _)| name.starts_with(&ctx.current_word))
+                .filter(|(_, s)| section.is_none_or(|wanted| s.starts_with(wanted)))
+                .filter(|(name, _)| seen.insert(name.as_str()))
+                .map(|(name, s)| {
+                    CompletionEntry::new(name.clone(), ProviderKind::Man)
+                        .with_type(CandidateType::Value)
+                        .with_description(format!("({})", s))
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Section given as the word before the cursor (`man 3 <Tab>`)
+fn requested_section(ctx: &CompletionContext) -> Option<&str> {
+    let previous = ctx.previous_word.as_deref()?;
+    is_section(previous).then_some(previous)
+}
+
+/// Whether a word names a manual section (`1`, `3p`, `n`, ...)
+fn is_section(word: &str) -> bool {
+    (word.starts_with(|c: char| c.is_ascii_digit())
+        && word.chars().all(|c| c.is_ascii_alphanumeric()))
+        || matches!(word, "n" | "l")
+}
+
+/// List (name, section) pairs from the `man*/` directories of each root,
+/// sorted by name
+pub fn list_pages(roots: &[PathBuf]) -> Vec<(String, String)> {
+    let mut pages = Vec::new();
+    for root in roots {
+        let Ok(entries) = fs::read_dir(root) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let dir_name = entry.file_name();
+            if dir_name.to_string_lossy().starts_with("man") {
+                pages.extend(pages_in(&entry.path()));
+            }
+        }
+    }
+    pages.sort();
+    pages.dedup();
+    pages
+}
+
+fn pages_in(dir: &Path) -> Vec<(String, String)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| page_name(&e.file_name().to_string_lossy()))
+        .collect()
+}
+
+/// Split a page file name like `printf.3p.gz` into (`printf`, `3p`)
+pub fn page_name(file_name: &str) -> Option<(String, String)> {
+    let stem = COMPRESSION_SUFFIXES
+        .iter()
+        .find_map(|suffix| file_name.strip_suffix(suffix))
+        .unwrap_or(file_name);
+    let (name, section) = stem.rsplit_once('.')?;
+    if name.is_empty() || !is_section(section) {
+        return None;
+    }
+    Some((name.to_string(), section.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_page_name() {
+        assert_eq!(
+            page_name("printf.3p.gz"),
+            Some(("printf".to_string(), "3p".to_string()))
+        );
+        assert_eq!(
+            page_name("git-log.1"),
+            Some(("git-log".to_string(), "1".to_string()))
+        );
+        assert_eq!(page_name("README"), None);
+    }
+
+    #[test]
+    fn test_pages_by_prefix_and_section() {
+        let dir = tempfile::tempdir().unwrap();
+        for (section, file) in [
+            ("man1", "printf.1.gz"),
+            ("man1", "ls.1.gz"),
+            ("man3", "printf.3.gz"),
+            ("man3", "pthread_create.3"),
+        ] {
+            fs::create_dir_all(dir.path().join(section)).unwrap();
+            fs::write(dir.path().join(section).join(file), "").unwrap();
+        }
+        let provider = ManProvider::new().with_man_dirs(vec![dir.path().to_path_buf()]);
+
+        let values = |line: &str| -> Vec<String> {
+            provider
+                .try_complete(&ctx_for(line))
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|e| e.value)
+                .collect()
+        };
+        assert_eq!(values("man p"), vec!["printf", "pthread_create"]);
+        assert_eq!(values("man 1 p"), vec!["printf"]);
+        assert_eq!(values("man 3 "), vec!["printf", "pthread_create"]);
+    }
+}
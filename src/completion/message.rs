@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::bash::history::{self, HistoryFormat};
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, is_short_flag_cluster,
+};
+
+/// Completes the value of a message-taking flag (`git commit -m <Tab>`) with
+/// messages given to the same flags in history, quoted as a single argument
+pub struct MessageProvider {
+    /// Message-taking flags by command
+    flags: HashMap<String, Vec<String>>,
+    format: HistoryFormat,
+}
+
+impl Default for MessageProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageProvider {
+    pub fn new() -> Self {
+        Self {
+            flags: HashMap::new(),
+            format: HistoryFormat::Auto,
+        }
+    }
+
+    /// Declare the message-taking flags of commands (e.g. `git` -> `-m`, `--message`)
+    pub fn with_flags(mut self, flags: HashMap<String, Vec<String>>) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn with_format(mut self, format: HistoryFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
+
+impl CompletionProvider for MessageProvider {
+    fn name(&self) -> &'static str {
+        "message"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Message
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        self.flags
+            .get(&ctx.command)
+            .is_some_and(|flags| message_at(ctx, flags).is_some())
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some(flags) = self.flags.get(&ctx.command) else {
+            return Ok(None);
+        };
+        let commands: Vec<String> = history::read_history_with_format(None, self.format, true)
+            .into_iter()
+            .map(|entry| entry.command)
+            .collect();
+        Ok(message_candidates(ctx, flags, &commands))
+    }
+}
+
+/// The text kept before the message (e.g. `--message=`) and the typed part of
+/// it, without an opening quote, when the cursor is on a message
+pub fn message_at<'a>(ctx: &'a CompletionContext, flags: &[String]) -> Option<(&'a str, &'a str)> {
+    let word = ctx.current_word.as_str();
+    let (kept, typed) = match word.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") && flags.iter().any(|f| f == flag) => {
+            (&word[..flag.len() + 1], value)
+        }
+        _ if ctx
+            .previous_word
+            .as_deref()
+            .is_some_and(|w| takes_message(w, flags)) =>
+        {
+            ("", word)
+        }
+        _ => return None,
+    };
+    Some((kept, typed.trim_start_matches(['"', '\''])))
+}
+
+/// Messages from `commands` (history lines, oldest first) of the command at
+/// the cursor that start with the typed text, newest first and quoted
+pub fn message_candidates(
+    ctx: &CompletionContext,
+    flags: &[String],
+    commands: &[String],
+) -> Option<Vec<CompletionEntry>> {
+    let (kept, typed) = message_at(ctx, flags)?;
+    let mut messages: Vec<String> = Vec::new();
+    for message in commands
+        .iter()
+        .rev()
+        .flat_map(|line| extract_messages(line, &ctx.command, flags))
+    {
+        if message.starts_with(typed) && !messages.contains(&message) {
+            messages.push(message);
+        }
+    }
+
+    Some(
+        messages
+            .into_iter()
+            .filter_map(|message| {
+                let quoted = shlex::try_quote(&message).ok()?.into_owned();
+                Some(
+                    CompletionEntry::new(format!("{}{}", kept, quoted), ProviderKind::Message)
+                        .with_type(CandidateType::Value)
+                        .with_display(message),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// The messages a history line of `command` passes to one of `flags`, either
+/// as the next word (also after a `-am` cluster) or as `--message=...`
+pub fn extract_messages(line: &str, command: &str, flags: &[String]) -> Vec<String> {
+    let Some(words) = shlex::split(line) else {
+        return Vec::new();
+    };
+    if words.first().map(String::as_str) != Some(command) {
+        return Vec::new();
+    }
+
+    let mut messages = Vec::new();
+    let mut iter = words.iter().skip(1);
+    while let Some(word) = iter.next() {
+        if takes_message(word, flags) {
+            if let Some(message) = iter.next() {
+                messages.push(message.clone());
+            }
+        } else if let Some((flag, message)) = word.split_once('=')
+            && flags.iter().any(|f| f == flag)
+        {
+            messages.push(message.to_string());
+        }
+    }
+    messages.retain(|m| !m.trim().is_empty());
+    messages
+}
+
+/// `word` is one of `flags`, or a short flag cluster ending in one (`-am`)
+fn takes_message(word: &str, flags: &[String]) -> bool {
+    flags.iter().any(|flag| {
+        word == flag
+            || (flag.len() == 2
+                && !flag.starts_with("--")
+                && is_short_flag_cluster(word)
+                && word.ends_with(&flag[1..]))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    fn git_flags() -> Vec<String> {
+        vec!["-m".to_string(), "--message".to_string()]
+    }
+
+    #[test]
+    fn test_extract_quoted_message() {
+        let flags = git_flags();
+        assert_eq!(
+            extract_messages(r#"git commit -m "Fix the parser""#, "git", &flags),
+            vec!["Fix the parser"]
+        );
+        assert_eq!(
+            extract_messages("git commit -am 'Bump version' --no-verify", "git", &flags),
+            vec!["Bump version"]
+        );
+        assert_eq!(
+            extract_messages("git tag v1 --message='Release 1'", "git", &flags),
+            vec!["Release 1"]
+        );
+        assert!(extract_messages("python -m http.server", "git", &flags).is_empty());
+        assert!(extract_messages("git commit -m \"unterminated", "git", &flags).is_empty());
+    }
+
+    #[test]
+    fn test_message_inserted_quoted() {
+        let history = vec![
+            r#"git commit -m "Fix the parser""#.to_string(),
+            "ls -la".to_string(),
+            "git commit -m 'Add tests'".to_string(),
+            r#"git commit -m "Fix the parser""#.to_string(),
+        ];
+        let flags = git_flags();
+        let entries = message_candidates(&ctx_for("git commit -m "), &flags, &history).unwrap();
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["'Fix the parser'", "'Add tests'"]);
+        assert_eq!(entries[0].display.as_deref(), Some("Fix the parser"));
+        // The quoted value is a single word holding the whole message
+        assert_eq!(
+            shlex::split(&entries[0].value).unwrap(),
+            vec!["Fix the parser"]
+        );
+
+        let entries = message_candidates(&ctx_for("git commit -m \"Ad"), &flags, &history).unwrap();
+        assert_eq!(entries[0].value, "'Add tests'");
+        let entries =
+            message_candidates(&ctx_for("git commit --message=F"), &flags, &history).unwrap();
+        assert_eq!(entries[0].value, "--message='Fix the parser'");
+
+        assert!(message_candidates(&ctx_for("git commit "), &flags, &history).is_none());
+    }
+}
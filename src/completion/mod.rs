@@ -1,9 +1,39 @@
 use crate::bash::{self, history};
+use crate::config::AssignmentValue;
 use crate::parser::{self, ParsedLine};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
 use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
 use thiserror::Error;
 
+pub mod ansible;
+pub mod cache;
 pub mod carapace;
+pub mod colon;
+pub mod compression;
+pub mod cron;
+pub mod docker;
+pub mod editor;
+pub mod external;
+pub mod files;
+pub mod git;
+pub mod go;
+pub mod just;
+pub mod make;
+pub mod man;
+pub mod message;
+pub mod network;
+pub mod packages;
+pub mod process;
+pub mod python;
+pub mod ripgrep;
+pub mod spec;
+pub mod ssh;
+pub mod systemd;
+pub mod timezone;
 
 #[derive(Error, Debug)]
 pub enum CompletionError {
@@ -31,12 +61,32 @@ impl From<glob::PatternError> for CompletionError {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ProviderKind {
     Carapace,
     Bash,
     EnvVar,
     History,
+    Ansible,
+    Compression,
+    Cron,
+    Docker,
+    EditorRecent,
+    External,
+    Git,
+    Go,
+    Just,
+    Make,
+    Man,
+    Message,
+    Network,
+    Packages,
+    Python,
+    Ripgrep,
+    Spec,
+    Ssh,
+    Systemd,
+    Timezone,
     Pipeline,
     Unknown,
 }
@@ -48,27 +98,113 @@ impl fmt::Display for ProviderKind {
             ProviderKind::Bash => write!(f, "bash"),
             ProviderKind::EnvVar => write!(f, "envvar"),
             ProviderKind::History => write!(f, "history"),
+            ProviderKind::Ansible => write!(f, "ansible"),
+            ProviderKind::Compression => write!(f, "compression"),
+            ProviderKind::Cron => write!(f, "cron"),
+            ProviderKind::Docker => write!(f, "docker"),
+            ProviderKind::EditorRecent => write!(f, "editor_recent"),
+            ProviderKind::External => write!(f, "external"),
+            ProviderKind::Git => write!(f, "git"),
+            ProviderKind::Go => write!(f, "go"),
+            ProviderKind::Just => write!(f, "just"),
+            ProviderKind::Make => write!(f, "make"),
+            ProviderKind::Man => write!(f, "man"),
+            ProviderKind::Message => write!(f, "message"),
+            ProviderKind::Network => write!(f, "network"),
+            ProviderKind::Packages => write!(f, "packages"),
+            ProviderKind::Python => write!(f, "python"),
+            ProviderKind::Ripgrep => write!(f, "ripgrep"),
+            ProviderKind::Spec => write!(f, "spec"),
+            ProviderKind::Ssh => write!(f, "ssh"),
+            ProviderKind::Systemd => write!(f, "systemd"),
+            ProviderKind::Timezone => write!(f, "timezone"),
             ProviderKind::Pipeline => write!(f, "pipeline"),
             ProviderKind::Unknown => write!(f, "unknown"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// What a candidate refers to, independent of the provider that produced it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum CandidateType {
+    File,
+    Dir,
+    Command,
+    Flag,
+    Value,
+    #[default]
+    Other,
+}
+
+impl CandidateType {
+    /// Short marker shown before selector items when `show_types` is set
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CandidateType::File => "f",
+            CandidateType::Dir => "d",
+            CandidateType::Command => "c",
+            CandidateType::Flag => "-",
+            CandidateType::Value => "v",
+            CandidateType::Other => " ",
+        }
+    }
+}
+
+impl fmt::Display for CandidateType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CandidateType::File => write!(f, "file"),
+            CandidateType::Dir => write!(f, "dir"),
+            CandidateType::Command => write!(f, "command"),
+            CandidateType::Flag => write!(f, "flag"),
+            CandidateType::Value => write!(f, "value"),
+            CandidateType::Other => write!(f, "other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CompletionEntry {
     pub value: String,
     pub kind: ProviderKind,
+    pub candidate_type: CandidateType,
+    /// Optional human-readable description (never inserted)
+    pub description: Option<String>,
+    /// Text shown in the selector instead of `value` (never inserted)
+    pub display: Option<String>,
 }
 
 impl CompletionEntry {
     pub fn new(value: String, kind: ProviderKind) -> Self {
-        Self { value, kind }
+        Self {
+            value,
+            kind,
+            candidate_type: CandidateType::Other,
+            description: None,
+            display: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: String) -> Self {
+        self.description = Some(description);
+        self
+    }
+
+    pub fn with_type(mut self, candidate_type: CandidateType) -> Self {
+        self.candidate_type = candidate_type;
+        self
+    }
+
+    pub fn with_display(mut self, display: String) -> Self {
+        self.display = Some(display);
+        self
     }
 }
 
+/// What the selector shows: the display text if any, otherwise the value
 impl fmt::Display for CompletionEntry {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.value)
+        write!(f, "{}", self.display.as_deref().unwrap_or(&self.value))
     }
 }
 
@@ -80,82 +216,385 @@ pub struct CompletionContext {
     pub point: usize,
     pub command: String,
     pub current_word: String,
+    /// Part of `current_word` before the cursor; this is what gets replaced on insertion
+    pub current_word_prefix: String,
     pub previous_word: Option<String>,
-    /// If true, completion is for a command after pipe
+    /// If true, completion is for a command after a pipe, `&&` or `||`
     pub is_after_pipe: bool,
-    /// The command before the pipe (for context)
+    /// The word before that separator (for context)
     pub previous_command: Option<String>,
     /// Arguments for the command after the pipe
     pub pipe_command_args: Vec<String>,
+    /// Variable name when the cursor is on the value of a `NAME=value` assignment
+    pub assignment_name: Option<String>,
+    /// Array name when the cursor is inside an unclosed `NAME=(...` literal
+    pub array_assignment: Option<String>,
+    /// Here-document/here-string operator the current word belongs to
+    pub here_operator: Option<HereOperator>,
+    /// `host:path` operand of `scp`/`rsync` at the cursor
+    pub remote_path: Option<RemotePath>,
+    /// The current word is the file operand of a redirection (`> out`, `2> log`)
+    pub is_redirect_target: bool,
+    /// Cursor inside an unclosed `$((...`; `current_word` is then the variable
+    /// name being typed
+    pub arithmetic: bool,
+    /// Set when the cursor is past the subcommand of a subcommand dispatcher
+    pub subcommand: Option<SubcommandContext>,
+}
+
+/// Completion scope below the subcommand of a dispatcher command (`git`,
+/// `docker`, ...), where the subcommand defines what can follow
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubcommandContext {
+    /// The combined command, e.g. `docker run`
+    pub command: String,
+    /// Index of the subcommand in `words`
+    pub offset: usize,
+    /// `current_word_idx` counted from the subcommand (1 is its first argument)
+    pub current_word_idx: usize,
+}
+
+/// Path on another machine, written `[user@]host:path`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemotePath {
+    pub host: String,
+    pub path: String,
+}
+
+/// Commands whose first non-option argument is a subcommand
+pub const DEFAULT_SUBCOMMAND_DISPATCHERS: &[&str] =
+    &["git", "cargo", "docker", "podman", "kubectl", "npm", "go"];
+
+/// Global options of the dispatchers that take the next word as their value
+/// (`git -C dir status`), which is then not the subcommand
+const GLOBAL_VALUE_OPTIONS: &[(&str, &[&str])] = &[
+    (
+        "git",
+        &[
+            "-C",
+            "-c",
+            "--git-dir",
+            "--work-tree",
+            "--namespace",
+            "--config-env",
+        ],
+    ),
+    ("cargo", &["-C", "-Z", "--config", "--color"]),
+    (
+        "docker",
+        &[
+            "-H",
+            "--host",
+            "-c",
+            "--context",
+            "--config",
+            "-l",
+            "--log-level",
+            "--tlscacert",
+            "--tlscert",
+            "--tlskey",
+        ],
+    ),
+    (
+        "podman",
+        &[
+            "-c",
+            "--connection",
+            "--url",
+            "--root",
+            "--runroot",
+            "--log-level",
+            "--storage-driver",
+        ],
+    ),
+    (
+        "kubectl",
+        &[
+            "-n",
+            "--namespace",
+            "--context",
+            "--cluster",
+            "--kubeconfig",
+            "-s",
+            "--server",
+            "--user",
+            "--token",
+        ],
+    ),
+    ("npm", &["--prefix", "-w", "--workspace", "--registry"]),
+];
+
+/// Commands that run the command following their options
+pub const DEFAULT_WRAPPER_COMMANDS: &[&str] =
+    &["sudo", "env", "nohup", "doas", "time", "command", "xargs"];
+
+/// Redirection operators whose operand is a file (`2>` splits into `2` and `>`)
+const REDIRECT_OPERATORS: &[&str] = &[">", ">>", "<", "&>", "&>>", ">|", "<>"];
+
+/// Redirection operators whose operand is not an ordinary argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HereOperator {
+    /// `<<`/`<<-`: the word is a here-document delimiter, nothing to complete
+    Delimiter,
+    /// `<<<`: the word is a string fed to stdin, completed as a file/value
+    String,
 }
 
 impl CompletionContext {
     pub fn from_parsed(parsed: &ParsedLine, line: String, point: usize) -> Self {
-        let command = parsed.words.first().cloned().unwrap_or_default();
+        let wrappers: Vec<String> = DEFAULT_WRAPPER_COMMANDS
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        Self::from_parsed_with_wrappers(parsed, line, point, &wrappers)
+    }
+
+    /// Like `from_parsed`, completing past the given wrapper commands
+    /// (`sudo systemctl st` completes `systemctl`)
+    pub fn from_parsed_with_wrappers(
+        parsed: &ParsedLine,
+        line: String,
+        point: usize,
+        wrappers: &[String],
+    ) -> Self {
         let current_word = parsed
             .words
             .get(parsed.current_word_index)
             .cloned()
             .unwrap_or_default();
-        let previous_word = if parsed.current_word_index > 0 {
-            parsed.words.get(parsed.current_word_index - 1).cloned()
+
+        // Only the statement under the cursor (between `|`, `;`, `&&`, `||`) is completed
+        let (start, end) = parser::statement_bounds(&parsed.words, parsed.current_word_index);
+        let words = parsed.words[start..end].to_vec();
+        let current_word_idx = parsed.current_word_index - start;
+        let command = words.first().cloned().unwrap_or_default();
+        let previous_word = if current_word_idx > 0 {
+            words.get(current_word_idx - 1).cloned()
         } else {
             None
         };
 
-        let pipe_idx = parser::find_last_pipe_index(&parsed.words);
-        let (is_after_pipe, previous_command, pipe_command_args) = if let Some(pipe_idx) = pipe_idx
-        {
-            let cmd_idx = pipe_idx + 1;
-            if parsed.current_word_index > pipe_idx {
-                let prev_cmd = parsed.words.get(pipe_idx.saturating_sub(1)).cloned();
-                let args = if cmd_idx + 1 < parsed.words.len() {
-                    parsed.words[cmd_idx + 1..].to_vec()
-                } else {
-                    vec![]
-                };
-                (true, prev_cmd, args)
-            } else {
-                (false, None, vec![])
-            }
+        let is_after_pipe = start > 0;
+        let previous_command = if start > 1 {
+            parsed.words.get(start - 2).cloned()
         } else {
-            (false, None, vec![])
+            None
         };
-
-        let effective_command = if is_after_pipe {
-            if let Some(cmd) = parsed.words.get(pipe_idx.unwrap() + 1) {
-                cmd.clone()
-            } else {
-                command
-            }
+        let pipe_command_args = if is_after_pipe {
+            words.iter().skip(1).cloned().collect()
         } else {
-            command
+            vec![]
         };
 
-        Self {
-            words: parsed.words.clone(),
-            current_word_idx: parsed.current_word_index,
+        let mut ctx = Self {
+            words,
+            current_word_idx,
             line,
             point,
-            command: effective_command,
+            command,
             current_word,
+            current_word_prefix: parsed.current_word_prefix.clone(),
             previous_word,
             is_after_pipe,
             previous_command,
             pipe_command_args,
+            assignment_name: None,
+            array_assignment: None,
+            here_operator: None,
+            remote_path: None,
+            is_redirect_target: false,
+            arithmetic: false,
+            subcommand: None,
+        };
+        ctx.unwrap_wrappers(wrappers);
+        ctx.detect_assignment();
+        ctx.detect_array_assignment();
+        ctx.detect_here_operator();
+        ctx.detect_redirect_target();
+        ctx.detect_remote_path();
+        ctx.detect_arithmetic();
+        ctx
+    }
+
+    /// Complete the variable under the cursor inside `$((...`, where words
+    /// are split on operators rather than whitespace
+    fn detect_arithmetic(&mut self) {
+        let before = &self.line[..parser::clamp_to_char_boundary(&self.line, self.point)];
+        if let Some(name) = parser::open_arithmetic(before) {
+            self.arithmetic = true;
+            self.assignment_name = None;
+            self.current_word = name.to_string();
+            self.current_word_prefix = name.to_string();
+        }
+    }
+
+    /// Recognize a `host:path` operand: a `:` with a non-empty host before it
+    /// and no `/` ahead of it (`./a:b` and `/x:y` are local)
+    fn detect_remote_path(&mut self) {
+        if self.current_word_idx == 0
+            || colon::segment_at(&self.command, 0) != Some(colon::Segment::Host)
+            || self.current_word.starts_with('-')
+        {
+            return;
+        }
+        let segments = colon::split_segments(&self.current_word);
+        let host = segments[0];
+        if segments.len() < 2 || host.is_empty() || host.contains('/') {
+            return;
         }
+        self.remote_path = Some(RemotePath {
+            host: host.to_string(),
+            path: self.current_word[host.len() + 1..].to_string(),
+        });
+    }
+
+    fn detect_redirect_target(&mut self) {
+        self.is_redirect_target = self
+            .previous_word
+            .as_deref()
+            .is_some_and(|w| REDIRECT_OPERATORS.contains(&w));
+    }
+
+    /// Whether nothing should be offered at the cursor (a here-document delimiter)
+    pub fn suppresses_completion(&self) -> bool {
+        self.here_operator == Some(HereOperator::Delimiter)
+    }
+
+    /// Recognize the operand of `<<`, `<<-` and `<<<`, either as the word after
+    /// the operator or glued to it (`<<EOF`)
+    fn detect_here_operator(&mut self) {
+        self.here_operator = match self.previous_word.as_deref() {
+            Some("<<<") => Some(HereOperator::String),
+            Some("<<" | "<<-") => Some(HereOperator::Delimiter),
+            _ if self.current_word.starts_with("<<<") => None,
+            _ if self.current_word.starts_with("<<") => Some(HereOperator::Delimiter),
+            _ => None,
+        };
     }
 
-    /// Returns true if we're completing a command name after a pipe
+    /// Rename the command for provider lookup (`k` completes as `kubectl`).
+    /// The typed words are only rewritten in the command position, so the
+    /// word being completed and its insertion are unaffected.
+    pub fn with_command_map(mut self, command_map: &HashMap<String, String>) -> Self {
+        let Some(mapped) = command_map.get(&self.command) else {
+            return self;
+        };
+        if self.current_word_idx > 0 {
+            self.words[0] = mapped.clone();
+            if self.current_word_idx == 1 {
+                self.previous_word = Some(mapped.clone());
+            }
+            self.command = mapped.clone();
+        }
+        self
+    }
+
+    /// Scope the context to the subcommand when `command` is one of the
+    /// `dispatchers` and the cursor is past its first non-option argument
+    /// (the value of a global option such as `git -C dir` is not one)
+    pub fn with_subcommand_dispatchers(mut self, dispatchers: &[String]) -> Self {
+        if !dispatchers.contains(&self.command) || self.current_word_idx == 0 {
+            return self;
+        }
+        let value_options = GLOBAL_VALUE_OPTIONS
+            .iter()
+            .find(|(command, _)| *command == self.command)
+            .map_or(&[][..], |(_, options)| *options);
+        let mut offset = 1;
+        while offset < self.current_word_idx && self.words[offset].starts_with('-') {
+            let takes_value = value_options.contains(&self.words[offset].as_str());
+            offset += if takes_value { 2 } else { 1 };
+        }
+        if offset >= self.current_word_idx {
+            return self;
+        }
+        self.subcommand = Some(SubcommandContext {
+            command: format!("{} {}", self.command, self.words[offset]),
+            offset,
+            current_word_idx: self.current_word_idx - offset,
+        });
+        self
+    }
+
+    /// Complete the element under the cursor when inside `NAME=(a b ...`; the
+    /// element, not the word the tokenizer saw, is what gets replaced
+    fn detect_array_assignment(&mut self) {
+        let before = &self.line[..parser::clamp_to_char_boundary(&self.line, self.point)];
+        if let Some((name, element)) = parser::open_array_assignment(before) {
+            self.array_assignment = Some(name.to_string());
+            self.assignment_name = None;
+            self.current_word = element.to_string();
+            self.current_word_prefix = element.to_string();
+        }
+    }
+
+    /// Mark the current word as an assignment value when it is a `NAME=value`
+    /// in assignment position: before the command, or an `export`-style argument
+    fn detect_assignment(&mut self) {
+        if self.assignment_name.is_some() {
+            return;
+        }
+
+        let before_command = self.words[..self.current_word_idx.min(self.words.len())]
+            .iter()
+            .all(|w| parser::assignment_name(w).is_some());
+        let declaration = self.current_word_idx > 0
+            && matches!(
+                self.command.as_str(),
+                "export" | "declare" | "local" | "readonly" | "typeset"
+            );
+
+        if before_command || declaration {
+            self.assignment_name =
+                parser::assignment_name(&self.current_word).map(|name| name.to_string());
+        }
+    }
+
+    /// Rebase the context past leading `NAME=value` assignments and wrapper
+    /// commands with their options (`env` also with its assignments), so the
+    /// real command drives completion (`FOO=1 git che`, `sudo -u www git che`
+    /// and `env FOO=1 git che` complete `git`)
+    fn unwrap_wrappers(&mut self, wrappers: &[String]) {
+        loop {
+            let cmd_start = if wrappers.contains(&self.command) {
+                parser::skip_wrapper_prefix(&self.words)
+            } else {
+                self.words
+                    .iter()
+                    .take_while(|w| parser::assignment_name(w).is_some())
+                    .count()
+            };
+            if cmd_start == 0 {
+                return;
+            }
+
+            if self.current_word_idx < cmd_start {
+                // The assignment values themselves are detected by `detect_assignment`
+                if self.command == "env" && self.current_word_idx > 0 {
+                    self.assignment_name =
+                        parser::assignment_name(&self.current_word).map(|name| name.to_string());
+                }
+                return;
+            }
+
+            self.words = self.words[cmd_start..].to_vec();
+            self.current_word_idx -= cmd_start;
+            self.command = self.words.first().cloned().unwrap_or_default();
+            self.previous_word = if self.current_word_idx > 0 {
+                self.words.get(self.current_word_idx - 1).cloned()
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Returns true if we're completing a command name after a separator
     pub fn is_completing_pipe_command(&self) -> bool {
-        self.is_after_pipe
-            && self.current_word_idx > 0
-            && parser::find_last_pipe_index(&self.words)
-                .is_some_and(|pipe_idx| self.current_word_idx == pipe_idx + 1)
+        self.is_after_pipe && self.current_word_idx == 0
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionOptions {
     pub filenames: bool,
     pub noquote: bool,
@@ -167,7 +606,7 @@ pub struct CompletionOptions {
     pub nosort: bool,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CompletionSpec {
     pub function: Option<String>,
     pub wordlist: Option<String>,
@@ -193,7 +632,7 @@ pub trait CompletionProvider: Send {
 }
 
 /// Result of a completion attempt
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompletionResult {
     pub candidates: Vec<CompletionEntry>,
     pub used_provider: ProviderKind,
@@ -207,7 +646,12 @@ impl CompletionResult {
 }
 
 /// Carapace-based completion provider
-pub struct CarapaceProvider;
+pub struct CarapaceProvider {
+    program: PathBuf,
+    timeout: Duration,
+    /// Extra `export` arguments by command
+    command_args: HashMap<String, Vec<String>>,
+}
 
 impl Default for CarapaceProvider {
     fn default() -> Self {
@@ -217,7 +661,38 @@ impl Default for CarapaceProvider {
 
 impl CarapaceProvider {
     pub fn new() -> Self {
-        Self
+        Self {
+            program: PathBuf::from(carapace::CARAPACE),
+            timeout: process::DEFAULT_TIMEOUT,
+            command_args: HashMap::new(),
+        }
+    }
+
+    /// Run this binary instead of `carapace` from `PATH`
+    pub fn with_program(mut self, program: Option<PathBuf>) -> Self {
+        if let Some(program) = program {
+            self.program = program;
+        }
+        self
+    }
+
+    /// Kill carapace after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Pass extra arguments to `carapace <command> export` for these commands
+    pub fn with_command_args(mut self, command_args: HashMap<String, Vec<String>>) -> Self {
+        self.command_args = command_args;
+        self
+    }
+
+    fn extra_args(&self, command: &str) -> &[String] {
+        self.command_args
+            .get(command)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
     }
 }
 
@@ -230,36 +705,140 @@ impl CompletionProvider for CarapaceProvider {
         ProviderKind::Carapace
     }
 
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.assignment_name.is_none()
+            && ctx.array_assignment.is_none()
+            && ctx.here_operator.is_none()
+            && !ctx.is_redirect_target
+            && !ctx.arithmetic
+    }
+
     fn try_complete(
         &self,
         ctx: &CompletionContext,
     ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
-        let args = if ctx.is_after_pipe {
-            std::iter::once(ctx.command.clone())
-                .chain(ctx.pipe_command_args.clone())
-                .collect()
+        // Truncate args to the current cursor position to handle mid-line completion
+        let args = if ctx.current_word_idx < ctx.words.len() {
+            ctx.words[0..=ctx.current_word_idx].to_vec()
         } else {
-            // Truncate args to the current cursor position to handle mid-line completion
-            if ctx.current_word_idx < ctx.words.len() {
-                ctx.words[0..=ctx.current_word_idx].to_vec()
-            } else {
-                ctx.words.clone()
-            }
+            ctx.words.clone()
         };
 
-        let items = carapace::CarapaceProvider::fetch_suggestions(&ctx.command, &args)?;
+        let items = carapace::CarapaceProvider::fetch_suggestions(
+            &self.program,
+            &ctx.command,
+            self.extra_args(&ctx.command),
+            &args,
+            self.timeout,
+        )?;
 
         Ok(items.map(|items| {
             items
                 .into_iter()
-                .map(|i| CompletionEntry::new(i.value, ProviderKind::Carapace))
+                .map(|i| {
+                    let candidate_type = if i.value.starts_with('-') {
+                        CandidateType::Flag
+                    } else {
+                        CandidateType::Value
+                    };
+                    let entry = CompletionEntry::new(i.value, ProviderKind::Carapace)
+                        .with_type(candidate_type);
+                    // Shown dimmed next to the value by the selector, never inserted
+                    match i.description {
+                        Some(description) if !description.is_empty() => {
+                            entry.with_description(description)
+                        }
+                        _ => entry,
+                    }
+                })
                 .collect()
         }))
     }
 }
 
+/// Directory-changing commands that also offer submodules and worktrees
+const CD_COMMANDS: &[&str] = &["cd", "pushd"];
+
+/// Bash builtins, offered as command names even when `compgen -c` misses them
+const BASH_BUILTINS: &[&str] = &[
+    ".",
+    ":",
+    "[",
+    "alias",
+    "bg",
+    "bind",
+    "break",
+    "builtin",
+    "caller",
+    "cd",
+    "command",
+    "compgen",
+    "complete",
+    "compopt",
+    "continue",
+    "declare",
+    "dirs",
+    "disown",
+    "echo",
+    "enable",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fc",
+    "fg",
+    "getopts",
+    "hash",
+    "help",
+    "history",
+    "jobs",
+    "kill",
+    "let",
+    "local",
+    "logout",
+    "mapfile",
+    "popd",
+    "printf",
+    "pushd",
+    "pwd",
+    "read",
+    "readarray",
+    "readonly",
+    "return",
+    "set",
+    "shift",
+    "shopt",
+    "source",
+    "suspend",
+    "test",
+    "times",
+    "trap",
+    "true",
+    "type",
+    "typeset",
+    "ulimit",
+    "umask",
+    "unalias",
+    "unset",
+    "wait",
+];
+
+/// Builtins whose arguments name shell variables
+const VARIABLE_COMMANDS: &[&str] = &["declare", "readonly", "local", "typeset"];
+
 /// Bash-based completion provider
-pub struct BashProvider;
+pub struct BashProvider {
+    trim_candidates: bool,
+    interactive: bool,
+    assignment_values: HashMap<String, AssignmentValue>,
+    dir_only_commands: Vec<String>,
+    /// Offer submodules and worktrees to `cd`, which needs git
+    repo_directories: bool,
+    /// `PATH`-like variable whose directories also complete a command's arguments
+    path_search_vars: HashMap<String, String>,
+    timeout: Duration,
+}
 
 impl Default for BashProvider {
     fn default() -> Self {
@@ -269,7 +848,103 @@ impl Default for BashProvider {
 
 impl BashProvider {
     pub fn new() -> Self {
-        Self
+        Self {
+            trim_candidates: true,
+            interactive: false,
+            assignment_values: HashMap::new(),
+            dir_only_commands: Vec::new(),
+            repo_directories: false,
+            path_search_vars: HashMap::new(),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Give up on a bash or git query after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Also complete arguments of a command from the directories listed in a
+    /// variable (e.g. `python` -> `PYTHONPATH`)
+    pub fn with_path_search_vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.path_search_vars = vars;
+        self
+    }
+
+    /// Commands whose arguments always complete directories, whatever their compspec says
+    pub fn with_dir_only_commands(mut self, commands: Vec<String>) -> Self {
+        self.dir_only_commands = commands;
+        self
+    }
+
+    /// Offer the submodules and worktrees of the repository first to `cd`
+    pub fn with_repo_directories(mut self, enabled: bool) -> Self {
+        self.repo_directories = enabled;
+        self
+    }
+
+    /// Per-variable completion mode for `NAME=value` assignments
+    pub fn with_assignment_values(mut self, values: HashMap<String, AssignmentValue>) -> Self {
+        self.assignment_values = values;
+        self
+    }
+
+    /// Complete the value of a `NAME=value` assignment, keeping the `NAME=` prefix
+    fn assignment_value_candidates(
+        &self,
+        name: &str,
+        ctx: &CompletionContext,
+    ) -> Result<(Vec<String>, CandidateType), CompletionError> {
+        let value = ctx
+            .current_word
+            .split_once('=')
+            .map(|(_, v)| v)
+            .unwrap_or_default();
+        let mode = assignment_value_mode(name, &self.assignment_values);
+        let (flag, candidate_type) = match mode {
+            AssignmentValue::Command => ("-c", CandidateType::Command),
+            AssignmentValue::File => ("-f", CandidateType::File),
+            AssignmentValue::Directory => ("-d", CandidateType::Dir),
+        };
+
+        let values = bash::execute_compgen(
+            &[flag.to_string(), "--".to_string(), value.to_string()],
+            self.timeout,
+        )?;
+        Ok((
+            values
+                .into_iter()
+                .map(|v| format!("{}={}", name, v))
+                .collect(),
+            candidate_type,
+        ))
+    }
+
+    /// Use an interactive bash (`bash -i`) where rc-defined state such as aliases matters
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Strip surrounding whitespace from each candidate (inner spaces are kept)
+    pub fn with_trim_candidates(mut self, trim: bool) -> Self {
+        self.trim_candidates = trim;
+        self
+    }
+
+    fn clean_candidates(&self, candidates: Vec<String>) -> Vec<String> {
+        candidates
+            .into_iter()
+            .filter(|c| !c.trim().is_empty())
+            .map(|c| {
+                if self.trim_candidates {
+                    c.trim().to_string()
+                } else {
+                    c
+                }
+            })
+            .collect()
     }
 }
 
@@ -282,27 +957,276 @@ impl CompletionProvider for BashProvider {
         ProviderKind::Bash
     }
 
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        // Local files never match a `host:path` operand
+        ctx.remote_path.is_none()
+    }
+
     fn try_complete(
         &self,
         ctx: &CompletionContext,
     ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
-        let spec = resolve_compspec(&ctx.command)?;
+        if ctx.array_assignment.is_some()
+            || ctx.here_operator == Some(HereOperator::String)
+            || ctx.is_redirect_target
+        {
+            return Ok(Some(
+                files::complete_paths(&ctx.current_word, false)
+                    .into_iter()
+                    .map(|c| {
+                        CompletionEntry::new(c, ProviderKind::Bash).with_type(CandidateType::File)
+                    })
+                    .collect(),
+            ));
+        }
 
-        let candidates = if ctx.is_completing_pipe_command()
-            || is_command_name_completion(&spec, ctx)
+        if ctx.arithmetic {
+            // `$` is optional in arithmetic, so keep whichever form was typed
+            let (dollar, name) = match ctx.current_word.strip_prefix('$') {
+                Some(name) => ("$", name),
+                None => ("", ctx.current_word.as_str()),
+            };
+            let names = bash::execute_compgen(
+                &["-v".to_string(), "--".to_string(), name.to_string()],
+                self.timeout,
+            )?;
+            return Ok(Some(
+                self.clean_candidates(names)
+                    .into_iter()
+                    .map(|c| {
+                        CompletionEntry::new(format!("{}{}", dollar, c), ProviderKind::Bash)
+                            .with_type(CandidateType::Value)
+                    })
+                    .collect(),
+            ));
+        }
+
+        if ctx.current_word_idx > 0
+            && !ctx.current_word.starts_with('-')
+            && self.dir_only_commands.contains(&ctx.command)
         {
-            bash::execute_compgen(&["-c".to_string(), "--".to_string(), ctx.current_word.clone()])?
-        } else {
-            execute_completion(&spec, ctx)?
-        };
+            let repo_dirs = if self.repo_directories && CD_COMMANDS.contains(&ctx.command.as_str())
+            {
+                git::repo_directories(self.timeout)
+            } else {
+                Vec::new()
+            };
+            return Ok(Some(directory_entries(
+                repo_dirs,
+                files::complete_paths(&ctx.current_word, true),
+                &ctx.current_word,
+            )));
+        }
 
-        Ok(Some(
-            candidates
-                .into_iter()
-                .map(|c| CompletionEntry::new(c, ProviderKind::Bash))
-                .collect(),
-        ))
+        if let Some(name) = &ctx.assignment_name {
+            let (values, candidate_type) = self.assignment_value_candidates(name, ctx)?;
+            return Ok(Some(
+                self.clean_candidates(values)
+                    .into_iter()
+                    .map(|c| CompletionEntry::new(c, ProviderKind::Bash).with_type(candidate_type))
+                    .collect(),
+            ));
+        }
+
+        let (interactive, timeout) = (self.interactive, self.timeout);
+        if let Some(names) = alias_candidates(ctx, interactive, |word| {
+            let args = ["-a".to_string(), "--".to_string(), word.to_string()];
+            Ok(bash::execute_compgen_interactive(&args, timeout)?)
+        })? {
+            return Ok(Some(
+                self.clean_candidates(names)
+                    .into_iter()
+                    .map(|c| {
+                        CompletionEntry::new(c, ProviderKind::Bash)
+                            .with_type(CandidateType::Command)
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(names) = variable_candidates(ctx, |word| {
+            let args = ["-v".to_string(), "--".to_string(), word.to_string()];
+            Ok(if interactive {
+                bash::execute_compgen_interactive(&args, timeout)?
+            } else {
+                bash::execute_compgen(&args, timeout)?
+            })
+        })? {
+            return Ok(Some(
+                self.clean_candidates(names)
+                    .into_iter()
+                    .map(|c| {
+                        CompletionEntry::new(c, ProviderKind::Bash).with_type(CandidateType::Value)
+                    })
+                    .collect(),
+            ));
+        }
+
+        if let Some(values) = colon::segment_candidates(ctx, colon::segment_values) {
+            return Ok(Some(
+                self.clean_candidates(values)
+                    .into_iter()
+                    .map(|c| {
+                        CompletionEntry::new(c, ProviderKind::Bash).with_type(CandidateType::Value)
+                    })
+                    .collect(),
+            ));
+        }
+
+        let spec = resolve_compspec(&ctx.command, self.timeout)?;
+
+        if ctx.is_completing_pipe_command() || is_command_name_completion(&spec, ctx) {
+            let commands = bash::execute_compgen(
+                &["-c".to_string(), "--".to_string(), ctx.current_word.clone()],
+                self.timeout,
+            )?;
+            return Ok(Some(command_entries(
+                self.clean_candidates(commands),
+                &ctx.current_word,
+            )));
+        }
+
+        let mut candidates = execute_completion(&spec, ctx, self.timeout)?;
+        if let Some(var) = self.path_search_vars.get(&ctx.command)
+            && !ctx.current_word.starts_with('-')
+            && let Ok(search_path) = env::var(var)
+        {
+            add_search_path_candidates(&mut candidates, &search_path, &ctx.current_word);
+        }
+
+        Ok(Some(compspec_entries(
+            self.clean_candidates(candidates),
+            &spec,
+        )))
+    }
+}
+
+/// Entries for the candidates a compspec generated, wrapped in its `-P`
+/// prefix and `-S` suffix, which apply to these only
+fn compspec_entries(candidates: Vec<String>, spec: &CompletionSpec) -> Vec<CompletionEntry> {
+    let entries = candidates
+        .into_iter()
+        .map(|c| CompletionEntry::new(c, ProviderKind::Bash).with_type(CandidateType::Other))
+        .collect();
+    crate::quoting::apply_affixes(entries, &spec.prefix, &spec.suffix)
+}
+
+/// Append paths found for `word` in the directories of `search_path` that the
+/// working directory did not already offer
+fn add_search_path_candidates(candidates: &mut Vec<String>, search_path: &str, word: &str) {
+    for path in files::complete_in_search_path(search_path, word) {
+        if !candidates.contains(&path) {
+            candidates.push(path);
+        }
+    }
+}
+
+/// Directory candidates: repository submodules/worktrees matching `word`
+/// first, then the plain listing. All end in `/`
+fn directory_entries(
+    repo_dirs: Vec<(String, &'static str)>,
+    listing: Vec<String>,
+    word: &str,
+) -> Vec<CompletionEntry> {
+    let mut entries: Vec<CompletionEntry> = repo_dirs
+        .into_iter()
+        .filter(|(path, _)| path.starts_with(word))
+        .map(|(path, label)| {
+            CompletionEntry::new(
+                format!("{}/", path.trim_end_matches('/')),
+                ProviderKind::Bash,
+            )
+            .with_type(CandidateType::Dir)
+            .with_description(label.to_string())
+        })
+        .collect();
+    for dir in listing {
+        let value = format!("{}/", dir);
+        if !entries.iter().any(|e| e.value == value) {
+            entries.push(
+                CompletionEntry::new(value, ProviderKind::Bash).with_type(CandidateType::Dir),
+            );
+        }
+    }
+    entries
+}
+
+/// Command names from `compgen -c` with the builtins starting with `word`
+/// merged in; builtins are described as such
+fn command_entries(commands: Vec<String>, word: &str) -> Vec<CompletionEntry> {
+    let builtin = |name: &str| BASH_BUILTINS.contains(&name);
+    let mut entries: Vec<CompletionEntry> = commands
+        .into_iter()
+        .map(|c| {
+            let entry =
+                CompletionEntry::new(c, ProviderKind::Bash).with_type(CandidateType::Command);
+            if builtin(&entry.value) {
+                entry.with_description("builtin".to_string())
+            } else {
+                entry
+            }
+        })
+        .collect();
+    for name in BASH_BUILTINS.iter().filter(|b| b.starts_with(word)) {
+        if !entries.iter().any(|e| e.value == *name) {
+            entries.push(
+                CompletionEntry::new(name.to_string(), ProviderKind::Bash)
+                    .with_type(CandidateType::Command)
+                    .with_description("builtin".to_string()),
+            );
+        }
     }
+    entries
+}
+
+/// Completion mode for an assignment value: configured per variable, files otherwise
+fn assignment_value_mode(name: &str, values: &HashMap<String, AssignmentValue>) -> AssignmentValue {
+    values.get(name).copied().unwrap_or(AssignmentValue::File)
+}
+
+/// Complete alias names for `alias`/`unalias` arguments using `list_aliases(word)`.
+/// Returns `None` when the cursor is not on an alias name, or when bash is not
+/// interactive: it has no aliases then, and the `alias`/`unalias` compspec
+/// should answer instead of an empty list.
+fn alias_candidates<F>(
+    ctx: &CompletionContext,
+    interactive: bool,
+    list_aliases: F,
+) -> Result<Option<Vec<String>>, CompletionError>
+where
+    F: FnOnce(&str) -> Result<Vec<String>, CompletionError>,
+{
+    let is_alias_cmd = ctx.command == "alias" || ctx.command == "unalias";
+    if !interactive
+        || !is_alias_cmd
+        || ctx.current_word_idx == 0
+        || ctx.current_word.starts_with('-')
+        || ctx.current_word.contains('=')
+    {
+        return Ok(None);
+    }
+
+    list_aliases(&ctx.current_word).map(Some)
+}
+
+/// Complete shell variable names (not only exported ones) for `declare`/`local`/...
+/// arguments using `list_variables(word)`. Returns `None` elsewhere.
+fn variable_candidates<F>(
+    ctx: &CompletionContext,
+    list_variables: F,
+) -> Result<Option<Vec<String>>, CompletionError>
+where
+    F: FnOnce(&str) -> Result<Vec<String>, CompletionError>,
+{
+    if !VARIABLE_COMMANDS.contains(&ctx.command.as_str())
+        || ctx.current_word_idx == 0
+        || ctx.current_word.starts_with('-')
+        || ctx.current_word.contains('=')
+    {
+        return Ok(None);
+    }
+
+    list_variables(&ctx.current_word).map(Some)
 }
 
 fn is_command_name_completion(spec: &CompletionSpec, ctx: &CompletionContext) -> bool {
@@ -313,12 +1237,16 @@ fn is_command_name_completion(spec: &CompletionSpec, ctx: &CompletionContext) ->
         && spec.glob_pattern.is_none()
 }
 
-pub fn resolve_compspec(command: &str) -> Result<CompletionSpec, CompletionError> {
+pub fn resolve_compspec(
+    command: &str,
+    timeout: Duration,
+) -> Result<CompletionSpec, CompletionError> {
     if command.is_empty() {
-        return Ok(CompletionSpec::default());
+        // Empty line: bash consults the `complete -E` compspec
+        return Ok(bash::query_complete_empty(timeout)?.unwrap_or_default());
     }
 
-    if let Some(spec) = bash::query_complete(command)? {
+    if let Some(spec) = bash::query_complete(command, timeout)? {
         Ok(spec)
     } else {
         let mut spec = CompletionSpec::default();
@@ -330,6 +1258,7 @@ pub fn resolve_compspec(command: &str) -> Result<CompletionSpec, CompletionError
 pub fn execute_completion(
     spec: &CompletionSpec,
     ctx: &CompletionContext,
+    timeout: Duration,
 ) -> Result<Vec<String>, CompletionError> {
     let mut candidates = Vec::new();
     let word = &ctx.current_word;
@@ -338,18 +1267,12 @@ pub fn execute_completion(
         let mut args = flags;
         args.push("--".to_string());
         args.push(word.clone());
-        Ok(bash::execute_compgen(&args)?)
+        Ok(bash::execute_compgen(&args, timeout)?)
     };
 
     if let Some(function) = &spec.function {
         candidates.extend(bash::execute_completion_function(
-            function,
-            &ctx.command,
-            word,
-            ctx.previous_word.as_deref(),
-            &ctx.words,
-            &ctx.line,
-            ctx.point,
+            function, &ctx.words, &ctx.line, ctx.point, timeout,
         )?);
     }
 
@@ -365,18 +1288,21 @@ pub fn execute_completion(
         candidates.extend(run_compgen(vec!["-G".to_string(), glob.clone()])?);
     }
 
+    // Like `compgen -f`/`-d`, through the directory listing cache
     if spec.options.filenames || spec.options.default {
-        candidates.extend(run_compgen(vec!["-f".to_string()])?);
+        candidates.extend(files::complete_paths(word, false));
     }
     if spec.options.dirnames {
-        candidates.extend(run_compgen(vec!["-d".to_string()])?);
+        candidates.extend(files::complete_paths(word, true));
     }
 
     Ok(candidates)
 }
 
 /// Environment variable completion provider
-pub struct EnvVarProvider;
+pub struct EnvVarProvider {
+    match_case: bool,
+}
 
 impl Default for EnvVarProvider {
     fn default() -> Self {
@@ -386,7 +1312,13 @@ impl Default for EnvVarProvider {
 
 impl EnvVarProvider {
     pub fn new() -> Self {
-        Self
+        Self { match_case: false }
+    }
+
+    /// Match variable names case-sensitively (`$pa` no longer matches `$PATH`)
+    pub fn with_match_case(mut self, match_case: bool) -> Self {
+        self.match_case = match_case;
+        self
     }
 }
 
@@ -408,7 +1340,7 @@ impl CompletionProvider for EnvVarProvider {
         ctx: &CompletionContext,
     ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
         let var_prefix = ctx.current_word[1..].to_string();
-        let vars = get_env_variables(&var_prefix);
+        let vars = get_env_variables(&var_prefix, self.match_case);
         Ok(Some(
             vars.into_iter()
                 .map(|v| CompletionEntry::new(v, ProviderKind::EnvVar))
@@ -417,10 +1349,16 @@ impl CompletionProvider for EnvVarProvider {
     }
 }
 
-pub fn get_env_variables(prefix: &str) -> Vec<String> {
+pub fn get_env_variables(prefix: &str, match_case: bool) -> Vec<String> {
     let prefix_lower = prefix.to_lowercase();
-    std::env::vars()
-        .filter(|(k, _)| k.to_lowercase().starts_with(&prefix_lower))
+    env::vars()
+        .filter(|(k, _)| {
+            if match_case {
+                k.starts_with(prefix)
+            } else {
+                k.to_lowercase().starts_with(&prefix_lower)
+            }
+        })
         .map(|(k, _)| format!("${}", k))
         .collect()
 }
@@ -428,6 +1366,7 @@ pub fn get_env_variables(prefix: &str) -> Vec<String> {
 /// History-based completion provider
 pub struct HistoryProvider {
     limit: Option<usize>,
+    format: history::HistoryFormat,
 }
 
 impl Default for HistoryProvider {
@@ -438,7 +1377,15 @@ impl Default for HistoryProvider {
 
 impl HistoryProvider {
     pub fn new(limit: Option<usize>) -> Self {
-        Self { limit }
+        Self {
+            limit,
+            format: history::HistoryFormat::Auto,
+        }
+    }
+
+    pub fn with_format(mut self, format: history::HistoryFormat) -> Self {
+        self.format = format;
+        self
     }
 }
 
@@ -461,7 +1408,7 @@ impl CompletionProvider for HistoryProvider {
     ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
         // Use the full line as prefix to match history
         let prefix = ctx.line.trim();
-        let matches = history::get_history_commands_by_prefix(prefix, self.limit);
+        let matches = history::get_history_commands_by_prefix(prefix, self.limit, self.format);
 
         if !matches.is_empty() {
             Ok(Some(
@@ -479,23 +1426,45 @@ impl CompletionProvider for HistoryProvider {
 /// Orchestrates completion providers in order of priority
 pub struct CompletionEngine {
     provider: Box<dyn CompletionProvider>,
+    /// Single-letter flags by command, offered appended to a `-xv` cluster
+    short_flags: HashMap<String, String>,
+    timeout: Duration,
 }
 
 impl CompletionEngine {
     pub fn new(provider: Box<dyn CompletionProvider>) -> Self {
-        Self { provider }
+        Self {
+            provider,
+            short_flags: HashMap::new(),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Give up on the compspec query after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Declare the single-letter flags of commands (e.g. `tar` -> `cxtvzjf`)
+    pub fn with_short_flags(mut self, short_flags: HashMap<String, String>) -> Self {
+        self.short_flags = short_flags;
+        self
     }
 
     /// Generate completion candidates using all providers
     /// Returns the first non-empty result
     pub fn complete(&self, ctx: &CompletionContext) -> Result<CompletionResult, CompletionError> {
-        let candidates = if self.provider.should_try(ctx) {
+        let mut candidates = if self.provider.should_try(ctx) {
             self.provider.try_complete(ctx)?.unwrap_or_default()
         } else {
             Vec::new()
         };
+        if let Some(letters) = self.short_flags.get(&ctx.command) {
+            add_cluster_candidates(&mut candidates, &ctx.current_word, letters);
+        }
         let used_provider = self.provider.kind();
-        let spec = resolve_compspec(&ctx.command)?;
+        let spec = resolve_compspec(&ctx.command, self.timeout)?;
         Ok(CompletionResult {
             candidates,
             used_provider,
@@ -504,11 +1473,66 @@ impl CompletionEngine {
     }
 }
 
+/// Whether `word` is a cluster of short flags such as `-xv`
+pub fn is_short_flag_cluster(word: &str) -> bool {
+    word.strip_prefix('-')
+        .is_some_and(|flags| !flags.is_empty() && flags.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+/// The cluster `word` extended by each flag in `letters` it does not contain yet
+pub fn cluster_candidates(word: &str, letters: &str, kind: ProviderKind) -> Vec<CompletionEntry> {
+    if !is_short_flag_cluster(word) {
+        return Vec::new();
+    }
+    let mut seen = String::new();
+    letters
+        .chars()
+        .filter(|&c| c.is_ascii_alphanumeric() && !word[1..].contains(c))
+        .filter(|&c| {
+            let new = !seen.contains(c);
+            seen.push(c);
+            new
+        })
+        .map(|c| {
+            CompletionEntry::new(format!("{}{}", word, c), kind).with_type(CandidateType::Flag)
+        })
+        .collect()
+}
+
+/// Append the cluster extensions of `word` that providers did not return already
+fn add_cluster_candidates(candidates: &mut Vec<CompletionEntry>, word: &str, letters: &str) {
+    for entry in cluster_candidates(word, letters, ProviderKind::Unknown) {
+        if !candidates.iter().any(|c| c.value == entry.value) {
+            candidates.push(entry);
+        }
+    }
+}
+
+/// Trim surrounding whitespace and collapse repeated `/` (inner spaces and a
+/// URL's `scheme://` are kept)
+pub fn normalize_candidate(value: &str) -> String {
+    let mut normalized = String::with_capacity(value.len());
+    let mut prev = None;
+    for c in value.trim().chars() {
+        if c == '/' && prev == Some('/') && !normalized.ends_with(":/") {
+            continue;
+        }
+        normalized.push(c);
+        prev = Some(c);
+    }
+    normalized
+}
+
 /// Combines multiple providers into a pipeline
 /// Results are merged with deduplication, earlier providers have higher priority
 pub struct PipelineProvider {
     name: String,
-    providers: Vec<Box<dyn CompletionProvider>>,
+    /// Providers with an optional cap on the candidates each contributes
+    providers: Vec<(Box<dyn CompletionProvider>, Option<usize>)>,
+    /// Stop at the first provider that returns candidates instead of merging
+    exclusive: bool,
+    /// Normalize candidate values (see `normalize_candidate`) before deduplication
+    normalize: bool,
 }
 
 impl PipelineProvider {
@@ -516,18 +1540,46 @@ impl PipelineProvider {
         Self {
             name: name.to_string(),
             providers: Vec::new(),
+            exclusive: false,
+            normalize: false,
+        }
+    }
+
+    /// Trim whitespace and collapse doubled slashes in values before merging,
+    /// so `foo/` and `foo// ` become one candidate
+    pub fn with_normalization(&mut self, normalize: bool) -> &mut Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// A pipeline that falls back through its providers: the first one that
+    /// returns candidates wins and later providers are not queried
+    pub fn exclusive(name: &str) -> Self {
+        Self {
+            exclusive: true,
+            ..Self::new(name)
         }
     }
 
     /// Add a provider to the pipeline
     pub fn with<P: CompletionProvider + 'static>(&mut self, provider: P) -> &mut Self {
-        self.providers.push(Box::new(provider));
+        self.providers.push((Box::new(provider), None));
         self
     }
 
     /// Add a boxed provider to the pipeline
     pub fn with_boxed(&mut self, provider: Box<dyn CompletionProvider>) -> &mut Self {
-        self.providers.push(provider);
+        self.providers.push((provider, None));
+        self
+    }
+
+    /// Add a boxed provider whose candidates are truncated to `limit` before merging
+    pub fn with_limited(
+        &mut self,
+        provider: Box<dyn CompletionProvider>,
+        limit: Option<usize>,
+    ) -> &mut Self {
+        self.providers.push((provider, limit));
         self
     }
 }
@@ -548,24 +1600,37 @@ impl CompletionProvider for PipelineProvider {
         let mut merged: Vec<CompletionEntry> = Vec::new();
         let mut seen: std::collections::HashSet<String> = std::collections::HashSet::new();
 
-        for provider in &self.providers {
+        for (provider, limit) in &self.providers {
             if !provider.should_try(ctx) {
                 continue;
             }
 
-            if let Some(candidates) = provider.try_complete(ctx)? {
+            if let Some(mut candidates) = provider.try_complete(ctx)? {
+                if let Some(limit) = limit {
+                    candidates.truncate(*limit);
+                }
                 log::debug!(
                     "[pipeline] {} returned {} candidates",
                     provider.name(),
                     candidates.len()
                 );
-                for c in candidates {
+                for mut c in candidates {
+                    if self.normalize {
+                        c.value = normalize_candidate(&c.value);
+                        if c.value.is_empty() {
+                            continue;
+                        }
+                    }
                     // Use value for deduplication, but keep the entry (and its provider kind)
                     if seen.insert(c.value.clone()) {
                         merged.push(c);
                     }
                 }
             }
+
+            if self.exclusive && !merged.is_empty() {
+                break;
+            }
         }
 
         log::debug!("[pipeline] merged result ({} total)", merged.len());
@@ -578,6 +1643,27 @@ impl CompletionProvider for PipelineProvider {
     }
 }
 
+/// Context for completing at the end of `line`, shared by the provider tests
+#[cfg(test)]
+pub(crate) fn ctx_for(line: &str) -> CompletionContext {
+    let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+    CompletionContext::from_parsed(&parsed, line.to_string(), line.len())
+}
+
+/// Values `provider` completes at the end of `line`, or `None` when it does
+/// not apply there
+#[cfg(test)]
+pub(crate) fn values_for(provider: &dyn CompletionProvider, line: &str) -> Option<Vec<String>> {
+    let ctx = ctx_for(line);
+    if !provider.should_try(&ctx) {
+        return None;
+    }
+    provider
+        .try_complete(&ctx)
+        .unwrap()
+        .map(|entries| entries.into_iter().map(|e| e.value).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -598,6 +1684,726 @@ mod tests {
         assert!(ctx.pipe_command_args.is_empty());
     }
 
+    #[test]
+    fn test_completion_context_after_and() {
+        let parsed = parser::parse_shell_line("foo && gre", 10).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "foo && gre".to_string(), 10);
+
+        assert!(ctx.is_after_pipe);
+        assert!(ctx.is_completing_pipe_command());
+        assert_eq!(ctx.command, "gre");
+        assert_eq!(ctx.previous_command, Some("foo".to_string()));
+        assert!(ctx.pipe_command_args.is_empty());
+    }
+
+    #[test]
+    fn test_completion_context_after_or_and_pipe() {
+        let parsed = parser::parse_shell_line("a || b | c -", 12).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "a || b | c -".to_string(), 12);
+
+        assert!(ctx.is_after_pipe);
+        assert!(!ctx.is_completing_pipe_command());
+        assert_eq!(ctx.command, "c");
+        assert_eq!(ctx.previous_command, Some("b".to_string()));
+        assert_eq!(ctx.pipe_command_args, vec!["-".to_string()]);
+    }
+
+    #[test]
+    fn test_completion_context_after_semicolon() {
+        let parsed = parser::parse_shell_line("echo hi; gi", 11).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "echo hi; gi".to_string(), 11);
+        assert!(ctx.is_completing_pipe_command());
+        assert_eq!(ctx.words, vec!["gi".to_string()]);
+        assert_eq!(ctx.current_word_idx, 0);
+
+        let line = "echo hi; git che";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        assert!(!ctx.is_completing_pipe_command());
+        assert_eq!(ctx.command, "git");
+        assert_eq!(ctx.words, vec!["git".to_string(), "che".to_string()]);
+        assert_eq!(ctx.current_word_idx, 1);
+        assert_eq!(ctx.previous_word, Some("git".to_string()));
+
+        // The statement ends at the next separator
+        let parsed = parser::parse_shell_line("cd /t; ls -l", 5).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "cd /t; ls -l".to_string(), 5);
+        assert_eq!(ctx.command, "cd");
+        assert_eq!(ctx.words, vec!["cd".to_string(), "/t".to_string()]);
+    }
+
+    #[test]
+    fn test_empty_line_compspec_applied() {
+        let parsed = parser::parse_shell_line("", 0).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, String::new(), 0);
+        assert_eq!(ctx.command, "");
+
+        let spec = CompletionSpec {
+            wordlist: Some("start stop".to_string()),
+            ..Default::default()
+        };
+        assert!(!is_command_name_completion(&spec, &ctx));
+
+        let candidates = execute_completion(&spec, &ctx, process::DEFAULT_TIMEOUT).unwrap();
+        assert_eq!(candidates, vec!["start", "stop"]);
+    }
+
+    #[test]
+    fn test_bash_provider_cleans_compgen_output() {
+        let raw = bash::split_candidates("foo\n\n   \nbar baz  \n  qux\n");
+
+        let trimmed = BashProvider::new().clean_candidates(raw.clone());
+        assert_eq!(trimmed, vec!["foo", "bar baz", "qux"]);
+
+        let untrimmed = BashProvider::new()
+            .with_trim_candidates(false)
+            .clean_candidates(raw);
+        assert_eq!(untrimmed, vec!["foo", "bar baz  ", "  qux"]);
+    }
+
+    #[test]
+    fn test_completion_context_env_assignments() {
+        let words: Vec<String> = ["env", "A=1", "B=2", "git", "che"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let parsed = create_parsed(words, 4);
+        let ctx = CompletionContext::from_parsed(&parsed, "env A=1 B=2 git che".to_string(), 19);
+
+        assert_eq!(ctx.command, "git");
+        assert_eq!(ctx.words, vec!["git", "che"]);
+        assert_eq!(ctx.current_word_idx, 1);
+        assert_eq!(ctx.current_word, "che");
+        assert_eq!(ctx.previous_word.as_deref(), Some("git"));
+        assert!(ctx.assignment_name.is_none());
+    }
+
+    #[test]
+    fn test_completion_context_env_command_name() {
+        let parsed = parser::parse_shell_line("env A=1 gi", 10).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "env A=1 gi".to_string(), 10);
+
+        assert_eq!(ctx.words, vec!["gi"]);
+        assert_eq!(ctx.current_word_idx, 0);
+    }
+
+    #[test]
+    fn test_completion_context_leading_assignments() {
+        let parsed = parser::parse_shell_line("FOO=bar BAZ=qux gi", 18).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "FOO=bar BAZ=qux gi".to_string(), 18);
+        assert_eq!(ctx.command, "gi");
+        assert_eq!(ctx.words, vec!["gi"]);
+        assert_eq!(ctx.current_word_idx, 0);
+
+        let parsed = parser::parse_shell_line("FOO=bar git che", 15).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "FOO=bar git che".to_string(), 15);
+        assert_eq!(ctx.command, "git");
+        assert_eq!(ctx.words, vec!["git", "che"]);
+        assert_eq!(ctx.current_word_idx, 1);
+        assert_eq!(ctx.previous_word.as_deref(), Some("git"));
+        assert!(ctx.assignment_name.is_none());
+
+        // The value of an assignment is still completed as one
+        let parsed = parser::parse_shell_line("FOO=bar EDITOR=vi", 17).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "FOO=bar EDITOR=vi".to_string(), 17);
+        assert_eq!(ctx.assignment_name.as_deref(), Some("EDITOR"));
+    }
+
+    #[test]
+    fn test_completion_context_wrapper_commands() {
+        let line = "sudo systemctl sta";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        assert_eq!(ctx.command, "systemctl");
+        assert_eq!(ctx.words, vec!["systemctl", "sta"]);
+        assert_eq!(ctx.current_word_idx, 1);
+
+        let line = "sudo -u www nohup env FOO=1 git ";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        assert_eq!(ctx.command, "git");
+        assert_eq!(ctx.words, vec!["git", ""]);
+        assert_eq!(ctx.current_word_idx, 1);
+
+        // The value of a wrapper option, and wrappers not configured, stay as typed
+        let parsed = parser::parse_shell_line("sudo -u ro", 10).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "sudo -u ro".to_string(), 10);
+        assert_eq!(ctx.command, "sudo");
+        let ctx = CompletionContext::from_parsed_with_wrappers(
+            &parser::parse_shell_line("sudo git ", 9).unwrap(),
+            "sudo git ".to_string(),
+            9,
+            &["nohup".to_string()],
+        );
+        assert_eq!(ctx.command, "sudo");
+    }
+
+    #[test]
+    fn test_completion_context_env_assignment_value() {
+        let parsed = parser::parse_shell_line("env FOO=", 8).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "env FOO=".to_string(), 8);
+
+        assert_eq!(ctx.command, "env");
+        assert_eq!(ctx.assignment_name.as_deref(), Some("FOO"));
+        assert!(!CarapaceProvider::new().should_try(&ctx));
+    }
+
+    #[test]
+    fn test_alias_candidates_unalias() {
+        let parsed = parser::parse_shell_line("unalias g", 9).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "unalias g".to_string(), 9);
+
+        let stub = ["gs", "gco", "ll"];
+        let names = alias_candidates(&ctx, true, |word| {
+            Ok(stub
+                .iter()
+                .filter(|a| a.starts_with(word))
+                .map(|a| a.to_string())
+                .collect())
+        })
+        .unwrap();
+        assert_eq!(names, Some(vec!["gs".to_string(), "gco".to_string()]));
+
+        // A non-interactive bash has no aliases; leave it to the compspec
+        let names = alias_candidates(&ctx, false, |_| Ok(Vec::new())).unwrap();
+        assert_eq!(names, None);
+    }
+
+    #[test]
+    fn test_alias_candidates_skips_other_positions() {
+        let list = |_: &str| -> Result<Vec<String>, CompletionError> { Ok(vec!["ll".to_string()]) };
+
+        let parsed = parser::parse_shell_line("ls l", 4).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "ls l".to_string(), 4);
+        assert_eq!(alias_candidates(&ctx, true, list).unwrap(), None);
+
+        let parsed = parser::parse_shell_line("alias ll=", 9).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "alias ll=".to_string(), 9);
+        assert_eq!(alias_candidates(&ctx, true, list).unwrap(), None);
+    }
+
+    #[test]
+    fn test_variable_candidates_declare() {
+        let parsed = parser::parse_shell_line("declare MY", 10).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "declare MY".to_string(), 10);
+
+        let stub = ["MY_VAR", "MYLIST", "OTHER"];
+        let names = variable_candidates(&ctx, |word| {
+            Ok(stub
+                .iter()
+                .filter(|v| v.starts_with(word))
+                .map(|v| v.to_string())
+                .collect())
+        })
+        .unwrap();
+        assert_eq!(
+            names,
+            Some(vec!["MY_VAR".to_string(), "MYLIST".to_string()])
+        );
+
+        let list = |_: &str| -> Result<Vec<String>, CompletionError> { Ok(vec!["X".to_string()]) };
+        for line in ["declare -", "local X=", "echo MY"] {
+            let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+            let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+            assert_eq!(variable_candidates(&ctx, list).unwrap(), None, "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_assignment_value_modes() {
+        let config = crate::config::Config::default();
+        assert_eq!(
+            assignment_value_mode("EDITOR", &config.assignment_values),
+            AssignmentValue::Command
+        );
+        assert_eq!(
+            assignment_value_mode("LOG", &config.assignment_values),
+            AssignmentValue::File
+        );
+    }
+
+    #[test]
+    fn test_assignment_value_editor_offers_commands() {
+        let line = "EDITOR=bas";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        assert_eq!(ctx.assignment_name.as_deref(), Some("EDITOR"));
+
+        let provider = BashProvider::new()
+            .with_assignment_values(crate::config::Config::default().assignment_values);
+        let result = provider.try_complete(&ctx).unwrap().unwrap();
+        assert!(result.iter().any(|e| e.value == "EDITOR=bash"));
+        assert!(
+            result
+                .iter()
+                .all(|e| e.candidate_type == CandidateType::Command)
+        );
+    }
+
+    #[test]
+    fn test_assignment_value_generic_var_offers_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("app.log"), "").unwrap();
+        let line = format!("export LOG={}/app", dir.path().display());
+
+        let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+        assert_eq!(ctx.assignment_name.as_deref(), Some("LOG"));
+
+        let result = BashProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let expected = format!("LOG={}/app.log", dir.path().display());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].value, expected);
+        assert_eq!(result[0].candidate_type, CandidateType::File);
+    }
+
+    #[test]
+    fn test_command_map() {
+        let map: HashMap<String, String> = [("k", "kubectl"), ("g", "git")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let parsed = parser::parse_shell_line("k get po", 8).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "k get po".to_string(), 8)
+            .with_command_map(&map);
+        assert_eq!(ctx.command, "kubectl");
+        assert_eq!(ctx.words, vec!["kubectl", "get", "po"]);
+        assert_eq!(ctx.current_word, "po");
+        assert_eq!(ctx.current_word_prefix, "po");
+
+        let parsed = parser::parse_shell_line("g ", 2).unwrap();
+        let ctx =
+            CompletionContext::from_parsed(&parsed, "g ".to_string(), 2).with_command_map(&map);
+        assert!(git::GitProvider::new().should_try(&ctx));
+
+        // Completing the command name itself is left alone
+        let parsed = parser::parse_shell_line("k", 1).unwrap();
+        let ctx =
+            CompletionContext::from_parsed(&parsed, "k".to_string(), 1).with_command_map(&map);
+        assert_eq!(ctx.command, "k");
+    }
+
+    #[test]
+    fn test_subcommand_context() {
+        let dispatchers: Vec<String> = DEFAULT_SUBCOMMAND_DISPATCHERS
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        let ctx_for = |line: &str| {
+            let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+            CompletionContext::from_parsed(&parsed, line.to_string(), line.len())
+                .with_subcommand_dispatchers(&dispatchers)
+        };
+
+        let ctx = ctx_for("docker --debug run -it ub");
+        assert_eq!(ctx.command, "docker");
+        assert_eq!(
+            ctx.subcommand,
+            Some(SubcommandContext {
+                command: "docker run".to_string(),
+                offset: 2,
+                current_word_idx: 2,
+            })
+        );
+
+        // The value of a global option is not the subcommand
+        let command = |line: &str| ctx_for(line).subcommand.map(|sub| sub.command);
+        assert_eq!(
+            command("git -C dir status "),
+            Some("git status".to_string())
+        );
+        assert_eq!(
+            command("docker -H host run "),
+            Some("docker run".to_string())
+        );
+        assert_eq!(command("git -C dir "), None);
+        assert_eq!(command("git -C "), None);
+
+        // Still typing the subcommand, or not a dispatcher
+        assert_eq!(ctx_for("docker ru").subcommand, None);
+        assert_eq!(ctx_for("ls -la ").subcommand, None);
+    }
+
+    #[test]
+    fn test_env_var_case_matching() {
+        unsafe { std::env::set_var("BFT_CASE_TEST", "1") };
+
+        let insensitive = get_env_variables("bft_case", false);
+        assert!(insensitive.contains(&"$BFT_CASE_TEST".to_string()));
+
+        assert!(get_env_variables("bft_case", true).is_empty());
+        assert_eq!(
+            get_env_variables("BFT_CASE", true),
+            vec!["$BFT_CASE_TEST".to_string()]
+        );
+
+        unsafe { std::env::remove_var("BFT_CASE_TEST") };
+    }
+
+    #[test]
+    fn test_directory_entries_put_repo_dirs_first() {
+        let repo_dirs = vec![
+            ("vendor/lib".to_string(), "submodule"),
+            ("/src/app-hotfix".to_string(), "worktree"),
+        ];
+        let listing = vec!["build".to_string(), "vendor".to_string()];
+
+        let entries = directory_entries(repo_dirs.clone(), listing.clone(), "");
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec!["vendor/lib/", "/src/app-hotfix/", "build/", "vendor/"]
+        );
+        assert_eq!(entries[0].description.as_deref(), Some("submodule"));
+
+        let entries = directory_entries(repo_dirs, vec!["vendor".to_string()], "ve");
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["vendor/lib/", "vendor/"]);
+    }
+
+    #[test]
+    fn test_short_flag_clusters() {
+        assert!(is_short_flag_cluster("-xv"));
+        assert!(is_short_flag_cluster("-x"));
+        assert!(!is_short_flag_cluster("-"));
+        assert!(!is_short_flag_cluster("--verbose"));
+        assert!(!is_short_flag_cluster("-o=x"));
+
+        let values = |word: &str| -> Vec<String> {
+            cluster_candidates(word, "cxtvzf", ProviderKind::Unknown)
+                .into_iter()
+                .map(|e| e.value)
+                .collect()
+        };
+        assert_eq!(values("-xv"), vec!["-xvc", "-xvt", "-xvz", "-xvf"]);
+        assert!(values("tar").is_empty());
+
+        // Provider candidates come first and are not repeated
+        let mut candidates = vec![CompletionEntry::new("-xvz".to_string(), ProviderKind::Bash)];
+        add_cluster_candidates(&mut candidates, "-xvz", "xvzf");
+        let values: Vec<&str> = candidates.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(values, vec!["-xvz", "-xvzf"]);
+        assert_eq!(candidates[1].candidate_type, CandidateType::Flag);
+    }
+
+    #[test]
+    fn test_local_and_remote_copy_operands() {
+        let remote = |line: &str| {
+            let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+            CompletionContext::from_parsed(&parsed, line.to_string(), line.len()).remote_path
+        };
+        assert_eq!(remote("rsync loc"), None);
+        assert_eq!(
+            remote("rsync host:pa"),
+            Some(RemotePath {
+                host: "host".into(),
+                path: "pa".into()
+            })
+        );
+        assert_eq!(
+            remote("scp -P 2222 a.txt deploy@web1:/srv/"),
+            Some(RemotePath {
+                host: "deploy@web1".into(),
+                path: "/srv/".into()
+            })
+        );
+        // A `/` before the `:` makes it a local path
+        assert_eq!(remote("rsync ./backup:old"), None);
+        assert_eq!(remote("rsync src/a:b"), None);
+        assert_eq!(remote("cp host:pa"), None);
+
+        let parsed = parser::parse_shell_line("rsync host:pa", 13).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "rsync host:pa".to_string(), 13);
+        assert!(!BashProvider::new().should_try(&ctx));
+    }
+
+    #[test]
+    fn test_arithmetic_variable_names() {
+        unsafe { std::env::set_var("BFT_ARITH_TEST", "1") };
+        let line = "echo $(( BFT_ARITH_T";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        assert!(ctx.arithmetic);
+        assert_eq!(ctx.current_word, "BFT_ARITH_T");
+        assert!(!CarapaceProvider::new().should_try(&ctx));
+
+        let result = BashProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["BFT_ARITH_TEST"]);
+        unsafe { std::env::remove_var("BFT_ARITH_TEST") };
+    }
+
+    #[test]
+    fn test_carapace_command_args() {
+        let provider = CarapaceProvider::new().with_command_args(HashMap::from([(
+            "kubectl".to_string(),
+            vec!["--kubeconfig".to_string(), "/tmp/kube".to_string()],
+        )]));
+        let export_args = |command: &str| -> Vec<String> {
+            let words = vec![command.to_string(), "get".to_string(), String::new()];
+            carapace::CarapaceProvider::export_command(
+                &provider.program,
+                command,
+                provider.extra_args(command),
+                &words,
+            )
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect()
+        };
+        assert_eq!(
+            export_args("kubectl"),
+            vec![
+                "kubectl",
+                "export",
+                "kubectl",
+                "--kubeconfig",
+                "/tmp/kube",
+                "get",
+                ""
+            ]
+        );
+        assert_eq!(export_args("git"), vec!["git", "export", "git", "get", ""]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_carapace_timeout() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let slow = dir.path().join("carapace");
+        std::fs::write(&slow, "#!/bin/sh\nexec sleep 5\n").unwrap();
+        std::fs::set_permissions(&slow, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let line = "git chec";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let provider = CarapaceProvider::new()
+            .with_program(Some(slow))
+            .with_timeout(Duration::from_millis(100));
+
+        let started = std::time::Instant::now();
+        assert!(provider.try_complete(&ctx).unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_carapace_program() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let stub = dir.path().join("carapace-stub");
+        std::fs::write(
+            &stub,
+            "#!/bin/sh\necho '{\"values\":[{\"value\":\"checkout\",\"display\":\"checkout\",\"description\":\"Switch branches\"},{\"value\":\"cherry\",\"display\":\"cherry\",\"description\":\"\"}]}'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&stub, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let line = "git chec";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let entries = CarapaceProvider::new()
+            .with_program(Some(stub))
+            .try_complete(&ctx)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].value, "checkout");
+        assert_eq!(entries[0].description.as_deref(), Some("Switch branches"));
+        assert_eq!(entries[1].description, None);
+
+        // A missing binary leaves the word to bash
+        let missing = CarapaceProvider::new().with_program(Some(dir.path().join("nope")));
+        assert!(missing.try_complete(&ctx).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_here_string_and_here_doc() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("input.txt"), "").unwrap();
+        let line = format!("grep foo <<< {}/in", dir.path().display());
+        let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+        assert_eq!(ctx.here_operator, Some(HereOperator::String));
+        assert!(!ctx.suppresses_completion());
+        assert!(!CarapaceProvider::new().should_try(&ctx));
+
+        let result = BashProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec![format!("{}/input.txt", dir.path().display())]);
+
+        for line in ["cat << EO", "cat <<- ", "cat <<EO"] {
+            let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+            let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+            assert!(ctx.suppresses_completion(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_path_search_var_candidates() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("plugin_a.py"), "").unwrap();
+        std::fs::write(dir.path().join("plugin_b.py"), "").unwrap();
+        unsafe { std::env::set_var("BFT_SEARCH_TEST", dir.path()) };
+
+        let search_path = env::var("BFT_SEARCH_TEST").unwrap();
+        let mut candidates = vec!["plugin_a.py".to_string()];
+        add_search_path_candidates(&mut candidates, &search_path, "plug");
+        assert_eq!(candidates, vec!["plugin_a.py", "plugin_b.py"]);
+
+        let provider = BashProvider::new().with_path_search_vars(HashMap::from([(
+            "mytool".to_string(),
+            "BFT_SEARCH_TEST".to_string(),
+        )]));
+        let parsed = parser::parse_shell_line("mytool plugin_b", 15).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "mytool plugin_b".to_string(), 15);
+        let values: Vec<String> = provider
+            .try_complete(&ctx)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(values, vec!["plugin_b.py"]);
+        unsafe { std::env::remove_var("BFT_SEARCH_TEST") };
+    }
+
+    #[test]
+    fn test_redirect_target_completes_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("output.log"), "").unwrap();
+        for op in [">", "2>", "&>", ">>"] {
+            let line = format!("make {} {}/out", op, dir.path().display());
+            let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+            let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+            assert!(ctx.is_redirect_target, "{}", line);
+            assert!(!CarapaceProvider::new().should_try(&ctx));
+
+            let result = BashProvider::new().try_complete(&ctx).unwrap().unwrap();
+            let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+            assert_eq!(values, vec![format!("{}/output.log", dir.path().display())]);
+        }
+
+        let parsed = parser::parse_shell_line("ls 2>&1 | gr", 12).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "ls 2>&1 | gr".to_string(), 12);
+        assert!(!ctx.is_redirect_target);
+    }
+
+    #[test]
+    fn test_compspec_entries_affixes() {
+        let spec = CompletionSpec {
+            prefix: "--".to_string(),
+            suffix: ":".to_string(),
+            ..Default::default()
+        };
+        let entries = compspec_entries(vec!["define".to_string()], &spec);
+        assert_eq!(entries[0].value, "--define:");
+        assert_eq!(entries[0].kind, ProviderKind::Bash);
+        let entries = compspec_entries(vec!["define".to_string()], &CompletionSpec::default());
+        assert_eq!(entries[0].value, "define");
+    }
+
+    #[test]
+    fn test_compspec_files_from_listing_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("setup.py"), "").unwrap();
+        let word = format!("{}/s", dir.path().display());
+        let parsed = create_parsed(vec!["cat".to_string(), word.clone()], 1);
+        let ctx = CompletionContext::from_parsed(&parsed, format!("cat {}", word), word.len() + 4);
+
+        let spec = CompletionSpec {
+            options: CompletionOptions {
+                filenames: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut files = execute_completion(&spec, &ctx, Duration::from_secs(1)).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![format!("{}etup.py", word), format!("{}rc", word)]
+        );
+
+        let spec = CompletionSpec {
+            options: CompletionOptions {
+                dirnames: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let dirs = execute_completion(&spec, &ctx, Duration::from_secs(1)).unwrap();
+        assert_eq!(dirs, vec![format!("{}rc", word)]);
+    }
+
+    #[test]
+    fn test_builtin_command_names() {
+        let entries = command_entries(Vec::new(), "ex");
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["exec", "exit", "export"]);
+        assert!(
+            entries
+                .iter()
+                .all(|e| e.candidate_type == CandidateType::Command
+                    && e.description.as_deref() == Some("builtin"))
+        );
+
+        // Merged with what compgen found, without duplicates
+        let entries = command_entries(vec!["exa".to_string(), "exit".to_string()], "ex");
+        let values: Vec<&str> = entries.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["exa", "exit", "exec", "export"]);
+        assert_eq!(entries[0].description, None);
+        assert_eq!(entries[1].description.as_deref(), Some("builtin"));
+    }
+
+    #[test]
+    fn test_dir_only_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("build")).unwrap();
+        std::fs::write(dir.path().join("build.log"), "").unwrap();
+        let line = format!("rmdir {}/bu", dir.path().display());
+
+        let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+        let provider = BashProvider::new().with_dir_only_commands(vec!["rmdir".to_string()]);
+        let result = provider.try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec![format!("{}/build/", dir.path().display())]);
+        assert_eq!(result[0].candidate_type, CandidateType::Dir);
+    }
+
+    #[test]
+    fn test_array_assignment_offers_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), "").unwrap();
+        std::fs::write(dir.path().join("b.log"), "").unwrap();
+        let element = format!("{}/a.t", dir.path().display());
+        let line = format!("arr=(x.txt {}", element);
+
+        let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+        assert_eq!(ctx.array_assignment.as_deref(), Some("arr"));
+        assert_eq!(ctx.current_word, element);
+        assert_eq!(ctx.current_word_prefix, element);
+        assert!(!CarapaceProvider::new().should_try(&ctx));
+
+        let result = BashProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec![format!("{}/a.txt", dir.path().display())]);
+
+        // Cursor before the closing paren still completes an element
+        let line = format!("arr=({} )", element);
+        let point = line.len() - 2;
+        let parsed = parser::parse_shell_line(&line, point).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), point);
+        assert_eq!(ctx.current_word, element);
+    }
+
     // ... (rest of the tests need to be updated or can be kept if they don't depend on try_complete return type, but here they do)
 
     #[test]
@@ -629,4 +2435,90 @@ mod tests {
 
         unsafe { std::env::remove_var("HISTFILE") };
     }
+
+    struct StaticProvider(Vec<&'static str>);
+
+    impl CompletionProvider for StaticProvider {
+        fn name(&self) -> &str {
+            "static"
+        }
+
+        fn kind(&self) -> ProviderKind {
+            ProviderKind::Unknown
+        }
+
+        fn try_complete(
+            &self,
+            _ctx: &CompletionContext,
+        ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+            Ok(Some(
+                self.0
+                    .iter()
+                    .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Unknown))
+                    .collect(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_pipeline_provider_limit() {
+        let parsed = create_parsed(vec!["ls".to_string(), String::new()], 1);
+        let ctx = CompletionContext::from_parsed(&parsed, "ls ".to_string(), 3);
+
+        let mut pipeline = PipelineProvider::new("test");
+        pipeline
+            .with_limited(Box::new(StaticProvider(vec!["a", "b", "c", "d"])), Some(2))
+            .with(StaticProvider(vec!["x", "y"]));
+
+        let result = pipeline.try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["a", "b", "x", "y"]);
+    }
+
+    #[test]
+    fn test_pipeline_normalizes_before_dedup() {
+        let parsed = create_parsed(vec!["ls".to_string(), String::new()], 1);
+        let ctx = CompletionContext::from_parsed(&parsed, "ls ".to_string(), 3);
+
+        let mut pipeline = PipelineProvider::new("test");
+        pipeline
+            .with_normalization(true)
+            .with(StaticProvider(vec!["foo/", " my file ", "  "]))
+            .with(StaticProvider(vec![
+                "foo//",
+                "a//b///c",
+                "https://example.com",
+            ]));
+
+        let result = pipeline.try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec!["foo/", "my file", "a/b/c", "https://example.com"]
+        );
+    }
+
+    #[test]
+    fn test_pipeline_provider_merge_vs_exclusive() {
+        let parsed = create_parsed(vec!["ls".to_string(), String::new()], 1);
+        let ctx = CompletionContext::from_parsed(&parsed, "ls ".to_string(), 3);
+        let values = |pipeline: &PipelineProvider| -> Vec<String> {
+            let result = pipeline.try_complete(&ctx).unwrap().unwrap();
+            result.into_iter().map(|e| e.value).collect()
+        };
+
+        // Stand-ins for carapace (subcommands) and bash (custom flags)
+        let mut merged = PipelineProvider::new("merged");
+        merged
+            .with(StaticProvider(vec!["build", "run"]))
+            .with(StaticProvider(vec!["run", "--fast"]));
+        assert_eq!(values(&merged), vec!["build", "run", "--fast"]);
+
+        let mut exclusive = PipelineProvider::exclusive("exclusive");
+        exclusive
+            .with(StaticProvider(vec![]))
+            .with(StaticProvider(vec!["build", "run"]))
+            .with(StaticProvider(vec!["--fast"]));
+        assert_eq!(values(&exclusive), vec!["build", "run"]);
+    }
 }
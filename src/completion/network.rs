@@ -0,0 +1,250 @@
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, process,
+};
+
+const DEFAULT_SYSFS_NET: &str = "/sys/class/net";
+
+/// `ip` objects whose verbs take an interface name
+const IP_LINK_OBJECTS: &[&str] = &["link", "l", "address", "addr", "a"];
+/// Verbs of those objects followed by an interface name
+const IP_LINK_VERBS: &[&str] = &["set", "show", "delete", "del", "up", "down", "flush"];
+
+const NMCLI_CONNECTION_OBJECTS: &[&str] = &["connection", "con", "c"];
+const NMCLI_CONNECTION_VERBS: &[&str] =
+    &["up", "down", "show", "modify", "edit", "delete", "clone"];
+const NMCLI_DEVICE_OBJECTS: &[&str] = &["device", "dev", "d"];
+const NMCLI_DEVICE_VERBS: &[&str] = &[
+    "connect",
+    "disconnect",
+    "show",
+    "status",
+    "modify",
+    "reapply",
+    "delete",
+];
+
+/// What kind of name the cursor position expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkValue {
+    Interface,
+    Connection,
+}
+
+/// Completes interface names for `ip`/`nmcli device` (from sysfs) and
+/// NetworkManager connection names for `nmcli connection`. Linux only
+pub struct NetworkProvider {
+    sysfs_net: PathBuf,
+    timeout: Duration,
+}
+
+impl Default for NetworkProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetworkProvider {
+    pub fn new() -> Self {
+        Self {
+            sysfs_net: PathBuf::from(DEFAULT_SYSFS_NET),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Read interfaces from `dir` instead of `/sys/class/net`
+    pub fn with_sysfs_net(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.sysfs_net = dir;
+        }
+        self
+    }
+
+    /// Kill `nmcli` after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for NetworkProvider {
+    fn name(&self) -> &'static str {
+        "network"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Network
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        cfg!(target_os = "linux")
+            && (ctx.command == "ip" || ctx.command == "nmcli")
+            && ctx.current_word_idx > 0
+            && !ctx.current_word.starts_with('-')
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let values = match value_kind(ctx) {
+            Some(NetworkValue::Interface) => list_interfaces(&self.sysfs_net),
+            Some(NetworkValue::Connection) => match list_connections(self.timeout) {
+                Some(names) => names,
+                None => return Ok(None),
+            },
+            None => return Ok(None),
+        };
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|v| v.starts_with(&ctx.current_word))
+                .map(|v| {
+                    CompletionEntry::new(v, ProviderKind::Network).with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Decide which names apply at the cursor: the word after `dev`, or the
+/// first argument of an interface/connection verb
+pub fn value_kind(ctx: &CompletionContext) -> Option<NetworkValue> {
+    if ctx.command == "ip" && ctx.previous_word.as_deref() == Some("dev") {
+        return Some(NetworkValue::Interface);
+    }
+
+    let positional: Vec<&str> = ctx.words[1..ctx.current_word_idx]
+        .iter()
+        .map(String::as_str)
+        .filter(|w| !w.starts_with('-'))
+        .collect();
+    let [object, verb] = positional[..] else {
+        return None;
+    };
+    match ctx.command.as_str() {
+        "ip" if IP_LINK_OBJECTS.contains(&object) && IP_LINK_VERBS.contains(&verb) => {
+            Some(NetworkValue::Interface)
+        }
+        "nmcli"
+            if NMCLI_CONNECTION_OBJECTS.contains(&object)
+                && NMCLI_CONNECTION_VERBS.contains(&verb) =>
+        {
+            Some(NetworkValue::Connection)
+        }
+        "nmcli" if NMCLI_DEVICE_OBJECTS.contains(&object) && NMCLI_DEVICE_VERBS.contains(&verb) => {
+            Some(NetworkValue::Interface)
+        }
+        _ => None,
+    }
+}
+
+/// Interface names: the entries of the sysfs net directory, sorted
+pub fn list_interfaces(sysfs_net: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(sysfs_net) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("[network] cannot read {}: {}", sysfs_net.display(), e);
+            return Vec::new();
+        }
+    };
+    let mut names: Vec<String> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    names.sort();
+    names
+}
+
+fn list_connections(timeout: Duration) -> Option<Vec<String>> {
+    let mut command = Command::new("nmcli");
+    command.args(["-t", "-f", "NAME", "connection", "show"]);
+    let output = process::run_with_timeout(&mut command, None, timeout)?;
+    if !output.status.success() {
+        debug!(
+            "[network] nmcli failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return None;
+    }
+    Some(parse_connection_names(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// One name per line as printed by `nmcli -t`, which escapes `:` as `\:`
+pub fn parse_connection_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.replace("\\:", ":"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_interfaces_from_sysfs() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["lo", "eth0", "wlan0"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        assert_eq!(list_interfaces(dir.path()), vec!["eth0", "lo", "wlan0"]);
+
+        let kind = |line: &str| value_kind(&ctx_for(line));
+        assert_eq!(kind("ip link set "), Some(NetworkValue::Interface));
+        assert_eq!(kind("ip -4 addr show e"), Some(NetworkValue::Interface));
+        assert_eq!(
+            kind("ip route add default dev "),
+            Some(NetworkValue::Interface)
+        );
+        assert_eq!(
+            kind("nmcli device disconnect "),
+            Some(NetworkValue::Interface)
+        );
+        assert_eq!(kind("ip link set eth0 "), None);
+        assert_eq!(kind("ip route "), None);
+
+        if cfg!(target_os = "linux") {
+            let provider = NetworkProvider::new().with_sysfs_net(Some(dir.path().to_path_buf()));
+            let ctx = ctx_for("ip link set e");
+            assert!(provider.should_try(&ctx));
+            let values: Vec<String> = provider
+                .try_complete(&ctx)
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|e| e.value)
+                .collect();
+            assert_eq!(values, vec!["eth0"]);
+        }
+    }
+
+    #[test]
+    fn test_nmcli_connections() {
+        let stubbed = "Wired connection 1\nhome\\:5G\n\nvpn-work\n";
+        assert_eq!(
+            parse_connection_names(stubbed),
+            vec!["Wired connection 1", "home:5G", "vpn-work"]
+        );
+        assert_eq!(
+            value_kind(&ctx_for("nmcli connection up ")),
+            Some(NetworkValue::Connection)
+        );
+        assert_eq!(
+            value_kind(&ctx_for("nmcli -t c down v")),
+            Some(NetworkValue::Connection)
+        );
+        assert_eq!(value_kind(&ctx_for("nmcli connection add ")), None);
+    }
+}
@@ -0,0 +1,359 @@
+use log::debug;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, cache::default_cache_dir, files, process,
+};
+
+/// How long a cached list of available packages is reused
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a cached list of installed packages is reused, unless the package
+/// database changes first
+pub const INSTALLED_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Where Homebrew installs formulae when `HOMEBREW_CELLAR` is not set
+const BREW_CELLARS: &[&str] = &[
+    "/opt/homebrew/Cellar",
+    "/usr/local/Cellar",
+    "/home/linuxbrew/.linuxbrew/Cellar",
+];
+
+/// dpkg's record of installed packages
+const DPKG_STATUS: &str = "/var/lib/dpkg/status";
+
+/// Package managers and their subcommands by the packages they act on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageManager {
+    Brew,
+    Apt,
+}
+
+/// Which packages a subcommand takes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageList {
+    Installed,
+    Available,
+}
+
+impl PackageManager {
+    fn from_command(command: &str) -> Option<Self> {
+        match command {
+            "brew" => Some(PackageManager::Brew),
+            "apt" | "apt-get" | "apt-cache" => Some(PackageManager::Apt),
+            _ => None,
+        }
+    }
+
+    fn list_for(&self, subcommand: &str) -> Option<PackageList> {
+        let (installed, available): (&[&str], &[&str]) = match self {
+            PackageManager::Brew => (
+                &[
+                    "uninstall",
+                    "remove",
+                    "rm",
+                    "upgrade",
+                    "reinstall",
+                    "link",
+                    "unlink",
+                    "pin",
+                    "unpin",
+                ],
+                &["install", "info", "home", "fetch", "deps", "desc"],
+            ),
+            PackageManager::Apt => (
+                &["remove", "purge", "reinstall", "autoremove"],
+                &["install", "show", "download", "source", "policy", "depends"],
+            ),
+        };
+        if installed.contains(&subcommand) {
+            Some(PackageList::Installed)
+        } else if available.contains(&subcommand) {
+            Some(PackageList::Available)
+        } else {
+            None
+        }
+    }
+
+    /// Command printing one package name per line
+    fn list_command(&self, list: PackageList) -> (&'static str, &'static [&'static str]) {
+        match (self, list) {
+            (PackageManager::Brew, PackageList::Installed) => ("brew", &["list", "-1"]),
+            (PackageManager::Brew, PackageList::Available) => ("brew", &["formulae"]),
+            (PackageManager::Apt, PackageList::Installed) => {
+                ("dpkg-query", &["-W", "-f=${Package}\\n"])
+            }
+            (PackageManager::Apt, PackageList::Available) => ("apt-cache", &["pkgnames"]),
+        }
+    }
+
+    fn cache_name(&self, list: PackageList) -> &'static str {
+        match (self, list) {
+            (PackageManager::Brew, PackageList::Installed) => "brew-installed.txt",
+            (PackageManager::Brew, PackageList::Available) => "brew-available.txt",
+            (PackageManager::Apt, PackageList::Installed) => "apt-installed.txt",
+            (PackageManager::Apt, PackageList::Available) => "apt-available.txt",
+        }
+    }
+
+    /// The file or directory modified by every install and removal: the
+    /// Cellar for Homebrew, dpkg's status file for apt
+    fn database(&self) -> Option<PathBuf> {
+        match self {
+            PackageManager::Brew => env::var_os("HOMEBREW_CELLAR")
+                .map(PathBuf::from)
+                .into_iter()
+                .chain(BREW_CELLARS.iter().map(PathBuf::from))
+                .find(|path| path.is_dir()),
+            PackageManager::Apt => Some(PathBuf::from(DPKG_STATUS)).filter(|path| path.exists()),
+        }
+    }
+}
+
+/// Completes installed packages for `brew uninstall`/`apt remove` and
+/// available ones for `brew install`/`apt install`
+pub struct PackagesProvider {
+    /// Directory holding the cached package lists
+    cache_dir: Option<PathBuf>,
+    cache_ttl: Duration,
+    installed_ttl: Duration,
+    timeout: Duration,
+}
+
+impl Default for PackagesProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PackagesProvider {
+    pub fn new() -> Self {
+        Self {
+            cache_dir: default_cache_dir(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            installed_ttl: INSTALLED_CACHE_TTL,
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Cache package lists in `dir` instead of `~/.cache/bft`
+    pub fn with_cache_dir(mut self, dir: Option<PathBuf>) -> Self {
+        if dir.is_some() {
+            self.cache_dir = dir;
+        }
+        self
+    }
+
+    /// Refresh cached lists of available packages older than this
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = ttl;
+        self
+    }
+
+    /// Kill the listing command after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The package list, from the cache when fresh. Installed packages change
+    /// with every install, so their list is only kept briefly and dropped
+    /// as soon as the package database is modified after it
+    fn packages(&self, manager: PackageManager, list: PackageList) -> Option<Vec<String>> {
+        let cache = self
+            .cache_dir
+            .as_ref()
+            .map(|d| d.join(manager.cache_name(list)));
+        let (ttl, database) = match list {
+            PackageList::Available => (self.cache_ttl, None),
+            PackageList::Installed => (self.installed_ttl, manager.database()),
+        };
+        if let Some(path) = &cache
+            && let Some(content) = read_fresh(path, ttl, database.as_deref())
+        {
+            return Some(parse_package_names(&content));
+        }
+
+        let (program, args) = manager.list_command(list);
+        let mut command = Command::new(program);
+        command.args(args);
+        let output = process::run_with_timeout(&mut command, None, self.timeout)?;
+        if !output.status.success() {
+            debug!(
+                "[packages] {} {:?} exited with {}",
+                program, args, output.status
+            );
+            return None;
+        }
+        let content = String::from_utf8_lossy(&output.stdout);
+        if let Some(path) = &cache {
+            write_cache(path, &content);
+        }
+        Some(parse_package_names(&content))
+    }
+}
+
+impl CompletionProvider for PackagesProvider {
+    fn name(&self) -> &'static str {
+        "packages"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Packages
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 1
+            && !ctx.current_word.starts_with('-')
+            && PackageManager::from_command(&ctx.command).is_some()
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some((manager, list)) = package_list(ctx) else {
+            return Ok(None);
+        };
+        let Some(packages) = self.packages(manager, list) else {
+            return Ok(None);
+        };
+        Ok(Some(
+            packages
+                .into_iter()
+                .filter(|p| p.starts_with(&ctx.current_word))
+                .map(|p| {
+                    CompletionEntry::new(p, ProviderKind::Packages).with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// The package manager and the list its subcommand (the first non-option
+/// word) takes
+pub fn package_list(ctx: &CompletionContext) -> Option<(PackageManager, PackageList)> {
+    let manager = PackageManager::from_command(&ctx.command)?;
+    let subcommand = ctx.words[1..ctx.current_word_idx]
+        .iter()
+        .find(|w| !w.starts_with('-'))?;
+    Some((manager, manager.list_for(subcommand)?))
+}
+
+/// One package per line; `brew list` may print headers for casks
+pub fn parse_package_names(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("==>"))
+        .map(str::to_string)
+        .collect()
+}
+
+/// The cache at `path` when younger than `ttl` and not older than the last
+/// change to `database`
+fn read_fresh(path: &Path, ttl: Duration, database: Option<&Path>) -> Option<String> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let age = SystemTime::now()
+        .duration_since(modified)
+        .unwrap_or_default();
+    if age >= ttl {
+        debug!("[packages] cache {} is stale", path.display());
+        return None;
+    }
+    if let Some(database) = database
+        && let Ok(changed) = fs::metadata(database).and_then(|m| m.modified())
+        && changed >= modified
+    {
+        debug!(
+            "[packages] {} changed since {} was cached",
+            database.display(),
+            path.display()
+        );
+        return None;
+    }
+    fs::read_to_string(path).ok()
+}
+
+fn write_cache(path: &Path, content: &str) {
+    if let Err(e) = files::write_atomically(path, content) {
+        debug!("[packages] cannot write {}: {}", path.display(), e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_install_vs_uninstall() {
+        let list = |line: &str| package_list(&ctx_for(line));
+        use PackageList::*;
+        use PackageManager::*;
+        assert_eq!(list("brew install "), Some((Brew, Available)));
+        assert_eq!(list("brew uninstall "), Some((Brew, Installed)));
+        assert_eq!(list("brew --verbose upgrade wg"), Some((Brew, Installed)));
+        assert_eq!(list("apt-get -y install ng"), Some((Apt, Available)));
+        assert_eq!(list("apt purge "), Some((Apt, Installed)));
+        assert_eq!(list("brew doctor "), None);
+        assert_eq!(list("brew "), None);
+
+        let stubbed = "==> Formulae\nwget\njq\n\n";
+        assert_eq!(parse_package_names(stubbed), vec!["wget", "jq"]);
+    }
+
+    #[test]
+    fn test_available_list_from_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("brew-available.txt"),
+            "ripgrep\nrbenv\nwget\n",
+        )
+        .unwrap();
+        let provider = PackagesProvider::new().with_cache_dir(Some(dir.path().to_path_buf()));
+
+        let ctx = ctx_for("brew install r");
+        assert!(provider.should_try(&ctx));
+        let values: Vec<String> = provider
+            .try_complete(&ctx)
+            .unwrap()
+            .unwrap()
+            .into_iter()
+            .map(|e| e.value)
+            .collect();
+        assert_eq!(values, vec!["ripgrep", "rbenv"]);
+
+        // A stale cache is not used
+        let cached = dir.path().join("brew-available.txt");
+        assert!(read_fresh(&cached, Duration::ZERO, None).is_none());
+    }
+
+    #[test]
+    fn test_installed_list_cached_until_database_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let database = dir.path().join("status");
+        fs::write(&database, "").unwrap();
+        let cached = dir.path().join("apt-installed.txt");
+        write_cache(&cached, "curl\ngit\n");
+
+        let past = SystemTime::now() - Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&database)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        let content = read_fresh(&cached, INSTALLED_CACHE_TTL, Some(&database)).unwrap();
+        assert_eq!(parse_package_names(&content), vec!["curl", "git"]);
+
+        // An install after the list was cached invalidates it
+        fs::write(&database, "Package: jq\n").unwrap();
+        assert!(read_fresh(&cached, INSTALLED_CACHE_TTL, Some(&database)).is_none());
+    }
+}
@@ -0,0 +1,88 @@
+use log::debug;
+use std::env;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Subprocess timeout of providers built without an explicit one
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Where `program` would be run from: itself when it is a path, else the
+/// first match on `PATH`
+pub fn find_program(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Run `command` (with `input` on stdin, if any) and collect its output, or
+/// `None` if it cannot be started or is killed after `timeout`
+pub fn run_with_timeout(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Duration,
+) -> Option<Output> {
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| debug!("[process] cannot spawn {:?}: {}", command.get_program(), e))
+        .ok()?;
+
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input)
+        && let Err(e) = stdin.write_all(input)
+    {
+        debug!("[process] cannot write stdin: {}", e);
+    }
+
+    // Drain the pipes concurrently so a chatty child cannot block on a full pipe
+    let stdout = drain(child.stdout.take());
+    let stderr = drain(child.stderr.take());
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                debug!(
+                    "[process] killing {:?} after {:?}",
+                    command.get_program(),
+                    timeout
+                );
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+
+    Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn drain(pipe: Option<impl Read + Send + 'static>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
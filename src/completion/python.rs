@@ -0,0 +1,282 @@
+use log::debug;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, files, process,
+};
+
+/// Upper bound on modules collected from `sys.path`
+const MAX_MODULES: usize = 5000;
+
+const PRINT_SYS_PATH: &str = "import sys; print('\\n'.join(sys.path))";
+
+/// Completes `python -m` module names and `pip install` package names
+pub struct PythonProvider {
+    /// File with one package name per line, offered for `pip install`
+    package_cache: Option<PathBuf>,
+    /// Interpreters taking `-m` besides `python`, `python3` and `python3.N`
+    interpreters: Vec<String>,
+    timeout: Duration,
+}
+
+impl Default for PythonProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PythonProvider {
+    pub fn new() -> Self {
+        Self {
+            package_cache: None,
+            interpreters: Vec::new(),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Offer the package names listed in `path` for `pip install`
+    pub fn with_package_cache(mut self, path: Option<PathBuf>) -> Self {
+        self.package_cache = path;
+        self
+    }
+
+    /// Also complete `-m` modules for these interpreters (e.g. `pypy3`)
+    pub fn with_interpreters(mut self, interpreters: Vec<String>) -> Self {
+        self.interpreters = interpreters;
+        self
+    }
+
+    /// `<interpreter> -m <module>`
+    fn is_module_position(&self, ctx: &CompletionContext) -> bool {
+        (is_python_interpreter(&ctx.command) || self.interpreters.contains(&ctx.command))
+            && ctx.previous_word.as_deref() == Some("-m")
+    }
+
+    fn modules(&self, python: &str) -> Vec<String> {
+        let mut command = Command::new(python);
+        command
+            .current_dir(files::working_dir())
+            .args(["-c", PRINT_SYS_PATH]);
+        let output = match process::run_with_timeout(&mut command, None, self.timeout) {
+            Some(o) if o.status.success() => o,
+            Some(_) => return Vec::new(),
+            None => {
+                debug!("[python] {} failed to run or timed out", python);
+                return Vec::new();
+            }
+        };
+        let dirs: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    files::working_dir()
+                } else {
+                    files::resolve_path(line)
+                }
+            })
+            .collect();
+        discover_modules(&dirs, MAX_MODULES)
+    }
+
+    fn packages(&self) -> Vec<String> {
+        let Some(path) = &self.package_cache else {
+            return Vec::new();
+        };
+        match fs::read_to_string(shellexpand::tilde(&path.to_string_lossy()).as_ref()) {
+            Ok(content) => content
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(str::to_string)
+                .collect(),
+            Err(e) => {
+                debug!(
+                    "[python] cannot read package cache {}: {}",
+                    path.display(),
+                    e
+                );
+                Vec::new()
+            }
+        }
+    }
+}
+
+impl CompletionProvider for PythonProvider {
+    fn name(&self) -> &'static str {
+        "python"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Python
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        self.is_module_position(ctx) || is_pip_install(ctx)
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let values = if self.is_module_position(ctx) {
+            self.modules(&ctx.command)
+        } else if is_pip_install(ctx) {
+            self.packages()
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|v| v.starts_with(&ctx.current_word))
+                .map(|v| {
+                    CompletionEntry::new(v, ProviderKind::Python).with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// `python`, `python3` or a versioned `python3.N`, but not other commands
+/// starting with `python` (`python3-config`, `pythonw`)
+pub fn is_python_interpreter(command: &str) -> bool {
+    match command.strip_prefix("python3") {
+        Some("") => true,
+        Some(version) => version
+            .strip_prefix('.')
+            .is_some_and(|minor| !minor.is_empty() && minor.chars().all(|c| c.is_ascii_digit())),
+        None => command == "python",
+    }
+}
+
+/// A package argument of `pip install` / `pip3 install`
+fn is_pip_install(ctx: &CompletionContext) -> bool {
+    (ctx.command == "pip" || ctx.command == "pip3")
+        && ctx.current_word_idx > 1
+        && ctx.words.get(1).is_some_and(|w| w == "install")
+        && !ctx.current_word.starts_with('-')
+}
+
+/// Top-level importable modules found in `dirs`: `.py` files, packages and
+/// extension modules, collecting at most `limit` names
+pub fn discover_modules(dirs: &[PathBuf], limit: usize) -> Vec<String> {
+    let mut modules = BTreeSet::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if modules.len() >= limit {
+                return modules.into_iter().collect();
+            }
+            if let Some(name) = module_name(&entry.path()) {
+                modules.insert(name);
+            }
+        }
+    }
+    modules.into_iter().collect()
+}
+
+fn module_name(path: &Path) -> Option<String> {
+    let file_name = path.file_name()?.to_str()?;
+    let name = if path.is_dir() {
+        path.join("__init__.py").is_file().then_some(file_name)?
+    } else if let Some(stem) = file_name.strip_suffix(".py") {
+        stem
+    } else if file_name.ends_with(".so") || file_name.ends_with(".pyd") {
+        // e.g. `_ssl.cpython-312-x86_64-linux-gnu.so`
+        file_name.split('.').next()?
+    } else {
+        return None;
+    };
+
+    let is_identifier = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+    (is_identifier && !name.starts_with("__")).then(|| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_discover_modules() {
+        let site = tempdir().unwrap();
+        let stdlib = tempdir().unwrap();
+        fs::write(stdlib.path().join("json.py"), "").unwrap();
+        fs::write(stdlib.path().join("__future__.py"), "").unwrap();
+        fs::write(
+            stdlib.path().join("_ssl.cpython-312-x86_64-linux-gnu.so"),
+            "",
+        )
+        .unwrap();
+        fs::create_dir(site.path().join("requests")).unwrap();
+        fs::write(site.path().join("requests/__init__.py"), "").unwrap();
+        fs::create_dir(site.path().join("requests-2.31.0.dist-info")).unwrap();
+        fs::write(site.path().join("README.txt"), "").unwrap();
+
+        let dirs = vec![
+            stdlib.path().to_path_buf(),
+            site.path().to_path_buf(),
+            PathBuf::from("/nonexistent"),
+        ];
+        assert_eq!(
+            discover_modules(&dirs, 100),
+            vec!["_ssl", "json", "requests"]
+        );
+        assert_eq!(discover_modules(&dirs, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_python_interpreters() {
+        for command in ["python", "python3", "python3.12"] {
+            assert!(is_python_interpreter(command), "{}", command);
+        }
+        for command in [
+            "python3-config",
+            "python3.",
+            "python3.x",
+            "pythonw",
+            "python2",
+        ] {
+            assert!(!is_python_interpreter(command), "{}", command);
+        }
+
+        let ctx = ctx_for("pypy3 -m ");
+        assert!(!PythonProvider::new().should_try(&ctx));
+        let provider = PythonProvider::new().with_interpreters(vec!["pypy3".to_string()]);
+        assert!(provider.should_try(&ctx));
+    }
+
+    #[test]
+    fn test_pip_packages_from_cache() {
+        let dir = tempdir().unwrap();
+        let cache = dir.path().join("packages.txt");
+        fs::write(&cache, "requests\nrich\nnumpy\n").unwrap();
+
+        let ctx = ctx_for("pip install ri");
+
+        let provider = PythonProvider::new().with_package_cache(Some(cache));
+        assert!(provider.should_try(&ctx));
+        let result = provider.try_complete(&ctx).unwrap().unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].value, "rich");
+    }
+}
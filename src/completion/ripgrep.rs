@@ -0,0 +1,167 @@
+use log::debug;
+use std::cell::OnceCell;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, process,
+};
+
+const RG: &str = "rg";
+
+/// Options of `rg` whose value is a file type name
+const TYPE_FLAGS: &[&str] = &["-t", "--type", "-T", "--type-not"];
+
+/// Completes file type names for `rg -t`/`--type`/`-T`/`--type-not`
+pub struct RipgrepProvider {
+    /// `rg --type-list` output, queried at most once (`None` if rg is unavailable)
+    types: OnceCell<Option<Vec<(String, String)>>>,
+    timeout: Duration,
+}
+
+impl Default for RipgrepProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RipgrepProvider {
+    pub fn new() -> Self {
+        Self {
+            types: OnceCell::new(),
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    fn types(&self) -> Option<&Vec<(String, String)>> {
+        self.types
+            .get_or_init(|| {
+                let mut command = Command::new(RG);
+                command.arg("--type-list");
+                let output = match process::run_with_timeout(&mut command, None, self.timeout) {
+                    Some(o) if o.status.success() => o,
+                    Some(_) => return None,
+                    None => {
+                        debug!("[ripgrep] rg failed to run or timed out");
+                        return None;
+                    }
+                };
+                Some(parse_type_list(&String::from_utf8_lossy(&output.stdout)))
+            })
+            .as_ref()
+    }
+}
+
+impl CompletionProvider for RipgrepProvider {
+    fn name(&self) -> &'static str {
+        "ripgrep"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ripgrep
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == RG && type_prefix(ctx).is_some()
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let Some((flag, prefix)) = type_prefix(ctx) else {
+            return Ok(None);
+        };
+        let Some(types) = self.types() else {
+            return Ok(None);
+        };
+
+        Ok(Some(
+            types
+                .iter()
+                .filter(|(name, _)| name.starts_with(prefix))
+                .map(|(name, globs)| {
+                    CompletionEntry::new(format!("{}{}", flag, name), ProviderKind::Ripgrep)
+                        .with_type(CandidateType::Value)
+                        .with_description(globs.clone())
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// For a type-name position, the text to keep before the name (`--type=` or
+/// nothing) and the typed part of the name
+fn type_prefix(ctx: &CompletionContext) -> Option<(&'static str, &str)> {
+    for flag in ["--type=", "--type-not="] {
+        if let Some(prefix) = ctx.current_word.strip_prefix(flag) {
+            return Some((flag, prefix));
+        }
+    }
+    let previous = ctx.previous_word.as_deref()?;
+    TYPE_FLAGS
+        .contains(&previous)
+        .then_some(("", ctx.current_word.as_str()))
+}
+
+/// Parse `rg --type-list` output (`name: glob, glob`) into (name, globs) pairs
+pub fn parse_type_list(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, globs) = line.split_once(':')?;
+            Some((name.trim().to_string(), globs.trim().to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{ctx_for, values_for};
+
+    const TYPE_LIST: &str = "\
+py: *.py, *.pyi
+r: *.R, *.r, *.Rmd
+ruby: *.rb, Gemfile
+rust: *.rs
+";
+
+    fn stubbed() -> RipgrepProvider {
+        let provider = RipgrepProvider::new();
+        let _ = provider.types.set(Some(parse_type_list(TYPE_LIST)));
+        provider
+    }
+
+    #[test]
+    fn test_parse_type_list() {
+        let types = parse_type_list(TYPE_LIST);
+        assert_eq!(types.len(), 4);
+        assert_eq!(types[1], ("r".to_string(), "*.R, *.r, *.Rmd".to_string()));
+    }
+
+    #[test]
+    fn test_type_names() {
+        let provider = stubbed();
+        assert_eq!(
+            values_for(&provider, "rg -t ru").unwrap_or_default(),
+            vec!["ruby", "rust"]
+        );
+        assert_eq!(
+            values_for(&provider, "rg foo -T p").unwrap_or_default(),
+            vec!["py"]
+        );
+        assert_eq!(
+            values_for(&provider, "rg --type=ru").unwrap_or_default(),
+            vec!["--type=ruby", "--type=rust"]
+        );
+        assert!(!stubbed().should_try(&ctx_for("rg -i ")));
+    }
+}
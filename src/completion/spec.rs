@@ -0,0 +1,306 @@
+use log::debug;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, cluster_candidates,
+};
+
+/// One level of a declarative command tree, read from `<command>.json5`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CommandSpec {
+    pub name: String,
+    pub description: Option<String>,
+    pub flags: Vec<String>,
+    /// Enumerated values of flags, completed after `--flag=` or `--flag `
+    pub flag_values: HashMap<String, Vec<String>>,
+    pub subcommands: Vec<CommandSpec>,
+}
+
+impl CommandSpec {
+    /// Follow the subcommand words before the cursor down the tree; `None`
+    /// once a word is not a known subcommand (i.e. it is a plain argument)
+    pub fn descend<'a>(&'a self, words: &[String]) -> Option<&'a CommandSpec> {
+        let mut node = self;
+        let mut words = words.iter();
+        while let Some(word) = words.next() {
+            if word.starts_with('-') {
+                // The value of an enumerated flag is not a subcommand
+                if node.flag_values.contains_key(word) {
+                    words.next();
+                }
+                continue;
+            }
+            node = node.subcommands.iter().find(|sub| &sub.name == word)?;
+        }
+        Some(node)
+    }
+
+    /// Letters of the single-letter flags (`-x`), which can be clustered
+    fn short_flags(&self) -> String {
+        self.flags
+            .iter()
+            .filter_map(|f| {
+                let mut chars = f.strip_prefix('-')?.chars();
+                let letter = chars.next()?;
+                (chars.next().is_none() && letter != '-').then_some(letter)
+            })
+            .collect()
+    }
+
+    /// The flag values at the cursor: the text kept before the value
+    /// (`--flag=` or nothing), the values and the typed part of the value
+    fn values_at<'a>(
+        &'a self,
+        ctx: &'a CompletionContext,
+    ) -> Option<(&'a str, &'a [String], &'a str)> {
+        let word = ctx.current_word.as_str();
+        if let Some((flag, typed)) = word.split_once('=')
+            && let Some(values) = self.flag_values.get(flag)
+        {
+            return Some((&word[..flag.len() + 1], values, typed));
+        }
+        let values = self.flag_values.get(ctx.previous_word.as_deref()?)?;
+        Some(("", values, word))
+    }
+}
+
+/// Completes subcommands and flags of commands described by spec files
+pub struct SpecProvider {
+    dir: PathBuf,
+}
+
+impl Default for SpecProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpecProvider {
+    pub fn new() -> Self {
+        Self {
+            dir: default_spec_dir(),
+        }
+    }
+
+    /// Directory holding one `<command>.json5` spec per command, or
+    /// `<command>-<subcommand>.json5` per subcommand of a dispatcher
+    pub fn with_dir(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.dir = dir;
+        }
+        self
+    }
+
+    fn load(&self, command: &str) -> Option<CommandSpec> {
+        if command.is_empty() || command.contains('/') {
+            return None;
+        }
+        let path = self.dir.join(format!("{}.json5", command));
+        let content = fs::read_to_string(&path).ok()?;
+        match json5::from_str(&content) {
+            Ok(spec) => Some(spec),
+            Err(e) => {
+                debug!("[spec] invalid spec {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+impl CompletionProvider for SpecProvider {
+    fn name(&self) -> &'static str {
+        "spec"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Spec
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        // A `<command>-<subcommand>.json5` spec describes the tree below the
+        // subcommand; otherwise the whole command's spec is used
+        let scoped = ctx.subcommand.as_ref().and_then(|sub| {
+            let spec = self.load(&sub.command.replace(' ', "-"))?;
+            Some((spec, sub.offset + 1))
+        });
+        let Some((spec, first_arg)) = scoped.or_else(|| Some((self.load(&ctx.command)?, 1))) else {
+            return Ok(None);
+        };
+        let Some(node) = spec.descend(&ctx.words[first_arg..ctx.current_word_idx]) else {
+            return Ok(None);
+        };
+
+        let entries = if let Some((kept, values, typed)) = node.values_at(ctx) {
+            values
+                .iter()
+                .filter(|v| v.starts_with(typed))
+                .map(|v| {
+                    CompletionEntry::new(format!("{}{}", kept, v), ProviderKind::Spec)
+                        .with_type(CandidateType::Value)
+                })
+                .collect()
+        } else if ctx.current_word.starts_with('-') {
+            let flags = node
+                .flags
+                .iter()
+                .filter(|f| f.starts_with(&ctx.current_word))
+                .map(|f| {
+                    CompletionEntry::new(f.clone(), ProviderKind::Spec)
+                        .with_type(CandidateType::Flag)
+                });
+            let clusters =
+                cluster_candidates(&ctx.current_word, &node.short_flags(), ProviderKind::Spec);
+            flags
+                .chain(clusters)
+                .fold(Vec::new(), |mut entries: Vec<CompletionEntry>, entry| {
+                    if !entries.iter().any(|e| e.value == entry.value) {
+                        entries.push(entry);
+                    }
+                    entries
+                })
+        } else {
+            node.subcommands
+                .iter()
+                .filter(|sub| sub.name.starts_with(&ctx.current_word))
+                .map(|sub| {
+                    let entry = CompletionEntry::new(sub.name.clone(), ProviderKind::Spec)
+                        .with_type(CandidateType::Command);
+                    match &sub.description {
+                        Some(description) => entry.with_description(description.clone()),
+                        None => entry,
+                    }
+                })
+                .collect()
+        };
+        Ok(Some(entries))
+    }
+}
+
+/// `$XDG_CONFIG_HOME/bft/specs`, next to the config file
+pub fn default_spec_dir() -> PathBuf {
+    let config_home = env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        format!("{}/.config", home)
+    });
+    PathBuf::from(config_home).join("bft/specs")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    const AWS_SPEC: &str = r#"{
+        name: "aws",
+        flags: ["--region", "--profile", "--color", "--output", "-v", "-q", "-d"],
+        flag_values: {
+            "--color": ["auto", "always", "never"],
+            "--output": ["json", "yaml", "text", "table"],
+        },
+        subcommands: [
+            {
+                name: "s3",
+                subcommands: [
+                    { name: "cp", description: "Copy objects", flags: ["--recursive"] },
+                    { name: "ls" },
+                ],
+            },
+            { name: "sts", subcommands: [{ name: "get-caller-identity" }] },
+        ],
+    }"#;
+
+    fn values(dir: &std::path::Path, line: &str) -> Option<Vec<String>> {
+        let ctx = ctx_for(line);
+        SpecProvider::new()
+            .with_dir(Some(dir.to_path_buf()))
+            .try_complete(&ctx)
+            .unwrap()
+            .map(|entries| entries.into_iter().map(|e| e.value).collect())
+    }
+
+    #[test]
+    fn test_nested_subcommands() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("aws.json5"), AWS_SPEC).unwrap();
+
+        assert_eq!(
+            values(dir.path(), "aws s"),
+            Some(vec!["s3".into(), "sts".into()])
+        );
+        assert_eq!(
+            values(dir.path(), "aws --debug s3 "),
+            Some(vec!["cp".into(), "ls".into()])
+        );
+        assert_eq!(
+            values(dir.path(), "aws s3 cp --r"),
+            Some(vec!["--recursive".into()])
+        );
+        assert_eq!(
+            values(dir.path(), "aws --color="),
+            Some(vec![
+                "--color=auto".into(),
+                "--color=always".into(),
+                "--color=never".into()
+            ])
+        );
+        assert_eq!(
+            values(dir.path(), "aws --output t"),
+            Some(vec!["text".into(), "table".into()])
+        );
+        assert_eq!(
+            values(dir.path(), "aws -v"),
+            Some(vec!["-v".into(), "-vq".into(), "-vd".into()])
+        );
+        // A flag value does not end the subcommand path
+        assert_eq!(
+            values(dir.path(), "aws --output json s3 "),
+            Some(vec!["cp".into(), "ls".into()])
+        );
+        // Past the tree (a plain argument) and unknown commands are left to others
+        assert_eq!(values(dir.path(), "aws s3 cp src "), None);
+        assert_eq!(values(dir.path(), "gcloud "), None);
+    }
+
+    #[test]
+    fn test_subcommand_spec() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("docker-run.json5"),
+            r#"{ name: "run", flags: ["--rm", "--restart"], flag_values: { "--restart": ["no", "always"] } }"#,
+        )
+        .unwrap();
+        let dispatchers = vec!["docker".to_string()];
+        let values = |line: &str| {
+            let ctx = ctx_for(line).with_subcommand_dispatchers(&dispatchers);
+            SpecProvider::new()
+                .with_dir(Some(dir.path().to_path_buf()))
+                .try_complete(&ctx)
+                .unwrap()
+                .map(|entries| entries.into_iter().map(|e| e.value).collect::<Vec<_>>())
+        };
+
+        assert_eq!(
+            values("docker run --r"),
+            Some(vec!["--rm".into(), "--restart".into()])
+        );
+        assert_eq!(
+            values("docker run --restart a"),
+            Some(vec!["always".into()])
+        );
+        // Other subcommands have no spec
+        assert_eq!(values("docker ps --"), None);
+    }
+}
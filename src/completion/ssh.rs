@@ -0,0 +1,655 @@
+use log::debug;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, RemotePath, process,
+};
+
+const SSH_COMMANDS: &[&str] = &["ssh", "scp", "sftp"];
+
+/// Ports offered after `-p`/`-P`
+const COMMON_PORTS: &[&str] = &["22", "2222"];
+
+/// Option keys accepted by `ssh -o`
+pub const OPTION_KEYS: &[&str] = &[
+    "AddKeysToAgent",
+    "BatchMode",
+    "BindAddress",
+    "CheckHostIP",
+    "Compression",
+    "ConnectTimeout",
+    "ControlMaster",
+    "ControlPath",
+    "ControlPersist",
+    "ForwardAgent",
+    "ForwardX11",
+    "HostKeyAlgorithms",
+    "HostName",
+    "IdentitiesOnly",
+    "IdentityFile",
+    "LogLevel",
+    "PasswordAuthentication",
+    "Port",
+    "PreferredAuthentications",
+    "ProxyCommand",
+    "ProxyJump",
+    "PubkeyAuthentication",
+    "RequestTTY",
+    "SendEnv",
+    "ServerAliveCountMax",
+    "ServerAliveInterval",
+    "StrictHostKeyChecking",
+    "User",
+    "UserKnownHostsFile",
+];
+
+/// Flags of `ssh` that consume the following word
+const SSH_VALUE_FLAGS: &[&str] = &[
+    "-B", "-b", "-c", "-D", "-E", "-e", "-F", "-I", "-i", "-J", "-L", "-l", "-m", "-O", "-o", "-p",
+    "-Q", "-R", "-S", "-W", "-w",
+];
+
+/// Flags of `scp` that consume the following word
+const SCP_VALUE_FLAGS: &[&str] = &["-c", "-D", "-F", "-i", "-J", "-l", "-o", "-P", "-S", "-X"];
+
+/// Flags of `sftp` that consume the following word
+const SFTP_VALUE_FLAGS: &[&str] = &[
+    "-B", "-b", "-c", "-D", "-F", "-i", "-J", "-l", "-o", "-P", "-R", "-S", "-s", "-X",
+];
+
+/// What kind of value the cursor position expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SshValue {
+    Port,
+    OptionKey,
+    User,
+    Host,
+    IdentityFile,
+}
+
+/// Nesting limit for `Include` directives (same as OpenSSH)
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// Files in `~/.ssh` that are never private keys
+const NON_KEY_FILES: &[&str] = &["config", "authorized_keys", "environment", "rc"];
+
+/// Completes hosts and flag values for `ssh`, `scp` and `sftp`, and
+/// optionally the remote part of `host:path` operands of `scp`/`rsync`
+pub struct SshProvider {
+    remote_paths: bool,
+    timeout: Duration,
+}
+
+impl Default for SshProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SshProvider {
+    pub fn new() -> Self {
+        Self {
+            remote_paths: false,
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// List `host:path` operands by running `ls` on the host over ssh
+    pub fn with_remote_paths(mut self, enabled: bool) -> Self {
+        self.remote_paths = enabled;
+        self
+    }
+
+    /// Kill the remote listing after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for SshProvider {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Ssh
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.current_word_idx > 0
+            && (SSH_COMMANDS.contains(&ctx.command.as_str()) || ctx.remote_path.is_some())
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        if let Some(remote) = &ctx.remote_path {
+            if !self.remote_paths {
+                return Ok(None);
+            }
+            return Ok(list_remote_paths(remote, self.timeout).map(|paths| {
+                paths
+                    .into_iter()
+                    .map(|path| {
+                        let candidate_type = if path.ends_with('/') {
+                            CandidateType::Dir
+                        } else {
+                            CandidateType::File
+                        };
+                        CompletionEntry::new(path, ProviderKind::Ssh).with_type(candidate_type)
+                    })
+                    .collect()
+            }));
+        }
+
+        if value_kind(ctx) == Some(SshValue::IdentityFile) {
+            let keys = ssh_dir()
+                .map(|dir| list_identity_files(&dir))
+                .unwrap_or_default();
+            return Ok(Some(
+                keys.into_iter()
+                    .map(|name| format!("~/.ssh/{}", name))
+                    .filter(|path| path.starts_with(&ctx.current_word))
+                    .map(|path| {
+                        CompletionEntry::new(path, ProviderKind::Ssh).with_type(CandidateType::File)
+                    })
+                    .collect(),
+            ));
+        }
+
+        let plain = |values: Vec<String>| values.into_iter().map(|v| (v, None)).collect();
+        let values: Vec<(String, Option<String>)> = match value_kind(ctx) {
+            Some(SshValue::Port) => plain(COMMON_PORTS.iter().map(|p| p.to_string()).collect()),
+            Some(SshValue::OptionKey) => plain(OPTION_KEYS.iter().map(|k| k.to_string()).collect()),
+            Some(SshValue::User) => plain(
+                fs::read_to_string("/etc/passwd")
+                    .map(|content| parse_passwd_users(&content))
+                    .unwrap_or_default(),
+            ),
+            Some(SshValue::Host) => ssh_config_path()
+                .map(|path| {
+                    read_config_host_entries(&path)
+                        .into_iter()
+                        .map(|host| (host.alias, host.description))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            Some(SshValue::IdentityFile) | None => return Ok(None),
+        };
+        debug!("[ssh] {} values for {:?}", values.len(), value_kind(ctx));
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|(v, _)| v.starts_with(&ctx.current_word))
+                .map(|(v, description)| {
+                    let entry =
+                        CompletionEntry::new(v, ProviderKind::Ssh).with_type(CandidateType::Value);
+                    match description {
+                        Some(description) => entry.with_description(description),
+                        None => entry,
+                    }
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Decide which values apply at the cursor, based on the previous word
+pub fn value_kind(ctx: &CompletionContext) -> Option<SshValue> {
+    let is_ssh = ctx.command == "ssh";
+    match ctx.previous_word.as_deref() {
+        Some("-p") if is_ssh => return Some(SshValue::Port),
+        Some("-P") if !is_ssh => return Some(SshValue::Port),
+        Some("-l") if is_ssh => return Some(SshValue::User),
+        Some("-o") => return Some(SshValue::OptionKey),
+        Some("-i") => return Some(SshValue::IdentityFile),
+        _ => {}
+    }
+
+    let value_flags = value_flags(&ctx.command);
+    if ctx.current_word.starts_with('-')
+        || ctx
+            .previous_word
+            .as_deref()
+            .is_some_and(|word| value_flags.contains(&word))
+    {
+        return None;
+    }
+    match ctx.command.as_str() {
+        "ssh" | "sftp" if !has_positional_before_cursor(ctx) => Some(SshValue::Host),
+        // Any scp operand may be remote; past the `:` it is a path
+        "scp" if !ctx.current_word.contains(':') => Some(SshValue::Host),
+        _ => None,
+    }
+}
+
+fn value_flags(command: &str) -> &'static [&'static str] {
+    match command {
+        "scp" => SCP_VALUE_FLAGS,
+        "sftp" => SFTP_VALUE_FLAGS,
+        _ => SSH_VALUE_FLAGS,
+    }
+}
+
+/// True if an `ssh`/`sftp` destination was already given before the current word
+fn has_positional_before_cursor(ctx: &CompletionContext) -> bool {
+    let value_flags = value_flags(&ctx.command);
+    let mut idx = 1;
+    while idx < ctx.current_word_idx {
+        let word = ctx.words[idx].as_str();
+        if value_flags.contains(&word) {
+            idx += 2;
+        } else if word.starts_with('-') {
+            idx += 1;
+        } else {
+            return true;
+        }
+    }
+    // A value flag right before the cursor means the current word is its value
+    idx > ctx.current_word_idx
+}
+
+/// `host:path` candidates for the entries on `remote.host` starting with `remote.path`;
+/// `None` if the host cannot be reached without a prompt in time
+fn list_remote_paths(remote: &RemotePath, timeout: Duration) -> Option<Vec<String>> {
+    let mut command = Command::new("ssh");
+    command
+        .args(["-o", "BatchMode=yes", "--", &remote.host])
+        .arg(remote_listing_command(&remote.path));
+    let output = process::run_with_timeout(&mut command, None, timeout)?;
+    // `ls` exits non-zero when nothing matches the glob
+    Some(parse_remote_listing(
+        &remote.host,
+        &String::from_utf8_lossy(&output.stdout),
+    ))
+}
+
+/// Remote shell command listing entries starting with `path`, directories with
+/// a trailing `/`. A leading `~/` stays unquoted so the remote shell expands it.
+pub fn remote_listing_command(path: &str) -> String {
+    let (home, rest) = match path.strip_prefix("~/") {
+        Some(rest) => ("~/", rest),
+        None => ("", path),
+    };
+    let quoted = shlex::try_quote(rest).unwrap_or_default();
+    format!("ls -dp1 -- {}{}*", home, quoted)
+}
+
+/// Prefix each listed path with `host:`
+pub fn parse_remote_listing(host: &str, output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| format!("{}:{}", host, line))
+        .collect()
+}
+
+fn ssh_dir() -> Option<PathBuf> {
+    let home = env::var("HOME").ok()?;
+    Some(PathBuf::from(home).join(".ssh"))
+}
+
+pub fn ssh_config_path() -> Option<PathBuf> {
+    Some(ssh_dir()?.join("config"))
+}
+
+/// Likely private keys in an ssh directory: regular files except public
+/// keys, `known_hosts*` and other well-known non-key files
+pub fn list_identity_files(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut keys: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|name| {
+            !name.ends_with(".pub")
+                && !name.starts_with("known_hosts")
+                && !NON_KEY_FILES.contains(&name.as_str())
+        })
+        .collect();
+    keys.sort();
+    keys
+}
+
+/// A concrete `Host` alias of an ssh config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigHost {
+    pub alias: String,
+    /// Where the alias connects to, `[user@]hostname[:port]`, when its block
+    /// sets `HostName`, `User` or `Port`
+    pub description: Option<String>,
+}
+
+/// Hosts of an ssh config file and every file it pulls in through `Include`.
+/// Relative include paths resolve against the directory of `path`, as for `~/.ssh/config`
+pub fn read_config_hosts(path: &Path) -> Vec<String> {
+    read_config_host_entries(path)
+        .into_iter()
+        .map(|host| host.alias)
+        .collect()
+}
+
+/// Like `read_config_hosts`, with each alias's destination
+pub fn read_config_host_entries(path: &Path) -> Vec<ConfigHost> {
+    let base = path.parent().unwrap_or(Path::new("/"));
+    let mut hosts = Vec::new();
+    let mut visited = HashSet::new();
+    collect_hosts(path, base, 0, &mut visited, &mut hosts);
+    hosts
+}
+
+fn collect_hosts(
+    path: &Path,
+    base: &Path,
+    depth: usize,
+    visited: &mut HashSet<PathBuf>,
+    hosts: &mut Vec<ConfigHost>,
+) {
+    if depth > MAX_INCLUDE_DEPTH {
+        debug!("[ssh] include depth exceeded at {}", path.display());
+        return;
+    }
+    let key = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if !visited.insert(key) {
+        return;
+    }
+    let Ok(content) = fs::read_to_string(path) else {
+        return;
+    };
+
+    hosts.extend(parse_config_host_entries(&content));
+    for pattern in parse_config_includes(&content) {
+        let expanded = PathBuf::from(shellexpand::tilde(&pattern).as_ref());
+        let pattern = base.join(expanded);
+        let Ok(paths) = glob::glob(&pattern.to_string_lossy()) else {
+            continue;
+        };
+        for included in paths.filter_map(|p| p.ok()) {
+            collect_hosts(&included, base, depth + 1, visited, hosts);
+        }
+    }
+}
+
+/// Path patterns of the `Include` directives in an ssh config
+pub fn parse_config_includes(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(split_keyword)
+        .filter(|(keyword, _)| keyword.eq_ignore_ascii_case("include"))
+        .flat_map(|(_, args)| args.split_whitespace().map(|p| p.to_string()))
+        .collect()
+}
+
+/// Extract concrete `Host` aliases (no wildcard patterns) from an ssh config
+pub fn parse_config_hosts(content: &str) -> Vec<String> {
+    parse_config_host_entries(content)
+        .into_iter()
+        .map(|host| host.alias)
+        .collect()
+}
+
+/// Concrete `Host` aliases with the `HostName`/`User`/`Port` of their block
+pub fn parse_config_host_entries(content: &str) -> Vec<ConfigHost> {
+    let mut hosts = Vec::new();
+    let mut block = HostBlock::default();
+    for line in content.lines() {
+        let Some((keyword, args)) = split_keyword(line) else {
+            continue;
+        };
+        let value = || Some(args.to_string());
+        match keyword.to_ascii_lowercase().as_str() {
+            "host" => {
+                block.finish(&mut hosts);
+                block.aliases = args
+                    .split_whitespace()
+                    .filter(|h| !h.contains(['*', '?', '!']))
+                    .map(|h| h.to_string())
+                    .collect();
+            }
+            "match" => block.finish(&mut hosts),
+            // The first value of a keyword wins, as in ssh
+            "hostname" => block.hostname = block.hostname.take().or_else(value),
+            "user" => block.user = block.user.take().or_else(value),
+            "port" => block.port = block.port.take().or_else(value),
+            _ => {}
+        }
+    }
+    block.finish(&mut hosts);
+    hosts
+}
+
+/// A `Host` block being read
+#[derive(Default)]
+struct HostBlock {
+    aliases: Vec<String>,
+    hostname: Option<String>,
+    user: Option<String>,
+    port: Option<String>,
+}
+
+impl HostBlock {
+    /// Emit the aliases and start over
+    fn finish(&mut self, hosts: &mut Vec<ConfigHost>) {
+        let block = mem::take(self);
+        for alias in block.aliases.iter() {
+            hosts.push(ConfigHost {
+                alias: alias.clone(),
+                description: block.destination(alias),
+            });
+        }
+    }
+
+    /// `[user@]hostname[:port]`, `None` when the block sets none of them
+    fn destination(&self, alias: &str) -> Option<String> {
+        if self.hostname.is_none() && self.user.is_none() && self.port.is_none() {
+            return None;
+        }
+        let mut description = String::new();
+        if let Some(user) = &self.user {
+            description.push_str(user);
+            description.push('@');
+        }
+        description.push_str(self.hostname.as_deref().unwrap_or(alias));
+        if let Some(port) = &self.port {
+            description.push(':');
+            description.push_str(port);
+        }
+        Some(description)
+    }
+}
+
+/// Split an ssh config line into keyword and arguments (`Key value` or `Key=value`)
+fn split_keyword(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let idx = line.find(|c: char| c.is_whitespace() || c == '=')?;
+    let (keyword, rest) = line.split_at(idx);
+    let rest = rest.trim_start().strip_prefix('=').unwrap_or(rest).trim();
+    Some((keyword, rest))
+}
+
+/// User names from `/etc/passwd`-formatted content
+pub fn parse_passwd_users(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split(':').next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_remote_paths() {
+        assert_eq!(remote_listing_command("/srv/"), "ls -dp1 -- /srv/*");
+        assert_eq!(remote_listing_command(""), "ls -dp1 -- ''*");
+        assert_eq!(
+            remote_listing_command("~/my docs/"),
+            "ls -dp1 -- ~/'my docs/'*"
+        );
+        assert_eq!(
+            parse_remote_listing("web1", "/srv/app/\n/srv/notes.txt\n"),
+            vec!["web1:/srv/app/", "web1:/srv/notes.txt"]
+        );
+
+        let provider = SshProvider::new();
+        assert!(provider.should_try(&ctx_for("rsync -av host:pa")));
+        assert!(!provider.should_try(&ctx_for("rsync -av loc")));
+        // Remote listing is opt-in
+        assert!(
+            provider
+                .try_complete(&ctx_for("rsync host:pa"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_option_keys_after_dash_o() {
+        let ctx = ctx_for("ssh -o Str");
+        assert_eq!(value_kind(&ctx), Some(SshValue::OptionKey));
+
+        let result = SshProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["StrictHostKeyChecking"]);
+    }
+
+    #[test]
+    fn test_users_after_dash_l() {
+        assert_eq!(value_kind(&ctx_for("ssh -l ")), Some(SshValue::User));
+        // scp uses -l for a bandwidth limit
+        assert_eq!(value_kind(&ctx_for("scp -l ")), None);
+
+        let passwd =
+            "# comment\nroot:x:0:0:root:/root:/bin/bash\nalice:x:1000:1000::/home/alice:/bin/sh\n";
+        assert_eq!(parse_passwd_users(passwd), vec!["root", "alice"]);
+    }
+
+    #[test]
+    fn test_port_and_host_positions() {
+        assert_eq!(value_kind(&ctx_for("ssh -p ")), Some(SshValue::Port));
+        assert_eq!(value_kind(&ctx_for("scp -P ")), Some(SshValue::Port));
+        assert_eq!(value_kind(&ctx_for("ssh -i key web")), Some(SshValue::Host));
+        assert_eq!(value_kind(&ctx_for("ssh web ")), None);
+    }
+
+    #[test]
+    fn test_scp_and_sftp_hosts() {
+        assert_eq!(value_kind(&ctx_for("sftp we")), Some(SshValue::Host));
+        assert_eq!(value_kind(&ctx_for("sftp -P 22 we")), Some(SshValue::Host));
+        assert_eq!(value_kind(&ctx_for("sftp web ")), None);
+        // Source and target of scp can both be remote
+        assert_eq!(value_kind(&ctx_for("scp we")), Some(SshValue::Host));
+        assert_eq!(
+            value_kind(&ctx_for("scp notes.txt we")),
+            Some(SshValue::Host)
+        );
+        assert_eq!(value_kind(&ctx_for("scp web:/srv/")), None);
+        assert_eq!(value_kind(&ctx_for("scp -r")), None);
+    }
+
+    #[test]
+    fn test_identity_files() {
+        assert_eq!(
+            value_kind(&ctx_for("ssh -i ")),
+            Some(SshValue::IdentityFile)
+        );
+        assert_eq!(
+            value_kind(&ctx_for("scp -i ~/.ssh/")),
+            Some(SshValue::IdentityFile)
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "id_ed25519",
+            "id_ed25519.pub",
+            "work_rsa",
+            "known_hosts",
+            "known_hosts.old",
+            "config",
+            "authorized_keys",
+        ] {
+            fs::write(dir.path().join(name), "").unwrap();
+        }
+        fs::create_dir(dir.path().join("sockets")).unwrap();
+
+        assert_eq!(
+            list_identity_files(dir.path()),
+            vec!["id_ed25519", "work_rsa"]
+        );
+    }
+
+    #[test]
+    fn test_config_includes() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("config.d")).unwrap();
+        fs::write(
+            dir.path().join("config"),
+            "Include config.d/*\nHost web\n# Include loop\nInclude config\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("config.d/work"), "Host build ci\n").unwrap();
+        fs::write(
+            dir.path().join("config.d/home"),
+            "Host nas\nInclude ../config\n",
+        )
+        .unwrap();
+
+        let mut hosts = read_config_hosts(&dir.path().join("config"));
+        hosts.sort();
+        assert_eq!(hosts, vec!["build", "ci", "nas", "web"]);
+    }
+
+    #[test]
+    fn test_parse_config_hosts() {
+        let config = "Host web db\n  HostName 10.0.0.1\nHost *.internal\nhost=bastion\n";
+        assert_eq!(parse_config_hosts(config), vec!["web", "db", "bastion"]);
+    }
+
+    #[test]
+    fn test_config_host_descriptions() {
+        let config = "Host web db\n  HostName 10.0.0.1\n  User deploy\n  Port 2222\n\
+                      Host nas\n  HostName nas.lan\n  HostName ignored\n\
+                      Host bare\nHost *\n  User fallback\n";
+        let described = |alias: &str| {
+            parse_config_host_entries(config)
+                .into_iter()
+                .find(|host| host.alias == alias)
+                .and_then(|host| host.description)
+        };
+        assert_eq!(described("web").as_deref(), Some("deploy@10.0.0.1:2222"));
+        assert_eq!(described("db").as_deref(), Some("deploy@10.0.0.1:2222"));
+        assert_eq!(described("nas").as_deref(), Some("nas.lan"));
+        assert_eq!(described("bare"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("config"), config).unwrap();
+        let hosts = read_config_host_entries(&dir.path().join("config"));
+        assert_eq!(
+            hosts[0],
+            ConfigHost {
+                alias: "web".to_string(),
+                description: Some("deploy@10.0.0.1:2222".to_string()),
+            }
+        );
+    }
+}
@@ -0,0 +1,252 @@
+use log::debug;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind, process,
+};
+
+const SYSTEMCTL: &str = "systemctl";
+const JOURNALCTL: &str = "journalctl";
+
+/// Syslog priorities accepted by `journalctl -p`, most to least severe
+pub const PRIORITIES: &[&str] = &[
+    "emerg", "alert", "crit", "err", "warning", "notice", "info", "debug",
+];
+
+/// `systemctl` subcommands whose arguments are unit names
+const UNIT_SUBCOMMANDS: &[&str] = &[
+    "start",
+    "stop",
+    "restart",
+    "reload",
+    "try-restart",
+    "reload-or-restart",
+    "status",
+    "enable",
+    "disable",
+    "reenable",
+    "mask",
+    "unmask",
+    "is-active",
+    "is-enabled",
+    "is-failed",
+    "kill",
+    "cat",
+    "edit",
+    "show",
+    "list-dependencies",
+];
+
+/// What kind of value the cursor position expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemdValue {
+    Units,
+    Priorities,
+}
+
+/// Completes unit names for `systemctl`/`journalctl -u` and priorities for `journalctl -p`
+pub struct SystemdProvider {
+    timeout: Duration,
+}
+
+impl Default for SystemdProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemdProvider {
+    pub fn new() -> Self {
+        Self {
+            timeout: process::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Kill the subprocess after this long
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl CompletionProvider for SystemdProvider {
+    fn name(&self) -> &'static str {
+        "systemd"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Systemd
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.command == SYSTEMCTL || ctx.command == JOURNALCTL
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let values = match value_kind(ctx) {
+            Some(SystemdValue::Units) => match list_units(is_user_scope(ctx), self.timeout) {
+                Some(units) => units,
+                None => return Ok(None),
+            },
+            Some(SystemdValue::Priorities) => PRIORITIES.iter().map(|p| p.to_string()).collect(),
+            None => return Ok(None),
+        };
+
+        Ok(Some(
+            values
+                .into_iter()
+                .filter(|v| v.starts_with(&ctx.current_word))
+                .map(|v| {
+                    CompletionEntry::new(v, ProviderKind::Systemd).with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// Decide which values apply at the cursor, based on the command and previous word
+pub fn value_kind(ctx: &CompletionContext) -> Option<SystemdValue> {
+    let previous = ctx.previous_word.as_deref();
+
+    if ctx.command == JOURNALCTL {
+        return match previous {
+            Some("-u") | Some("--unit") | Some("--user-unit") => Some(SystemdValue::Units),
+            Some("-p") | Some("--priority") => Some(SystemdValue::Priorities),
+            _ => None,
+        };
+    }
+
+    if ctx.command == SYSTEMCTL {
+        let subcommand_idx = ctx
+            .words
+            .iter()
+            .enumerate()
+            .skip(1)
+            .find(|(_, w)| !w.starts_with('-'))
+            .map(|(i, _)| i)?;
+        if ctx.current_word_idx > subcommand_idx
+            && UNIT_SUBCOMMANDS.contains(&ctx.words[subcommand_idx].as_str())
+        {
+            return Some(SystemdValue::Units);
+        }
+    }
+
+    None
+}
+
+/// Whether the command addresses the user manager (`--user`, or `journalctl --user-unit`)
+pub fn is_user_scope(ctx: &CompletionContext) -> bool {
+    ctx.words[..ctx.current_word_idx.min(ctx.words.len())]
+        .iter()
+        .any(|w| w == "--user")
+        || (ctx.command == JOURNALCTL && ctx.previous_word.as_deref() == Some("--user-unit"))
+}
+
+/// Arguments for `systemctl` to list unit files of the system or user manager
+pub fn list_units_args(user: bool) -> Vec<&'static str> {
+    let mut args = Vec::new();
+    if user {
+        args.push("--user");
+    }
+    args.extend(["list-unit-files", "--no-legend", "--no-pager", "--plain"]);
+    args
+}
+
+/// List unit files known to systemd; `None` if systemctl is unavailable or too slow
+pub fn list_units(user: bool, timeout: Duration) -> Option<Vec<String>> {
+    let mut command = Command::new(SYSTEMCTL);
+    command.args(list_units_args(user));
+    let output = process::run_with_timeout(&mut command, None, timeout)?;
+
+    if !output.status.success() {
+        debug!("[systemd] list-unit-files exited with {}", output.status);
+        return None;
+    }
+
+    Some(parse_unit_files(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Extract unit names from `systemctl list-unit-files --no-legend` output
+pub fn parse_unit_files(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|unit| unit.contains('.'))
+        .map(|unit| unit.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_journalctl_unit_flag() {
+        assert_eq!(
+            value_kind(&ctx_for("journalctl -u ")),
+            Some(SystemdValue::Units)
+        );
+        assert_eq!(
+            value_kind(&ctx_for("journalctl --unit ss")),
+            Some(SystemdValue::Units)
+        );
+    }
+
+    #[test]
+    fn test_journalctl_priority_flag() {
+        let ctx = ctx_for("journalctl -p ");
+        assert_eq!(value_kind(&ctx), Some(SystemdValue::Priorities));
+
+        let result = SystemdProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, PRIORITIES);
+
+        let ctx = ctx_for("journalctl -p e");
+        let result = SystemdProvider::new().try_complete(&ctx).unwrap().unwrap();
+        let values: Vec<&str> = result.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["emerg", "err"]);
+    }
+
+    #[test]
+    fn test_user_scope_changes_command() {
+        let ctx = ctx_for("systemctl --user start ");
+        assert_eq!(value_kind(&ctx), Some(SystemdValue::Units));
+        assert!(is_user_scope(&ctx));
+        assert_eq!(list_units_args(is_user_scope(&ctx))[0], "--user");
+
+        let ctx = ctx_for("systemctl start ");
+        assert!(!is_user_scope(&ctx));
+        assert!(!list_units_args(is_user_scope(&ctx)).contains(&"--user"));
+
+        assert!(is_user_scope(&ctx_for("journalctl --user-unit ")));
+    }
+
+    #[test]
+    fn test_systemctl_unit_positions() {
+        assert_eq!(
+            value_kind(&ctx_for("systemctl restart ")),
+            Some(SystemdValue::Units)
+        );
+        assert_eq!(value_kind(&ctx_for("systemctl ")), None);
+        assert_eq!(value_kind(&ctx_for("systemctl daemon-reload ")), None);
+        assert_eq!(value_kind(&ctx_for("journalctl -f ")), None);
+    }
+
+    #[test]
+    fn test_parse_unit_files() {
+        let output = "sshd.service enabled enabled\n\
+                      getty@.service enabled enabled\n\
+                      \n\
+                      timers.target static -\n";
+        assert_eq!(
+            parse_unit_files(output),
+            vec!["sshd.service", "getty@.service", "timers.target"]
+        );
+    }
+}
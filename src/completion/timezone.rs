@@ -0,0 +1,188 @@
+use log::debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::completion::{
+    CandidateType, CompletionContext, CompletionEntry, CompletionError, CompletionProvider,
+    ProviderKind,
+};
+
+pub const DEFAULT_ZONEINFO: &str = "/usr/share/zoneinfo";
+const TZ_VARIABLE: &str = "TZ";
+
+/// Files in the zoneinfo tree that are not zones despite their name
+const NON_ZONES: &[&str] = &["SECURITY"];
+
+/// Completes zone names (`Region/City`) for `TZ=` and `timedatectl set-timezone`
+pub struct TimezoneProvider {
+    zoneinfo: PathBuf,
+}
+
+impl Default for TimezoneProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimezoneProvider {
+    pub fn new() -> Self {
+        Self {
+            zoneinfo: PathBuf::from(DEFAULT_ZONEINFO),
+        }
+    }
+
+    /// Read zones from `dir` instead of `/usr/share/zoneinfo`
+    pub fn with_zoneinfo(mut self, dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            self.zoneinfo = dir;
+        }
+        self
+    }
+}
+
+impl CompletionProvider for TimezoneProvider {
+    fn name(&self) -> &'static str {
+        "timezone"
+    }
+
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Timezone
+    }
+
+    fn should_try(&self, ctx: &CompletionContext) -> bool {
+        ctx.assignment_name.as_deref() == Some(TZ_VARIABLE) || is_set_timezone(ctx)
+    }
+
+    fn try_complete(
+        &self,
+        ctx: &CompletionContext,
+    ) -> Result<Option<Vec<CompletionEntry>>, CompletionError> {
+        let word = ctx.current_word.as_str();
+        let (kept, typed) = match word.split_once('=') {
+            Some((name, value)) if ctx.assignment_name.is_some() => {
+                (&word[..name.len() + 1], value)
+            }
+            _ => ("", word),
+        };
+
+        Ok(Some(
+            list_zones(&self.zoneinfo, typed)
+                .into_iter()
+                .map(|zone| {
+                    CompletionEntry::new(format!("{}{}", kept, zone), ProviderKind::Timezone)
+                        .with_type(CandidateType::Value)
+                })
+                .collect(),
+        ))
+    }
+}
+
+/// The first argument of `timedatectl set-timezone`
+fn is_set_timezone(ctx: &CompletionContext) -> bool {
+    if ctx.command != "timedatectl" {
+        return false;
+    }
+    let mut positional = ctx.words[1..ctx.current_word_idx]
+        .iter()
+        .filter(|w| !w.starts_with('-'));
+    positional.next().map(String::as_str) == Some("set-timezone") && positional.next().is_none()
+}
+
+/// Zone names under `zoneinfo` starting with `typed`, sorted
+pub fn list_zones(zoneinfo: &Path, typed: &str) -> Vec<String> {
+    let mut zones = Vec::new();
+    collect_zones(zoneinfo, "", typed, &mut zones);
+    zones.sort();
+    zones
+}
+
+fn collect_zones(dir: &Path, relative: &str, typed: &str, zones: &mut Vec<String>) {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("[timezone] cannot read {}: {}", dir.display(), e);
+            return;
+        }
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name();
+        let Some(name) = file_name.to_str() else {
+            continue;
+        };
+        // Zones are capitalized; this skips `posix/`, `right/`, `zone.tab`, `tzdata.zi`, ...
+        if !name.starts_with(|c: char| c.is_ascii_uppercase())
+            || name.contains('.')
+            || NON_ZONES.contains(&name)
+        {
+            continue;
+        }
+        let zone = format!("{}{}", relative, name);
+        let path = entry.path();
+        if path.is_dir() {
+            // Only descend into regions that can still match
+            let region = format!("{}/", zone);
+            if region.starts_with(typed) || typed.starts_with(&region) {
+                collect_zones(&path, &region, typed, zones);
+            }
+        } else if zone.starts_with(typed) {
+            zones.push(zone);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ctx_for;
+
+    #[test]
+    fn test_zones_from_zoneinfo_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for zone in [
+            "UTC",
+            "America/New_York",
+            "America/Argentina/Salta",
+            "Europe/Berlin",
+            "posix/Europe/Berlin",
+            "zone.tab",
+            "posixrules",
+        ] {
+            let path = root.join(zone);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "TZif").unwrap();
+        }
+
+        assert_eq!(
+            list_zones(root, ""),
+            vec![
+                "America/Argentina/Salta",
+                "America/New_York",
+                "Europe/Berlin",
+                "UTC"
+            ]
+        );
+
+        let provider = TimezoneProvider::new().with_zoneinfo(Some(root.to_path_buf()));
+        let values = |line: &str| -> Vec<String> {
+            let ctx = ctx_for(line);
+            assert!(provider.should_try(&ctx), "{}", line);
+            provider
+                .try_complete(&ctx)
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|e| e.value)
+                .collect()
+        };
+        assert_eq!(
+            values("timedatectl set-timezone Am"),
+            vec!["America/Argentina/Salta", "America/New_York"]
+        );
+        assert_eq!(values("TZ=Eu"), vec!["TZ=Europe/Berlin"]);
+        assert_eq!(values("export TZ=America/N"), vec!["TZ=America/New_York"]);
+
+        assert!(!provider.should_try(&ctx_for("timedatectl set-timezone UTC ")));
+        assert!(!provider.should_try(&ctx_for("timedatectl status ")));
+    }
+}
@@ -1,25 +1,254 @@
+use schemars::JsonSchema;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Default)]
+use crate::completion::{DEFAULT_SUBCOMMAND_DISPATCHERS, DEFAULT_WRAPPER_COMMANDS};
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, JsonSchema, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum SelectorType {
+    /// Built-in terminal selector (`dialoguer` feature)
     #[default]
     Dialoguer,
+    /// The external `fzf` binary (`fzf` feature)
+    Fzf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MatcherType {
+    #[default]
+    Skim,
+    Substring,
+    Prefix,
+}
+
+/// Order candidates are shown in, unless the compspec sets `nosort`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    /// As merged from the providers
+    #[default]
+    ProviderOrder,
+    Alphabetical,
+    /// Shortest first
+    Length,
+}
+
+/// What happens when several candidates remain after filtering
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AmbiguousBehavior {
+    /// Open the selector
+    #[default]
+    Menu,
+    /// Insert the candidates' common prefix and ring the terminal bell
+    InsertPrefixThenBell,
+    /// Ring the terminal bell and insert nothing
+    BellOnly,
+}
+
+/// Which candidates are cleaned up before they are shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimCandidates {
+    /// Keep candidates as the providers return them
+    Off,
+    /// Trim surrounding whitespace from bash completion output
+    #[default]
+    Bash,
+    /// Trim the candidates of every provider and collapse doubled slashes
+    /// before they are merged (bash output included)
+    All,
+}
+
+/// On-disk format of the history file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryFormat {
+    Bash,
+    Zsh,
+    Fish,
+    #[default]
+    Auto,
+}
+
+/// How the value of a `NAME=value` assignment is completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentValue {
+    Command,
+    File,
+    Directory,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// A provider of the pipeline. Those running a subprocess take a `timeout_ms`
+/// overriding `subprocess_timeout_ms` (`carapace_timeout_ms` for carapace)
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ProviderConfig {
-    History { limit: Option<usize> },
-    Carapace,
-    Bash,
-    EnvVar,
+    History {
+        limit: Option<usize>,
+    },
+    Carapace {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Bash {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    EnvVar {
+        limit: Option<usize>,
+    },
+    Ansible {
+        limit: Option<usize>,
+    },
+    Compression {
+        limit: Option<usize>,
+    },
+    Cron {
+        limit: Option<usize>,
+    },
+    Docker {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    EditorRecent {
+        limit: Option<usize>,
+        /// viminfo file to read (default `~/.viminfo`)
+        viminfo: Option<String>,
+    },
+    External {
+        limit: Option<usize>,
+        /// Shell command implementing the external completer protocol
+        cmd: String,
+        timeout_ms: Option<u64>,
+    },
+    Git {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Go {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Just {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Make {
+        limit: Option<usize>,
+    },
+    Man {
+        limit: Option<usize>,
+    },
+    Message {
+        limit: Option<usize>,
+    },
+    Network {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Packages {
+        limit: Option<usize>,
+        /// Seconds a cached list of available packages is reused (default one day)
+        cache_ttl_secs: Option<u64>,
+        timeout_ms: Option<u64>,
+    },
+    Python {
+        limit: Option<usize>,
+        /// File with one package name per line, offered for `pip install`
+        package_cache: Option<String>,
+        /// Interpreters completing `-m` modules besides `python`, `python3`
+        /// and `python3.N` (e.g. `["pypy3"]`)
+        #[serde(default)]
+        interpreters: Vec<String>,
+        timeout_ms: Option<u64>,
+    },
+    Ripgrep {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Spec {
+        limit: Option<usize>,
+        /// Directory of `<command>.json5` specs (default `~/.config/bft/specs`)
+        dir: Option<String>,
+    },
+    Ssh {
+        limit: Option<usize>,
+        /// Complete the remote part of `scp`/`rsync` `host:path` operands over ssh
+        #[serde(default)]
+        remote_paths: bool,
+        timeout_ms: Option<u64>,
+    },
+    Systemd {
+        limit: Option<usize>,
+        timeout_ms: Option<u64>,
+    },
+    Timezone {
+        limit: Option<usize>,
+        /// Zone database directory (default `/usr/share/zoneinfo`)
+        zoneinfo: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl ProviderConfig {
+    /// Maximum number of candidates this provider contributes to the merged list
+    pub fn limit(&self) -> Option<usize> {
+        match self {
+            ProviderConfig::History { limit }
+            | ProviderConfig::Carapace { limit, .. }
+            | ProviderConfig::Bash { limit, .. }
+            | ProviderConfig::EnvVar { limit }
+            | ProviderConfig::Ansible { limit }
+            | ProviderConfig::Compression { limit }
+            | ProviderConfig::Cron { limit }
+            | ProviderConfig::Docker { limit, .. }
+            | ProviderConfig::EditorRecent { limit, .. }
+            | ProviderConfig::External { limit, .. }
+            | ProviderConfig::Git { limit, .. }
+            | ProviderConfig::Go { limit, .. }
+            | ProviderConfig::Just { limit, .. }
+            | ProviderConfig::Make { limit }
+            | ProviderConfig::Man { limit }
+            | ProviderConfig::Message { limit }
+            | ProviderConfig::Network { limit, .. }
+            | ProviderConfig::Packages { limit, .. }
+            | ProviderConfig::Python { limit, .. }
+            | ProviderConfig::Ripgrep { limit, .. }
+            | ProviderConfig::Spec { limit, .. }
+            | ProviderConfig::Ssh { limit, .. }
+            | ProviderConfig::Systemd { limit, .. }
+            | ProviderConfig::Timezone { limit, .. } => *limit,
+        }
+    }
+
+    /// Subprocess timeout override of this provider, if it runs one
+    pub fn timeout_ms(&self) -> Option<u64> {
+        match self {
+            ProviderConfig::Carapace { timeout_ms, .. }
+            | ProviderConfig::Bash { timeout_ms, .. }
+            | ProviderConfig::Docker { timeout_ms, .. }
+            | ProviderConfig::External { timeout_ms, .. }
+            | ProviderConfig::Git { timeout_ms, .. }
+            | ProviderConfig::Go { timeout_ms, .. }
+            | ProviderConfig::Just { timeout_ms, .. }
+            | ProviderConfig::Network { timeout_ms, .. }
+            | ProviderConfig::Packages { timeout_ms, .. }
+            | ProviderConfig::Python { timeout_ms, .. }
+            | ProviderConfig::Ripgrep { timeout_ms, .. }
+            | ProviderConfig::Ssh { timeout_ms, .. }
+            | ProviderConfig::Systemd { timeout_ms, .. } => *timeout_ms,
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(default)]
 pub struct Config {
     pub selector_height: Option<String>,
@@ -29,7 +258,85 @@ pub struct Config {
     #[serde(skip, default = "default_completion_sep")]
     pub completion_sep: String,
     pub no_empty_cmd_completion: bool,
+    pub trim_candidates: TrimCandidates,
+    /// Show `./foo` candidates as `foo` unless the word being completed starts with `./`
+    pub strip_leading_dot_slash: bool,
+    /// Show path candidates shortened in the selector (`~` for the home
+    /// directory, `…` for middle directories); the full value is inserted
+    pub pretty_paths: bool,
+    pub interactive_bash: bool,
+    /// Merge carapace and bash candidates; when false the first of them (in
+    /// `providers` order) that returns candidates wins
+    pub merge_carapace_bash: bool,
+    /// Completion mode for assignment values by variable name (others complete files)
+    pub assignment_values: HashMap<String, AssignmentValue>,
+    /// Longest current word (in bytes) passed to carapace and bash; longer
+    /// words only reach the lightweight providers
+    pub max_word_len: usize,
+    /// Commands completed as another command (e.g. `k` as `kubectl`)
+    pub command_map: HashMap<String, String>,
+    /// Commands whose first argument is a subcommand (`git`, `docker`, ...);
+    /// past it, spec files are looked up as `<command>-<subcommand>.json5`
+    pub subcommand_dispatchers: Vec<String>,
+    /// Commands that run another command (`sudo`, `env`, ...); completion
+    /// skips them and their options to complete that command instead
+    pub wrapper_commands: Vec<String>,
+    /// Commands left to the shell's native completion
+    pub bypass_commands: Vec<String>,
+    /// Commands whose arguments complete directories only
+    pub dir_only_commands: Vec<String>,
+    /// File extensions sorted first when completing arguments of a command
+    pub preferred_extensions: HashMap<String, Vec<String>>,
+    /// Single-letter flags of commands, offered appended to a typed cluster
+    /// like `-xv` (e.g. `tar` -> `cxtvzjf`)
+    pub short_flags: HashMap<String, String>,
+    /// `PATH`-like variable per command whose directories also complete its
+    /// arguments (e.g. `python` -> `PYTHONPATH`)
+    pub path_search_vars: HashMap<String, String>,
+    /// Extra arguments passed to `carapace <command> export` per command
+    pub carapace_command_args: HashMap<String, Vec<String>>,
+    /// Flags of commands taking a message (e.g. `git` -> `-m`, `--message`),
+    /// completed by the `message` provider from history
+    pub message_flags: HashMap<String, Vec<String>>,
+    pub history_format: HistoryFormat,
     pub selector_type: SelectorType,
+    /// How fzf matches the typed query; the dialoguer selector always matches
+    /// with skim and rejects the others
+    pub matcher: MatcherType,
+    pub sort: SortOrder,
+    /// Match case-sensitively where providers filter by prefix (e.g. env vars)
+    pub match_case: bool,
+    /// Prefix selector items with their position (display only)
+    pub show_indices: bool,
+    /// Prefix selector items with a marker for their candidate type (display only)
+    pub show_types: bool,
+    /// Open the selector even for a single candidate instead of inserting it
+    pub confirm_single: bool,
+    /// Selector or readline-style bell when several candidates remain
+    pub ambiguous_behavior: AmbiguousBehavior,
+    /// Insert the only candidate the current word is a prefix of, even
+    /// when other (fuzzy) candidates were found
+    pub preselect_exact_prefix: bool,
+    /// Descriptions longer than this many chars are cut with an ellipsis
+    pub max_description_len: usize,
+    /// Wrap selector items wider than the terminal onto continuation lines
+    pub wrap_candidates: bool,
+    /// Milliseconds before a provider's subprocess (bash, git, ...) is
+    /// killed, unless the provider sets its own `timeout_ms`. The README
+    /// lists the precedence with `carapace_timeout_ms`
+    pub subprocess_timeout_ms: u64,
+    /// Carapace binary to run instead of `carapace` from `PATH`
+    pub carapace_path: Option<String>,
+    /// Milliseconds before carapace is killed and bash completes instead,
+    /// unless the carapace provider sets its own `timeout_ms`
+    pub carapace_timeout_ms: u64,
+    /// Milliseconds the candidates of a completion are reused when it is
+    /// triggered again at the same word (e.g. after inserting a common
+    /// prefix), narrowed to the longer word; 0 disables the cache
+    pub candidate_cache_ttl_ms: u64,
+    /// Share directory listings with the completions of the next few seconds
+    /// through `~/.cache/bft`, instead of keeping them for one invocation
+    pub share_dir_listings: bool,
     pub providers: Vec<ProviderConfig>,
 }
 
@@ -37,6 +344,48 @@ fn default_completion_sep() -> String {
     "\x01".to_string()
 }
 
+fn default_assignment_values() -> HashMap<String, AssignmentValue> {
+    ["EDITOR", "VISUAL", "PAGER", "SHELL", "BROWSER"]
+        .iter()
+        .map(|name| (name.to_string(), AssignmentValue::Command))
+        .collect()
+}
+
+fn default_message_flags() -> HashMap<String, Vec<String>> {
+    ["git", "hg", "svn"]
+        .iter()
+        .map(|command| {
+            (
+                command.to_string(),
+                vec!["-m".to_string(), "--message".to_string()],
+            )
+        })
+        .collect()
+}
+
+fn default_preferred_extensions() -> HashMap<String, Vec<String>> {
+    let tar = [
+        "tar", "tar.gz", "tgz", "tar.xz", "txz", "tar.zst", "tar.bz2", "tbz2",
+    ];
+    [
+        ("gunzip", &["gz", "tgz"][..]),
+        ("zcat", &["gz"]),
+        ("unxz", &["xz", "txz"]),
+        ("xzcat", &["xz"]),
+        ("unzstd", &["zst"]),
+        ("zstdcat", &["zst"]),
+        ("bunzip2", &["bz2", "tbz2"]),
+        ("unzip", &["zip"]),
+        ("tar", &tar),
+    ]
+    .iter()
+    .map(|(cmd, exts)| {
+        let exts = exts.iter().map(|e| e.to_string()).collect();
+        (cmd.to_string(), exts)
+    })
+    .collect()
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -46,12 +395,60 @@ impl Default for Config {
             prompt: "> ".to_string(),
             completion_sep: default_completion_sep(),
             no_empty_cmd_completion: false,
+            trim_candidates: TrimCandidates::Bash,
+            strip_leading_dot_slash: false,
+            pretty_paths: false,
+            interactive_bash: false,
+            merge_carapace_bash: true,
+            assignment_values: default_assignment_values(),
+            max_word_len: 4096,
+            command_map: HashMap::new(),
+            subcommand_dispatchers: DEFAULT_SUBCOMMAND_DISPATCHERS
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            wrapper_commands: DEFAULT_WRAPPER_COMMANDS
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            bypass_commands: Vec::new(),
+            dir_only_commands: ["cd", "pushd", "rmdir"]
+                .iter()
+                .map(|c| c.to_string())
+                .collect(),
+            preferred_extensions: default_preferred_extensions(),
+            short_flags: HashMap::new(),
+            path_search_vars: HashMap::new(),
+            carapace_command_args: HashMap::new(),
+            message_flags: default_message_flags(),
+            history_format: HistoryFormat::Auto,
             selector_type: SelectorType::Dialoguer,
+            matcher: MatcherType::Skim,
+            sort: SortOrder::ProviderOrder,
+            match_case: false,
+            show_indices: false,
+            show_types: false,
+            confirm_single: false,
+            ambiguous_behavior: AmbiguousBehavior::Menu,
+            preselect_exact_prefix: false,
+            max_description_len: 120,
+            wrap_candidates: false,
+            subprocess_timeout_ms: 1000,
+            carapace_path: None,
+            carapace_timeout_ms: 300,
+            candidate_cache_ttl_ms: 0,
+            share_dir_listings: false,
             providers: vec![
-                ProviderConfig::Bash,
+                ProviderConfig::Bash {
+                    limit: None,
+                    timeout_ms: None,
+                },
                 ProviderConfig::History { limit: Some(20) },
-                ProviderConfig::Carapace,
-                ProviderConfig::EnvVar,
+                ProviderConfig::Carapace {
+                    limit: None,
+                    timeout_ms: None,
+                },
+                ProviderConfig::EnvVar { limit: None },
             ],
         }
     }
@@ -65,13 +462,23 @@ impl Config {
         Self::from_env()
     }
 
-    fn from_file() -> Option<Self> {
+    /// `BFT_CONFIG` if set, otherwise `$XDG_CONFIG_HOME/bft/config.json5`
+    fn config_path() -> PathBuf {
+        if let Ok(path) = env::var("BFT_CONFIG")
+            && !path.is_empty()
+        {
+            return PathBuf::from(path);
+        }
+
         let xdg_config_home = env::var("XDG_CONFIG_HOME").unwrap_or_else(|_| {
             let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
             format!("{}/.config", home)
         });
+        PathBuf::from(xdg_config_home).join("bft/config.json5")
+    }
 
-        let config_path = PathBuf::from(xdg_config_home).join("bft/config.json5");
+    fn from_file() -> Option<Self> {
+        let config_path = Self::config_path();
         if config_path.exists()
             && let Ok(content) = fs::read_to_string(&config_path)
         {
@@ -85,6 +492,11 @@ impl Config {
         None
     }
 
+    /// JSON Schema describing the config file, for editor validation/completion
+    pub fn json_schema() -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&schemars::schema_for!(Config))
+    }
+
     pub fn from_env() -> Self {
         let selector_height = env::var("BFT_SELECTOR_HEIGHT").ok();
 
@@ -102,13 +514,83 @@ impl Config {
             .map(|v| v == "true" || v == "1")
             .unwrap_or(false);
 
+        let trim_candidates = env::var("BFT_TRIM_CANDIDATES")
+            .map(|v| match v.to_lowercase().as_str() {
+                "off" | "false" | "0" => TrimCandidates::Off,
+                "all" => TrimCandidates::All,
+                _ => TrimCandidates::Bash,
+            })
+            .unwrap_or_default();
+
+        let strip_leading_dot_slash = env::var("BFT_STRIP_LEADING_DOT_SLASH")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let interactive_bash = env::var("BFT_INTERACTIVE_BASH")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let merge_carapace_bash = env::var("BFT_MERGE_CARAPACE_BASH")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(true);
+
+        let bypass_commands = env::var("BFT_BYPASS_COMMANDS")
+            .map(|v| {
+                v.split(',')
+                    .map(|c| c.trim().to_string())
+                    .filter(|c| !c.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let history_format = env::var("BFT_HISTORY_FORMAT")
+            .map(|v| match v.to_lowercase().as_str() {
+                "bash" => HistoryFormat::Bash,
+                "zsh" => HistoryFormat::Zsh,
+                "fish" => HistoryFormat::Fish,
+                _ => HistoryFormat::Auto,
+            })
+            .unwrap_or(HistoryFormat::Auto);
+
         let selector_type = env::var("BFT_SELECTOR")
             .map(|v| match v.to_lowercase().as_str() {
                 "dialoguer" => SelectorType::Dialoguer,
+                "fzf" => SelectorType::Fzf,
                 _ => SelectorType::Dialoguer,
             })
             .unwrap_or(SelectorType::Dialoguer);
 
+        let matcher = env::var("BFT_MATCHER")
+            .map(|v| match v.to_lowercase().as_str() {
+                "substring" => MatcherType::Substring,
+                "prefix" => MatcherType::Prefix,
+                _ => MatcherType::Skim,
+            })
+            .unwrap_or(MatcherType::Skim);
+
+        let match_case = env::var("BFT_MATCH_CASE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let show_indices = env::var("BFT_SHOW_INDICES")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let show_types = env::var("BFT_SHOW_TYPES")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let confirm_single = env::var("BFT_CONFIRM_SINGLE")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let carapace_path = env::var("BFT_CARAPACE_BIN").ok().filter(|v| !v.is_empty());
+
+        let carapace_timeout_ms = env::var("BFT_CARAPACE_TIMEOUT_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(300);
+
         Self {
             selector_height,
             auto_common_prefix,
@@ -116,7 +598,20 @@ impl Config {
             prompt,
             completion_sep: default_completion_sep(),
             no_empty_cmd_completion,
+            trim_candidates,
+            strip_leading_dot_slash,
+            interactive_bash,
+            merge_carapace_bash,
+            bypass_commands,
+            history_format,
             selector_type,
+            matcher,
+            match_case,
+            show_indices,
+            show_types,
+            confirm_single,
+            carapace_path,
+            carapace_timeout_ms,
             ..Default::default()
         }
     }
@@ -143,14 +638,64 @@ mod tests {
         assert_eq!(config.providers.len(), 4); // default
     }
 
+    #[test]
+    fn test_bft_config_overrides_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("custom.json5");
+        fs::write(&path, "{ prompt: 'custom> ' }").unwrap();
+
+        unsafe { env::set_var("BFT_CONFIG", &path) };
+        let config = Config::from_file();
+        unsafe { env::remove_var("BFT_CONFIG") };
+
+        assert_eq!(config.unwrap().prompt, "custom> ");
+    }
+
+    #[test]
+    fn test_deserialize_matcher() {
+        let config: Config = json5::from_str("{}").unwrap();
+        assert_eq!(config.matcher, MatcherType::Skim);
+
+        let config: Config = json5::from_str("{ matcher: 'prefix' }").unwrap();
+        assert_eq!(config.matcher, MatcherType::Prefix);
+    }
+
+    #[test]
+    fn test_deserialize_trim_candidates() {
+        let config: Config = json5::from_str("{}").unwrap();
+        assert_eq!(config.trim_candidates, TrimCandidates::Bash);
+
+        let config: Config = json5::from_str("{ trim_candidates: 'all' }").unwrap();
+        assert_eq!(config.trim_candidates, TrimCandidates::All);
+    }
+
+    #[test]
+    fn test_json_schema_properties() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&Config::json_schema().unwrap()).unwrap();
+        let properties = schema["properties"].as_object().unwrap();
+        for key in ["selector_height", "prompt", "selector_type", "providers"] {
+            assert!(properties.contains_key(key), "missing {}", key);
+        }
+        assert!(!properties.contains_key("completion_sep"));
+    }
+
     #[test]
     fn test_deserialize_providers_override() {
         let json = "{ providers: [{ type: 'bash' }] }";
         let config: Config = json5::from_str(json).unwrap();
         assert_eq!(config.providers.len(), 1);
         match config.providers[0] {
-            ProviderConfig::Bash => {}
+            ProviderConfig::Bash { limit: None, .. } => {}
             _ => panic!("Expected Bash provider"),
         }
     }
+
+    #[test]
+    fn test_deserialize_provider_limit() {
+        let json = "{ providers: [{ type: 'carapace', limit: 50 }, { type: 'git' }] }";
+        let config: Config = json5::from_str(json).unwrap();
+        assert_eq!(config.providers[0].limit(), Some(50));
+        assert_eq!(config.providers[1].limit(), None);
+    }
 }
@@ -8,21 +8,57 @@ pub mod selector;
 use anyhow::Result;
 use log::{debug, info};
 use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::exit;
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
+use crate::bash::history;
+use crate::completion::ansible::AnsibleProvider;
+use crate::completion::cache::{self, CacheKey, CandidateCache};
+use crate::completion::carapace::CARAPACE;
+use crate::completion::compression::CompressionProvider;
+use crate::completion::cron::CronProvider;
+use crate::completion::docker::DockerProvider;
+use crate::completion::editor::EditorRecentProvider;
+use crate::completion::external::ExternalProvider;
+use crate::completion::files;
+use crate::completion::git::GitProvider;
+use crate::completion::go::GoProvider;
+use crate::completion::just::JustProvider;
+use crate::completion::make::MakeProvider;
+use crate::completion::man::ManProvider;
+use crate::completion::message::MessageProvider;
+use crate::completion::network::NetworkProvider;
+use crate::completion::packages::{self, PackagesProvider};
+use crate::completion::process;
+use crate::completion::python::PythonProvider;
+use crate::completion::ripgrep::RipgrepProvider;
+use crate::completion::spec::{self, SpecProvider};
+use crate::completion::ssh::SshProvider;
+use crate::completion::systemd::SystemdProvider;
+use crate::completion::timezone::{self, TimezoneProvider};
 use crate::completion::{
     BashProvider, CarapaceProvider, CompletionContext, CompletionEngine, CompletionEntry,
-    CompletionResult, EnvVarProvider, HistoryProvider, PipelineProvider, ProviderKind,
+    CompletionProvider, CompletionResult, CompletionSpec, EnvVarProvider, HistoryProvider,
+    PipelineProvider, ProviderKind,
 };
-use crate::config::{Config, ProviderConfig};
+use crate::config::{AmbiguousBehavior, Config, ProviderConfig, SelectorType, TrimCandidates};
 use crate::selector::{Selector, SelectorConfig};
 
 const ARG_INIT_SCRIPT: &str = "--init-script";
+const ARG_CONFIG_SCHEMA: &str = "--config-schema";
+const ARG_COMPLETE_WORD: &str = "--complete-word";
+const ARG_FROM_STDIN: &str = "--from-stdin";
+const ARG_LIST_PROVIDERS: &str = "--list-providers";
 const ENV_READLINE_LINE: &str = "READLINE_LINE";
 const ENV_READLINE_POINT: &str = "READLINE_POINT";
 const DEFAULT_READLINE_POINT_STR: &str = "0";
 const DEFAULT_READLINE_POINT: usize = 0;
 const DEFAULT_SELECTOR_HEIGHT: &str = "40%";
+/// Exit code telling the shell binding to run its native completion instead
+const EXIT_CODE_BYPASS: i32 = 3;
 
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -32,20 +68,51 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let readline_line = if args.len() >= 2 {
-        args[1].clone()
+    if args.len() > 1 && args[1] == ARG_CONFIG_SCHEMA {
+        println!("{}", Config::json_schema()?);
+        return Ok(());
+    }
+
+    if args.len() > 1 && args[1] == ARG_LIST_PROVIDERS {
+        for line in list_providers(&Config::load()) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    // `--complete-word <cword> -- words...` takes words already split by the caller
+    let complete_word = if args.len() > 1 && args[1] == ARG_COMPLETE_WORD {
+        Some(parse_complete_word_args(&args[2..])?)
+    } else {
+        None
+    };
+
+    // `--from-stdin <line> <point>` reads the candidates from stdin instead of the providers
+    let from_stdin = args.len() > 1 && args[1] == ARG_FROM_STDIN;
+    let line_args = if from_stdin { &args[1..] } else { &args[..] };
+
+    let readline_line = if line_args.len() >= 2 {
+        line_args[1].clone()
     } else {
         env::var(ENV_READLINE_LINE).unwrap_or_default()
     };
 
-    let readline_point: usize = if args.len() >= 3 {
-        args[2].parse().unwrap_or(DEFAULT_READLINE_POINT)
+    let readline_point: usize = if line_args.len() >= 3 {
+        line_args[2].parse().unwrap_or(DEFAULT_READLINE_POINT)
     } else {
         env::var(ENV_READLINE_POINT)
             .unwrap_or_else(|_| DEFAULT_READLINE_POINT_STR.to_string())
             .parse()
             .unwrap_or(DEFAULT_READLINE_POINT)
     };
+    let (readline_line, readline_point, word_parsed) = match complete_word {
+        Some((index, words)) => {
+            let (line, point, parsed) = parser::parse_words(words, index);
+            (line, point, Some(parsed))
+        }
+        None => (readline_line, readline_point, None),
+    };
+    let word_mode = word_parsed.is_some();
 
     env_logger::builder()
         .format_file(true)
@@ -55,7 +122,12 @@ fn main() -> Result<()> {
     info!("Starting bft");
 
     let config = Config::load();
+    if config.share_dir_listings {
+        files::share_listings();
+    }
 
+    // READLINE_POINT is a byte index; keep it on a char boundary from here on
+    let readline_point = parser::clamp_to_char_boundary(&readline_line, readline_point);
     debug!("Input: line='{}', point={}", readline_line, readline_point);
 
     if config.no_empty_cmd_completion && readline_line.trim().is_empty() {
@@ -63,40 +135,47 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let parsed = parser::parse_shell_line(&readline_line, readline_point)?;
+    let parsed = match word_parsed {
+        Some(parsed) => parsed,
+        None => parser::parse_shell_line(&readline_line, readline_point)?,
+    };
     debug!("Parsed command: {:?}", parsed);
 
-    let ctx = Rc::new(CompletionContext::from_parsed(
-        &parsed,
-        readline_line.clone(),
-        readline_point,
-    ));
+    let ctx = Rc::new(
+        CompletionContext::from_parsed_with_wrappers(
+            &parsed,
+            readline_line.clone(),
+            readline_point,
+            &config.wrapper_commands,
+        )
+        .with_command_map(&config.command_map)
+        .with_subcommand_dispatchers(&config.subcommand_dispatchers),
+    );
 
     debug!(
         "Command: '{}', current_word: '{}', current_word_idx: {}, is_after_pipe: {}",
         ctx.command, ctx.current_word, ctx.current_word_idx, ctx.is_after_pipe
     );
 
-    let mut pipeline = PipelineProvider::new("dynamic");
-    for provider_config in &config.providers {
-        match provider_config {
-            ProviderConfig::History { limit } => {
-                pipeline.with(HistoryProvider::new(*limit));
-            }
-            ProviderConfig::Carapace => {
-                pipeline.with(CarapaceProvider::new());
-            }
-            ProviderConfig::Bash => {
-                pipeline.with(BashProvider::new());
-            }
-            ProviderConfig::EnvVar => {
-                pipeline.with(EnvVarProvider::new());
-            }
-        }
-    }
-
-    let engine = CompletionEngine::new(Box::new(pipeline));
-    let result = engine.complete(&ctx)?;
+    let result = if from_stdin {
+        let Some(result) = stdin_result(&io::read_to_string(io::stdin())?) else {
+            debug!("No candidates on stdin");
+            return Ok(());
+        };
+        result
+    } else if ctx.suppresses_completion() {
+        debug!("Here-document delimiter, skipping completion");
+        return Ok(());
+    } else {
+        let Some(result) = complete_cached(&ctx, &config)? else {
+            info!(
+                "Command '{}' is bypassed, deferring to native completion",
+                ctx.command
+            );
+            exit(EXIT_CODE_BYPASS);
+        };
+        result
+    };
 
     info!(
         "Using {} provider, generated {} candidates",
@@ -106,15 +185,24 @@ fn main() -> Result<()> {
 
     let candidates = apply_post_processing(&result, &ctx, &config)?;
 
-    let (candidates, no_space_after_completion, _prefix) = crate::quoting::find_common_prefix(
-        &candidates,
-        ctx.current_word.len(),
-        config.auto_common_prefix_part,
-    );
+    let (mut candidates, mut no_space_after_completion, mut open_selector) =
+        prepare_selection(candidates, &ctx, &config);
+    if open_selector && candidates.len() > 1 && config.ambiguous_behavior != AmbiguousBehavior::Menu
+    {
+        info!(
+            "{} candidates, {:?} instead of the selector",
+            candidates.len(),
+            config.ambiguous_behavior
+        );
+        (candidates, no_space_after_completion) =
+            ambiguous_insertion(candidates, &ctx, config.ambiguous_behavior);
+        open_selector = false;
+        ring_bell(&mut io::stderr())?;
+    }
 
     debug!("After filtering: {} candidates", candidates.len());
 
-    let selected = if candidates.len() > 1 {
+    let selected = if open_selector {
         let selector_config = SelectorConfig {
             ctx: ctx.clone(),
             prompt: config.prompt.clone(),
@@ -124,35 +212,41 @@ fn main() -> Result<()> {
                 .unwrap_or_else(|| DEFAULT_SELECTOR_HEIGHT.to_string()),
             header: Some(readline_line.clone()),
             fuzzy: true,
+            nosort: result.spec.options.nosort,
+            matcher: config.matcher,
+            show_indices: config.show_indices,
+            show_types: config.show_types,
+            max_description_len: config.max_description_len,
+            wrap_candidates: config.wrap_candidates,
+            confirm_single: config.confirm_single,
         };
 
         info!("Opening selector with {} candidates", candidates.len());
 
-        let selector = crate::selector::dialoguer::DialoguerSelector::new();
-        selector.select_one(&candidates, &ctx.current_word, &selector_config)?
+        let selector = build_selector(&config.selector_type)?;
+        selector.select_one(&candidates, &ctx.current_word_prefix, &selector_config)?
     } else {
         debug!("Single candidate, skipping selector");
         candidates.first().cloned()
     };
 
     if let Some(entry) = selected {
-        debug!("Selected completion: '{}' ({})", entry.value, entry.kind);
+        debug!(
+            "Selected completion: '{}' ({}, {})",
+            entry.value, entry.kind, entry.candidate_type
+        );
+        let no_space = space_suppressed(no_space_after_completion, &result.spec, &entry);
         let mut completion = entry.value;
 
-        let current_word_char_count = ctx.current_word.chars().count();
-        let cursor_position_chars = readline_line.chars().take(readline_point).count();
-        let replacement_start_char_index =
-            cursor_position_chars.saturating_sub(current_word_char_count);
-        let before: String = readline_line
-            .chars()
-            .take(replacement_start_char_index)
-            .collect();
+        let before = &readline_line
+            [..replacement_start(&readline_line, readline_point, &ctx.current_word_prefix)];
 
-        let is_full_line = !before.is_empty() && completion.starts_with(&before);
+        let is_full_line = !before.is_empty() && completion.starts_with(before);
 
         if !is_full_line
             && entry.kind != ProviderKind::History
             && entry.kind != ProviderKind::EnvVar
+            && entry.kind != ProviderKind::Message
             && (result.spec.options.filenames
                 || result.spec.options.default
                 || result.spec.options.bashdefault)
@@ -160,13 +254,19 @@ fn main() -> Result<()> {
             completion = crate::quoting::quote_filename(&completion, true);
         }
 
-        insert_completion(
-            &readline_line,
-            readline_point,
-            &completion,
-            no_space_after_completion,
-            &ctx.current_word,
-        )?;
+        if word_mode {
+            // Callers of the word API replace COMP_WORDS[COMP_CWORD] themselves
+            println!("{}", completion);
+        } else {
+            insert_completion(
+                &mut io::stdout(),
+                &readline_line,
+                readline_point,
+                &completion,
+                no_space,
+                &ctx.current_word_prefix,
+            )?;
+        }
     } else {
         info!("No completion selected");
     }
@@ -175,10 +275,408 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Reuse the candidates of a recent completion at the same position when
+/// `candidate_cache_ttl_ms` is set, otherwise run the providers. `None` when
+/// the command is bypassed
+fn complete_cached(
+    ctx: &Rc<CompletionContext>,
+    config: &Config,
+) -> Result<Option<CompletionResult>> {
+    let cache = match cache::default_cache_dir() {
+        Some(dir) if config.candidate_cache_ttl_ms > 0 => {
+            CandidateCache::new(dir, Duration::from_millis(config.candidate_cache_ttl_ms))
+        }
+        _ => return complete_with_providers(ctx, config),
+    };
+
+    let key = CacheKey::for_context(ctx);
+    if let Some(result) = cache.lookup(&key, &ctx.current_word, SystemTime::now()) {
+        debug!("Reusing {} cached candidates", result.candidates.len());
+        return Ok(Some(result));
+    }
+    let result = complete_with_providers(ctx, config)?;
+    if let Some(result) = &result {
+        cache.store(&key, &ctx.current_word, result, SystemTime::now());
+    }
+    Ok(result)
+}
+
+/// Run the configured provider pipeline; `None` when the context is bypassed
+fn complete_with_providers(
+    ctx: &Rc<CompletionContext>,
+    config: &Config,
+) -> Result<Option<CompletionResult>> {
+    if is_bypassed(ctx, config) {
+        return Ok(None);
+    }
+
+    let skip_native = is_word_too_long(ctx, config);
+    if skip_native {
+        info!(
+            "Current word is {} bytes (max_word_len {}), skipping carapace and bash",
+            ctx.current_word.len(),
+            config.max_word_len
+        );
+    }
+
+    let mut pipeline = PipelineProvider::new("dynamic");
+    pipeline.with_normalization(config.trim_candidates == TrimCandidates::All);
+    let mut native_added = false;
+    for provider_config in &config.providers {
+        if is_native(provider_config) && skip_native {
+            continue;
+        }
+        if is_native(provider_config) && !config.merge_carapace_bash {
+            // Carapace and bash share one slot where the first to answer wins
+            if !native_added {
+                let mut native = PipelineProvider::exclusive("native");
+                for c in config.providers.iter().filter(|c| is_native(c)) {
+                    native.with_limited(build_provider(c, config), c.limit());
+                }
+                pipeline.with(native);
+                native_added = true;
+            }
+            continue;
+        }
+        pipeline.with_limited(
+            build_provider(provider_config, config),
+            provider_config.limit(),
+        );
+    }
+
+    let engine = CompletionEngine::new(Box::new(pipeline))
+        .with_short_flags(config.short_flags.clone())
+        .with_timeout(Duration::from_millis(config.subprocess_timeout_ms));
+    Ok(Some(engine.complete(ctx)?))
+}
+
+/// Newline-separated candidates piped to `--from-stdin`, blank lines skipped
+fn parse_stdin_candidates(input: &str) -> Vec<CompletionEntry> {
+    input
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| CompletionEntry::new(line.to_string(), ProviderKind::Unknown))
+        .collect()
+}
+
+/// The `--from-stdin` candidates as a completion result, `None` when nothing was piped in
+fn stdin_result(input: &str) -> Option<CompletionResult> {
+    let candidates = parse_stdin_candidates(input);
+    (!candidates.is_empty()).then(|| CompletionResult {
+        candidates,
+        used_provider: ProviderKind::Unknown,
+        spec: Default::default(),
+    })
+}
+
+/// Parse `<cword_index> [--] word0 word1 ...` for `--complete-word`
+fn parse_complete_word_args(args: &[String]) -> Result<(usize, Vec<String>)> {
+    let usage = || {
+        anyhow::anyhow!(
+            "usage: bft {} <cword_index> -- word0 word1 ...",
+            ARG_COMPLETE_WORD
+        )
+    };
+    let (index, rest) = args.split_first().ok_or_else(usage)?;
+    let index: usize = index.parse().map_err(|_| usage())?;
+    let words = rest
+        .strip_prefix(&["--".to_string()])
+        .unwrap_or(rest)
+        .to_vec();
+    if index > words.len() {
+        return Err(usage());
+    }
+    Ok((index, words))
+}
+
+fn build_provider(
+    provider_config: &ProviderConfig,
+    config: &Config,
+) -> Box<dyn CompletionProvider> {
+    let timeout = Duration::from_millis(
+        provider_config
+            .timeout_ms()
+            .unwrap_or(config.subprocess_timeout_ms),
+    );
+    match provider_config {
+        ProviderConfig::History { limit } => {
+            Box::new(HistoryProvider::new(*limit).with_format(config.history_format))
+        }
+        ProviderConfig::Carapace { timeout_ms, .. } => Box::new(
+            CarapaceProvider::new()
+                .with_program(config.carapace_path.as_ref().map(PathBuf::from))
+                .with_timeout(Duration::from_millis(
+                    timeout_ms.unwrap_or(config.carapace_timeout_ms),
+                ))
+                .with_command_args(config.carapace_command_args.clone()),
+        ),
+        ProviderConfig::Bash { .. } => Box::new(
+            BashProvider::new()
+                .with_trim_candidates(config.trim_candidates != TrimCandidates::Off)
+                .with_interactive(config.interactive_bash)
+                .with_assignment_values(config.assignment_values.clone())
+                .with_dir_only_commands(config.dir_only_commands.clone())
+                .with_repo_directories(
+                    config
+                        .providers
+                        .iter()
+                        .any(|p| matches!(p, ProviderConfig::Git { .. })),
+                )
+                .with_path_search_vars(config.path_search_vars.clone())
+                .with_timeout(timeout),
+        ),
+        ProviderConfig::EnvVar { .. } => {
+            Box::new(EnvVarProvider::new().with_match_case(config.match_case))
+        }
+        ProviderConfig::Ansible { .. } => Box::new(AnsibleProvider::new()),
+        ProviderConfig::Compression { .. } => Box::new(CompressionProvider::new()),
+        ProviderConfig::Cron { .. } => Box::new(CronProvider::new()),
+        ProviderConfig::Docker { .. } => Box::new(DockerProvider::new().with_timeout(timeout)),
+        ProviderConfig::EditorRecent { viminfo, .. } => {
+            Box::new(EditorRecentProvider::new().with_viminfo(viminfo.as_ref().map(PathBuf::from)))
+        }
+        ProviderConfig::External { cmd, .. } => {
+            Box::new(ExternalProvider::new(cmd.clone()).with_timeout(timeout))
+        }
+        ProviderConfig::Git { .. } => Box::new(GitProvider::new().with_timeout(timeout)),
+        ProviderConfig::Go { .. } => Box::new(GoProvider::new().with_timeout(timeout)),
+        ProviderConfig::Just { .. } => Box::new(JustProvider::new().with_timeout(timeout)),
+        ProviderConfig::Make { .. } => Box::new(MakeProvider::new()),
+        ProviderConfig::Man { .. } => Box::new(ManProvider::new()),
+        ProviderConfig::Message { .. } => Box::new(
+            MessageProvider::new()
+                .with_flags(config.message_flags.clone())
+                .with_format(config.history_format),
+        ),
+        ProviderConfig::Network { .. } => Box::new(NetworkProvider::new().with_timeout(timeout)),
+        ProviderConfig::Packages { cache_ttl_secs, .. } => Box::new(
+            PackagesProvider::new()
+                .with_cache_ttl(
+                    cache_ttl_secs
+                        .map(Duration::from_secs)
+                        .unwrap_or(packages::DEFAULT_CACHE_TTL),
+                )
+                .with_timeout(timeout),
+        ),
+        ProviderConfig::Python {
+            package_cache,
+            interpreters,
+            ..
+        } => Box::new(
+            PythonProvider::new()
+                .with_package_cache(package_cache.as_ref().map(PathBuf::from))
+                .with_interpreters(interpreters.clone())
+                .with_timeout(timeout),
+        ),
+        ProviderConfig::Ripgrep { .. } => Box::new(RipgrepProvider::new().with_timeout(timeout)),
+        ProviderConfig::Spec { dir, .. } => {
+            Box::new(SpecProvider::new().with_dir(dir.as_ref().map(PathBuf::from)))
+        }
+        ProviderConfig::Ssh { remote_paths, .. } => Box::new(
+            SshProvider::new()
+                .with_remote_paths(*remote_paths)
+                .with_timeout(timeout),
+        ),
+        ProviderConfig::Systemd { .. } => Box::new(SystemdProvider::new().with_timeout(timeout)),
+        ProviderConfig::Timezone { zoneinfo, .. } => {
+            Box::new(TimezoneProvider::new().with_zoneinfo(zoneinfo.as_ref().map(PathBuf::from)))
+        }
+    }
+}
+
+/// The selector for `selector_type`, if its feature was compiled in
+fn build_selector(selector_type: &SelectorType) -> Result<Box<dyn Selector>> {
+    match selector_type {
+        #[cfg(feature = "dialoguer")]
+        SelectorType::Dialoguer => Ok(Box::new(
+            crate::selector::dialoguer::DialoguerSelector::new(),
+        )),
+        #[cfg(not(feature = "dialoguer"))]
+        SelectorType::Dialoguer => Err(anyhow::anyhow!(
+            "bft was built without the `dialoguer` feature"
+        )),
+        #[cfg(feature = "fzf")]
+        SelectorType::Fzf => Ok(Box::new(crate::selector::fzf::FzfSelector::new())),
+        #[cfg(not(feature = "fzf"))]
+        SelectorType::Fzf => Err(anyhow::anyhow!("bft was built without the `fzf` feature")),
+    }
+}
+
+/// What a provider needs at runtime, checked by `--list-providers`
+enum Requirement {
+    /// Nothing outside bft (or only files found while completing)
+    BuiltIn,
+    /// One of these programs
+    Program(Vec<PathBuf>),
+    /// A file or directory it reads
+    Path(Option<PathBuf>),
+}
+
+fn provider_requirement(provider_config: &ProviderConfig, config: &Config) -> Requirement {
+    let programs = |names: &[&str]| Requirement::Program(names.iter().map(PathBuf::from).collect());
+    match provider_config {
+        ProviderConfig::Carapace { .. } => Requirement::Program(vec![PathBuf::from(
+            config.carapace_path.as_deref().unwrap_or(CARAPACE),
+        )]),
+        ProviderConfig::Bash { .. } => programs(&["bash"]),
+        ProviderConfig::History { .. } | ProviderConfig::Message { .. } => {
+            Requirement::Path(history::get_history_file())
+        }
+        ProviderConfig::Docker { .. } => programs(&["docker", "podman"]),
+        ProviderConfig::EditorRecent { viminfo, .. } => {
+            Requirement::Path(viminfo.as_ref().map(PathBuf::from).or_else(|| {
+                env::var("HOME")
+                    .ok()
+                    .map(|h| PathBuf::from(h).join(".viminfo"))
+            }))
+        }
+        ProviderConfig::External { cmd, .. } => {
+            programs(&[cmd.split_whitespace().next().unwrap_or("sh")])
+        }
+        ProviderConfig::Git { .. } => programs(&["git"]),
+        ProviderConfig::Go { .. } => programs(&["go"]),
+        ProviderConfig::Just { .. } => programs(&["just"]),
+        ProviderConfig::Network { .. } => programs(&["nmcli", "ip"]),
+        ProviderConfig::Packages { .. } => programs(&["brew", "apt-cache"]),
+        ProviderConfig::Python { .. } => programs(&["python3", "python"]),
+        ProviderConfig::Ripgrep { .. } => programs(&["rg"]),
+        ProviderConfig::Spec { dir, .. } => Requirement::Path(Some(
+            dir.as_ref()
+                .map(PathBuf::from)
+                .unwrap_or_else(spec::default_spec_dir),
+        )),
+        ProviderConfig::Ssh { .. } => programs(&["ssh"]),
+        ProviderConfig::Systemd { .. } => programs(&["systemctl"]),
+        ProviderConfig::Timezone { zoneinfo, .. } => Requirement::Path(Some(PathBuf::from(
+            zoneinfo.as_deref().unwrap_or(timezone::DEFAULT_ZONEINFO),
+        ))),
+        ProviderConfig::EnvVar { .. }
+        | ProviderConfig::Ansible { .. }
+        | ProviderConfig::Compression { .. }
+        | ProviderConfig::Cron { .. }
+        | ProviderConfig::Make { .. }
+        | ProviderConfig::Man { .. } => Requirement::BuiltIn,
+    }
+}
+
+/// One `name: status` line per configured provider
+fn list_providers(config: &Config) -> Vec<String> {
+    config
+        .providers
+        .iter()
+        .map(|provider_config| {
+            let provider = build_provider(provider_config, config);
+            let status = match provider_requirement(provider_config, config) {
+                Requirement::BuiltIn => "built in".to_string(),
+                Requirement::Program(programs) => {
+                    match programs.iter().find_map(|p| process::find_program(p)) {
+                        Some(path) => format!("found at {}", path.display()),
+                        None => {
+                            let names: Vec<String> =
+                                programs.iter().map(|p| p.display().to_string()).collect();
+                            format!("not found ({})", names.join(", "))
+                        }
+                    }
+                }
+                Requirement::Path(Some(path)) if path.exists() => {
+                    format!("{} (exists)", path.display())
+                }
+                Requirement::Path(Some(path)) => format!("{} (missing)", path.display()),
+                Requirement::Path(None) => "no path configured".to_string(),
+            };
+            format!("{}: {}", provider.name(), status)
+        })
+        .collect()
+}
+
+fn is_native(provider_config: &ProviderConfig) -> bool {
+    matches!(
+        provider_config,
+        ProviderConfig::Carapace { .. } | ProviderConfig::Bash { .. }
+    )
+}
+
+/// Whether the current word is too long to hand to the subprocess-backed providers
+fn is_word_too_long(ctx: &CompletionContext, config: &Config) -> bool {
+    ctx.current_word.len() > config.max_word_len
+}
+
+fn is_bypassed(ctx: &CompletionContext, config: &Config) -> bool {
+    !ctx.command.is_empty() && config.bypass_commands.contains(&ctx.command)
+}
+
+/// Collapse candidates to their common prefix where configured and decide
+/// whether the selector opens. Returns (candidates, no_space, open_selector)
+fn prepare_selection(
+    candidates: Vec<CompletionEntry>,
+    ctx: &CompletionContext,
+    config: &Config,
+) -> (Vec<CompletionEntry>, bool, bool) {
+    // A lone candidate is shown as-is so its description can be confirmed
+    if config.confirm_single && candidates.len() == 1 {
+        return (candidates, false, true);
+    }
+
+    if config.preselect_exact_prefix
+        && let Some(entry) = unique_prefix_match(&candidates, &ctx.current_word_prefix)
+    {
+        debug!(
+            "'{}' is the only candidate starting with the word",
+            entry.value
+        );
+        return (vec![entry], false, false);
+    }
+
+    let (candidates, no_space, _prefix) = crate::quoting::find_common_prefix(
+        &candidates,
+        ctx.current_word_prefix.chars().count(),
+        config.auto_common_prefix_part,
+    );
+    let open_selector = candidates.len() > 1;
+    (candidates, no_space, open_selector)
+}
+
+/// Readline-style handling of several candidates: the common prefix to insert
+/// (when it extends the word and `behavior` inserts it), else nothing.
+/// Returns (candidates, no_space)
+fn ambiguous_insertion(
+    candidates: Vec<CompletionEntry>,
+    ctx: &CompletionContext,
+    behavior: AmbiguousBehavior,
+) -> (Vec<CompletionEntry>, bool) {
+    if behavior != AmbiguousBehavior::InsertPrefixThenBell {
+        return (Vec::new(), false);
+    }
+    let (candidates, no_space, prefix) = crate::quoting::find_common_prefix(
+        &candidates,
+        ctx.current_word_prefix.chars().count(),
+        true,
+    );
+    if prefix.is_empty() {
+        return (Vec::new(), false);
+    }
+    (candidates, no_space)
+}
+
+fn ring_bell(out: &mut impl Write) -> io::Result<()> {
+    out.write_all(b"\x07")?;
+    out.flush()
+}
+
+/// The single candidate that starts with a non-empty `word`, if exactly one does
+fn unique_prefix_match(candidates: &[CompletionEntry], word: &str) -> Option<CompletionEntry> {
+    if word.is_empty() {
+        return None;
+    }
+    let mut matches = candidates.iter().filter(|c| c.value.starts_with(word));
+    let entry = matches.next()?;
+    matches.next().is_none().then(|| entry.clone())
+}
+
 fn apply_post_processing(
     result: &CompletionResult,
     ctx: &CompletionContext,
-    _config: &Config,
+    config: &Config,
 ) -> Result<Vec<CompletionEntry>, crate::completion::CompletionError> {
     let mut candidates = result.candidates.clone();
 
@@ -191,65 +689,429 @@ fn apply_post_processing(
         candidates = crate::quoting::mark_directories(candidates);
     }
 
+    if config.strip_leading_dot_slash {
+        candidates = crate::quoting::strip_leading_dot_slash(candidates, &ctx.current_word);
+    }
+
+    if !result.spec.options.nosort {
+        candidates = crate::quoting::sort_candidates(candidates, config.sort);
+    }
+
+    if let Some(extensions) = config.preferred_extensions.get(&ctx.command) {
+        candidates = crate::quoting::sort_by_extensions(candidates, extensions);
+    }
+
+    if config.pretty_paths {
+        let home = env::var("HOME").ok();
+        candidates = crate::quoting::apply_pretty_paths(candidates, home.as_deref());
+    }
+
     Ok(candidates)
 }
 
+/// Whether to leave out the space after `entry`. The compspec only governs
+/// bash's candidates: none follows those of a `nospace` compspec, nor one
+/// ending in its `-S` suffix, which ends the word itself (`=`, `,` or a
+/// separator such as a space)
+fn space_suppressed(no_space: bool, spec: &CompletionSpec, entry: &CompletionEntry) -> bool {
+    if no_space {
+        return true;
+    }
+    entry.kind == ProviderKind::Bash
+        && (spec.options.nospace || !spec.suffix.is_empty() && entry.value.ends_with(&spec.suffix))
+}
+
+/// Write the completed line and point to `out` as the `READLINE_*` assignments
+/// the shell binding evaluates
 fn insert_completion(
+    out: &mut impl Write,
     line: &str,
     point: usize,
     completion: &str,
     nospace: bool,
     current_word: &str,
 ) -> Result<()> {
-    let current_word_char_count = current_word.chars().count();
-    let cursor_position_chars = line.chars().take(point).count();
-
-    let replacement_start_char_index =
-        cursor_position_chars.saturating_sub(current_word_char_count);
+    let (new_line, new_point) =
+        build_completion_line(line, point, completion, nospace, current_word)?;
 
-    let before: String = line.chars().take(replacement_start_char_index).collect();
-    let after: String = line.chars().skip(cursor_position_chars).collect();
+    let quoted_line = shlex::try_quote(&new_line)
+        .map_err(|e| anyhow::anyhow!("Failed to quote completed line: {}", e))?;
+    writeln!(out, "READLINE_LINE={}", quoted_line)?;
+    writeln!(out, "READLINE_POINT={}", new_point)?;
 
-    let new_line = if completion.starts_with(&before) && !before.is_empty() {
-        format!("{}{}", completion, after)
-    } else {
-        format!("{}{}{}", before, completion, after)
-    };
-
-    let new_point = if completion.starts_with(&before) && !before.is_empty() {
-        completion.chars().count()
-    } else {
-        replacement_start_char_index + completion.chars().count()
-    };
+    Ok(())
+}
 
-    if !nospace && !completion.ends_with('/') {
-        let new_point_byte: usize = new_line.chars().take(new_point).map(|c| c.len_utf8()).sum();
+/// Byte index where the word being replaced starts: `current_word` ends at `point`
+fn replacement_start(line: &str, point: usize, current_word: &str) -> usize {
+    let point = parser::clamp_to_char_boundary(line, point);
+    parser::clamp_to_char_boundary(line, point.saturating_sub(current_word.len()))
+}
 
-        let mut new_line_bytes: Vec<u8> = new_line.bytes().collect();
-        new_line_bytes.insert(new_point_byte, b' ');
+/// Compute the edited line and the new point. `point` and the returned point
+/// are byte indices; `current_word` is the part of the word before the cursor;
+/// text after the cursor is kept.
+fn build_completion_line(
+    line: &str,
+    point: usize,
+    completion: &str,
+    nospace: bool,
+    current_word: &str,
+) -> Result<(String, usize)> {
+    let point = parser::clamp_to_char_boundary(line, point);
+    let start = replacement_start(line, point, current_word);
 
-        let new_line_with_space = String::from_utf8(new_line_bytes)
-            .map_err(|e| anyhow::anyhow!("Failed to convert line to UTF-8: {}", e))?;
-        let final_point = new_point_byte + 1;
+    let before = &line[..start];
+    let after = &line[point..];
 
-        println!(
-            "READLINE_LINE={}",
-            shlex::try_quote(&new_line_with_space).unwrap()
-        );
-        println!("READLINE_POINT={}", final_point);
+    let (mut new_line, mut new_point) = if completion.starts_with(before) && !before.is_empty() {
+        (format!("{}{}", completion, after), completion.len())
     } else {
-        let new_point_byte: usize = new_line.chars().take(new_point).map(|c| c.len_utf8()).sum();
-        println!("READLINE_LINE={}", shlex::try_quote(&new_line).unwrap());
-        println!("READLINE_POINT={}", new_point_byte);
+        (
+            format!("{}{}{}", before, completion, after),
+            start + completion.len(),
+        )
+    };
+
+    // Directories and `--opt=`/`NAME=` continue with their value
+    if !nospace && !completion.ends_with(['/', '=']) {
+        new_line.insert(new_point, ' ');
+        new_point += 1;
     }
 
-    Ok(())
+    Ok((new_line, new_point))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn strings(ws: &[&str]) -> Vec<String> {
+        ws.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn test_complete_word_api() {
+        let (index, words) =
+            parse_complete_word_args(&strings(&["1", "--", "echo", "$BFT_WORD_T"])).unwrap();
+        assert_eq!(index, 1);
+
+        let (line, point, parsed) = parser::parse_words(words, index);
+        let ctx = CompletionContext::from_parsed(&parsed, line, point);
+        assert_eq!(ctx.command, "echo");
+        assert_eq!(ctx.current_word, "$BFT_WORD_T");
+
+        unsafe { env::set_var("BFT_WORD_TEST", "1") };
+        let result = EnvVarProvider::new().try_complete(&ctx).unwrap().unwrap();
+        assert!(result.iter().any(|e| e.value == "$BFT_WORD_TEST"));
+        unsafe { env::remove_var("BFT_WORD_TEST") };
+
+        assert!(parse_complete_word_args(&strings(&["3", "--", "ls"])).is_err());
+        assert!(parse_complete_word_args(&strings(&["x"])).is_err());
+    }
+
+    #[test]
+    fn test_long_word_skips_native_providers() {
+        let config = Config::default();
+
+        let line = format!("echo {}", "x".repeat(config.max_word_len + 1));
+        let parsed = parser::parse_shell_line(&line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.clone(), line.len());
+        assert!(is_word_too_long(&ctx, &config));
+
+        let parsed = parser::parse_shell_line("echo xx", 7).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "echo xx".to_string(), 7);
+        assert!(!is_word_too_long(&ctx, &config));
+    }
+
+    #[test]
+    fn test_list_providers() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config {
+            carapace_path: Some(dir.path().join("no-carapace").display().to_string()),
+            providers: vec![
+                ProviderConfig::Carapace {
+                    limit: None,
+                    timeout_ms: None,
+                },
+                ProviderConfig::Bash {
+                    limit: None,
+                    timeout_ms: None,
+                },
+                ProviderConfig::Spec {
+                    limit: None,
+                    dir: Some(dir.path().display().to_string()),
+                },
+                ProviderConfig::EnvVar { limit: None },
+            ],
+            ..Default::default()
+        };
+        let lines = list_providers(&config);
+        assert_eq!(
+            lines[0],
+            format!("carapace: not found ({}/no-carapace)", dir.path().display())
+        );
+        assert!(lines[1].starts_with("bash: found at /"));
+        assert_eq!(lines[2], format!("spec: {} (exists)", dir.path().display()));
+        assert_eq!(lines[3], "envvar: built in");
+    }
+
+    #[test]
+    fn test_bypass_commands() {
+        let config = Config {
+            bypass_commands: vec!["kubectl".to_string()],
+            ..Default::default()
+        };
+
+        let parsed = parser::parse_shell_line("kubectl get ", 12).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "kubectl get ".to_string(), 12);
+        assert!(is_bypassed(&ctx, &config));
+
+        let parsed = parser::parse_shell_line("git ", 4).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, "git ".to_string(), 4);
+        assert!(!is_bypassed(&ctx, &config));
+    }
+
+    #[test]
+    fn test_bypassed_command_skips_providers() {
+        // Without providers a command that is not bypassed has nothing to offer
+        let config = Config {
+            bypass_commands: vec!["kubectl".to_string()],
+            providers: Vec::new(),
+            ..Default::default()
+        };
+        let complete = |line: &str| {
+            let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+            let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+            complete_cached(&Rc::new(ctx), &config).unwrap()
+        };
+
+        // `main` exits with EXIT_CODE_BYPASS, before anything is written to stdout
+        assert!(complete("kubectl get ").is_none());
+        assert!(complete("git status ").unwrap().candidates.is_empty());
+    }
+
+    fn post_process(line: &str, candidates: &[&str], config: &Config) -> Vec<String> {
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let result = CompletionResult {
+            candidates: candidates
+                .iter()
+                .map(|c| CompletionEntry::new(c.to_string(), ProviderKind::Bash))
+                .collect(),
+            used_provider: ProviderKind::Bash,
+            spec: Default::default(),
+        };
+        apply_post_processing(&result, &ctx, config)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.value)
+            .collect()
+    }
+
+    #[test]
+    fn test_confirm_single_opens_selector() {
+        let line = "git chec";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let single = vec![
+            CompletionEntry::new("checkout".to_string(), ProviderKind::Bash)
+                .with_description("Switch branches".to_string()),
+        ];
+
+        let (candidates, _, open) = prepare_selection(single.clone(), &ctx, &Config::default());
+        assert!(!open);
+        assert_eq!(candidates[0].value, "checkout");
+
+        let config = Config {
+            confirm_single: true,
+            ..Default::default()
+        };
+        let (candidates, _, open) = prepare_selection(single, &ctx, &config);
+        assert!(open);
+        assert_eq!(
+            candidates[0].description.as_deref(),
+            Some("Switch branches")
+        );
+
+        // A prefix shared by several candidates is still inserted directly
+        let config = Config {
+            confirm_single: true,
+            auto_common_prefix_part: true,
+            ..Default::default()
+        };
+        let several = vec![
+            CompletionEntry::new("cherry".to_string(), ProviderKind::Bash),
+            CompletionEntry::new("cherry-pick".to_string(), ProviderKind::Bash),
+        ];
+        let (candidates, no_space, open) = prepare_selection(several, &ctx, &config);
+        assert!(!open && no_space);
+        assert_eq!(candidates[0].value, "cherry");
+    }
+
+    #[test]
+    fn test_ambiguous_behavior() {
+        let line = "git che";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let candidates: Vec<CompletionEntry> = ["checkout", "cherry", "cherry-pick"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+
+        // Menu: the selector opens with every candidate
+        let (shown, _, open) = prepare_selection(candidates.clone(), &ctx, &Config::default());
+        assert!(open);
+        assert_eq!(shown.len(), 3);
+
+        let (inserted, no_space) = ambiguous_insertion(
+            candidates.clone(),
+            &ctx,
+            AmbiguousBehavior::InsertPrefixThenBell,
+        );
+        assert!(inserted.is_empty() && !no_space);
+
+        let cher: Vec<CompletionEntry> = candidates[1..].to_vec();
+        let (inserted, no_space) =
+            ambiguous_insertion(cher.clone(), &ctx, AmbiguousBehavior::InsertPrefixThenBell);
+        assert_eq!(inserted.len(), 1);
+        assert_eq!(inserted[0].value, "cherry");
+        assert!(no_space);
+
+        let (inserted, _) = ambiguous_insertion(cher, &ctx, AmbiguousBehavior::BellOnly);
+        assert!(inserted.is_empty());
+
+        let mut out = Vec::new();
+        ring_bell(&mut out).unwrap();
+        assert_eq!(out, b"\x07");
+    }
+
+    #[test]
+    fn test_from_stdin_candidates() {
+        // Nothing piped in leaves the line alone
+        assert!(stdin_result("").is_none());
+        assert!(stdin_result("\n  \n").is_none());
+
+        let line = "git checkout ma";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let result = stdin_result("main\n\n").unwrap();
+        let config = Config::default();
+
+        let candidates = apply_post_processing(&result, &ctx, &config).unwrap();
+        let (candidates, no_space, open) = prepare_selection(candidates, &ctx, &config);
+        assert!(!open);
+        let mut out = Vec::new();
+        insert_completion(
+            &mut out,
+            line,
+            line.len(),
+            &candidates[0].value,
+            no_space,
+            &ctx.current_word_prefix,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "READLINE_LINE='git checkout main '\nREADLINE_POINT=18\n"
+        );
+    }
+
+    #[test]
+    fn test_preselect_exact_prefix() {
+        let line = "git sta";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        // Fuzzy providers may return candidates not starting with the word
+        let candidates: Vec<CompletionEntry> = ["stash", "status", "instaweb", "show-branch"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::History))
+            .collect();
+        let config = Config {
+            preselect_exact_prefix: true,
+            ..Default::default()
+        };
+
+        // Two candidates start with `sta`: still ambiguous
+        let (_, _, open) = prepare_selection(candidates.clone(), &ctx, &config);
+        assert!(open);
+
+        let unique: Vec<CompletionEntry> = candidates
+            .into_iter()
+            .filter(|c| c.value != "stash")
+            .collect();
+        let (selected, no_space, open) = prepare_selection(unique.clone(), &ctx, &config);
+        assert!(!open && !no_space);
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].value, "status");
+
+        // Disabled by default
+        let (_, _, open) = prepare_selection(unique, &ctx, &Config::default());
+        assert!(open);
+    }
+
+    #[test]
+    fn test_pretty_paths_display_only() {
+        let line = "vim ";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let path = "/home/ann/src/app/web/components/button.tsx";
+        let candidates = crate::quoting::apply_pretty_paths(
+            vec![CompletionEntry::new(path.to_string(), ProviderKind::Bash)],
+            Some("/home/ann"),
+        );
+        assert_eq!(candidates[0].to_string(), "~/src/…/components/button.tsx");
+
+        let (candidates, no_space, _) = prepare_selection(candidates, &ctx, &Config::default());
+        let (new_line, _) = build_completion_line(
+            line,
+            line.len(),
+            &candidates[0].value,
+            no_space,
+            &ctx.current_word_prefix,
+        )
+        .unwrap();
+        assert_eq!(new_line, format!("vim {} ", path));
+    }
+
+    #[test]
+    fn test_strip_leading_dot_slash() {
+        let candidates = ["./src", "./run.sh", "docs"];
+
+        let config = Config::default();
+        assert_eq!(
+            post_process("cat ", &candidates, &config),
+            vec!["./src", "./run.sh", "docs"]
+        );
+
+        let config = Config {
+            strip_leading_dot_slash: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            post_process("cat ", &candidates, &config),
+            vec!["src", "run.sh", "docs"]
+        );
+        // The user typed `./`, so the candidates keep it
+        assert_eq!(
+            post_process("cat ./", &["./src", "./run.sh"], &config),
+            vec!["./src", "./run.sh"]
+        );
+    }
+
+    #[test]
+    fn test_build_completion_line_point_mid_multibyte_char() {
+        // "é" occupies bytes 3..5; point 4 lands inside it and is clamped to 3
+        let (line, point) = build_completion_line("ls é", 4, "a.txt", false, "").unwrap();
+        assert_eq!(line, "ls a.txt é");
+        assert_eq!(point, 9);
+
+        // Point 7 is inside "本" (bytes 6..9): only "日" is before the cursor
+        let (line, point) = build_completion_line("ls 日本", 7, "日本語", true, "日").unwrap();
+        assert_eq!(line, "ls 日本語本");
+        assert_eq!(point, 12);
+    }
+
     #[test]
     fn test_insert_completion_ascii() {
         let line = "ls file";
@@ -257,7 +1119,14 @@ mod tests {
         let completion = "file.txt";
         let current_word = "file";
 
-        let result = insert_completion(line, point, completion, false, current_word);
+        let result = insert_completion(
+            &mut Vec::new(),
+            line,
+            point,
+            completion,
+            false,
+            current_word,
+        );
         assert!(result.is_ok());
     }
 
@@ -268,7 +1137,14 @@ mod tests {
         let completion = "test.txt";
         let current_word = "中文";
 
-        let result = insert_completion(line, point, completion, false, current_word);
+        let result = insert_completion(
+            &mut Vec::new(),
+            line,
+            point,
+            completion,
+            false,
+            current_word,
+        );
         assert!(result.is_ok());
     }
 
@@ -279,7 +1155,14 @@ mod tests {
         let completion = "feature-中文";
         let current_word = "feat";
 
-        let result = insert_completion(line, point, completion, false, current_word);
+        let result = insert_completion(
+            &mut Vec::new(),
+            line,
+            point,
+            completion,
+            false,
+            current_word,
+        );
         assert!(result.is_ok());
     }
 
@@ -290,10 +1173,95 @@ mod tests {
         let completion = "/";
         let current_word = "path";
 
-        let result = insert_completion(line, point, completion, true, current_word);
+        let result =
+            insert_completion(&mut Vec::new(), line, point, completion, true, current_word);
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_compspec_prefix_and_suffix_bash_only() {
+        let line = "tool --de";
+        let parsed = parser::parse_shell_line(line, line.len()).unwrap();
+        let ctx = CompletionContext::from_parsed(&parsed, line.to_string(), line.len());
+        let spec = CompletionSpec {
+            prefix: "--".to_string(),
+            suffix: ":".to_string(),
+            ..Default::default()
+        };
+        // BashProvider wraps the compspec's candidates; carapace's are its own
+        let result = CompletionResult {
+            candidates: vec![
+                CompletionEntry::new("--define:".to_string(), ProviderKind::Bash),
+                CompletionEntry::new("--debug".to_string(), ProviderKind::Carapace),
+            ],
+            used_provider: ProviderKind::Pipeline,
+            spec,
+        };
+
+        let candidates = apply_post_processing(&result, &ctx, &Config::default()).unwrap();
+        let values: Vec<&str> = candidates.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, vec!["--define:", "--debug"]);
+        assert!(space_suppressed(false, &result.spec, &candidates[0]));
+        assert!(!space_suppressed(false, &result.spec, &candidates[1]));
+
+        let (new_line, _) = build_completion_line(
+            line,
+            line.len(),
+            &candidates[0].value,
+            space_suppressed(false, &result.spec, &candidates[0]),
+            &ctx.current_word_prefix,
+        )
+        .unwrap();
+        assert_eq!(new_line, "tool --define:");
+        let (new_line, _) = build_completion_line(
+            line,
+            line.len(),
+            &candidates[1].value,
+            space_suppressed(false, &result.spec, &candidates[1]),
+            &ctx.current_word_prefix,
+        )
+        .unwrap();
+        assert_eq!(new_line, "tool --debug ");
+    }
+
+    #[test]
+    fn test_compspec_nospace() {
+        let mut spec = CompletionSpec::default();
+        spec.options.nospace = true;
+        let line = "git push orig";
+        let (new_line, new_point) = build_completion_line(
+            line,
+            line.len(),
+            "origin",
+            space_suppressed(
+                false,
+                &spec,
+                &CompletionEntry::new("origin".to_string(), ProviderKind::Bash),
+            ),
+            "orig",
+        )
+        .unwrap();
+        assert_eq!(new_line, "git push origin");
+        assert_eq!(new_point, new_line.len());
+
+        // Other providers' candidates are not governed by the compspec
+        let carapace = CompletionEntry::new("origin".to_string(), ProviderKind::Carapace);
+        assert!(!space_suppressed(false, &spec, &carapace));
+    }
+
+    #[test]
+    fn test_insert_completion_trailing_equals() {
+        let entry = CompletionEntry::new("--color=".to_string(), ProviderKind::Carapace);
+        let (new_line, new_point) =
+            build_completion_line("ls --col", 8, &entry.value, false, "--col").unwrap();
+        assert_eq!(new_line, "ls --color=");
+        assert_eq!(new_point, 11);
+
+        let (new_line, _) =
+            build_completion_line("ls --col", 8, "--color", false, "--col").unwrap();
+        assert_eq!(new_line, "ls --color ");
+    }
+
     #[test]
     fn test_insert_completion_empty_word() {
         let line = "ls ";
@@ -301,10 +1269,28 @@ mod tests {
         let completion = "file.txt";
         let current_word = "";
 
-        let result = insert_completion(line, point, completion, false, current_word);
+        let result = insert_completion(
+            &mut Vec::new(),
+            line,
+            point,
+            completion,
+            false,
+            current_word,
+        );
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_insert_completion_mid_word_keeps_suffix() {
+        // Cursor after "file" in "fileX.txt"; only the part before the cursor is replaced
+        let line = "ls fileX.txt";
+        let point = 7;
+        let (new_line, new_point) =
+            build_completion_line(line, point, "file.log", true, "file").unwrap();
+        assert_eq!(new_line, "ls file.logX.txt");
+        assert_eq!(new_point, 11);
+    }
+
     #[test]
     fn test_insert_completion_full_line() {
         let line = "git sta";
@@ -312,7 +1298,14 @@ mod tests {
         let completion = "git status"; // Full line completion
         let current_word = "sta";
 
-        let result = insert_completion(line, point, completion, false, current_word);
+        let result = insert_completion(
+            &mut Vec::new(),
+            line,
+            point,
+            completion,
+            false,
+            current_word,
+        );
         assert!(result.is_ok());
     }
 }
@@ -21,6 +21,8 @@ pub struct ParsedLine {
     pub raw_words: Vec<String>,
     pub cursor_position: usize,
     pub current_word_index: usize,
+    /// Part of the current word before the cursor (the whole word when unknown)
+    pub current_word_prefix: String,
 }
 
 impl ParsedLine {
@@ -30,15 +32,67 @@ impl ParsedLine {
         cursor_position: usize,
         current_word_index: usize,
     ) -> Self {
+        let current_word_prefix = words.get(current_word_index).cloned().unwrap_or_default();
         Self {
             words,
             raw_words,
             cursor_position,
             current_word_index,
+            current_word_prefix,
         }
     }
 }
 
+/// Build a `ParsedLine` from words that were already split by the caller (as
+/// in `COMP_WORDS`/`COMP_CWORD`), skipping tokenization.
+///
+/// Returns the reconstructed line (words shell-quoted and joined by spaces),
+/// the cursor as a byte index at the end of the current word, and the parse.
+/// An index one past the last word completes a new, empty word.
+pub fn parse_words(
+    mut words: Vec<String>,
+    current_word_index: usize,
+) -> (String, usize, ParsedLine) {
+    let current_word_index = current_word_index.min(words.len());
+    if current_word_index == words.len() {
+        words.push(String::new());
+    }
+
+    let raw_words: Vec<String> = words
+        .iter()
+        .map(|w| {
+            if w.is_empty() {
+                String::new()
+            } else {
+                shlex::try_quote(w).map_or_else(|_| w.clone(), |q| q.into_owned())
+            }
+        })
+        .collect();
+
+    let line = raw_words.join(" ");
+    let point = raw_words[..=current_word_index]
+        .iter()
+        .map(|w| w.len())
+        .sum::<usize>()
+        + current_word_index;
+
+    let parsed = ParsedLine::new(words, raw_words, point, current_word_index);
+    (line, point, parsed)
+}
+
+/// Clamp a cursor position to a char boundary of `line`.
+///
+/// Cursor positions are always byte indices into the line (as readline reports
+/// `READLINE_POINT`); a point past the end or inside a multibyte character is
+/// moved back to the nearest boundary.
+pub fn clamp_to_char_boundary(line: &str, point: usize) -> usize {
+    let mut point = point.min(line.len());
+    while !line.is_char_boundary(point) {
+        point -= 1;
+    }
+    point
+}
+
 fn byte_to_char_index(s: &str, byte_idx: usize) -> usize {
     s.char_indices()
         .take_while(|(idx, _)| *idx < byte_idx)
@@ -46,6 +100,11 @@ fn byte_to_char_index(s: &str, byte_idx: usize) -> usize {
 }
 
 pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, ParseError> {
+    let cursor_pos = clamp_to_char_boundary(input, cursor_pos);
+    // Inside `$(...)` only the inner command line is completed
+    if let Some((start, end)) = command_substitution_at(input, cursor_pos) {
+        return parse_shell_line(&input[start..end], cursor_pos - start);
+    }
     if input.trim().is_empty() {
         return Ok(ParsedLine::new(vec![], vec![], cursor_pos, 0));
     }
@@ -55,6 +114,12 @@ pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, Pa
         Err(_) => return Ok(fallback_parse(input, cursor_pos)),
     };
 
+    // Non-empty input that tokenizes to nothing (e.g. only a comment) would
+    // leave `words` empty while callers index into it.
+    if tokens.is_empty() {
+        return Ok(fallback_parse(input, cursor_pos));
+    }
+
     let mut words = Vec::new();
     let mut raw_words = Vec::new();
     let mut current_word_index = 0;
@@ -62,6 +127,7 @@ pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, Pa
     let cursor_char_pos = byte_to_char_index(input, cursor_pos);
     let mut found_cursor = false;
     let mut last_end_char = 0;
+    let mut current_word_prefix = None;
 
     for token in tokens.iter() {
         let (raw, loc) = match token {
@@ -69,8 +135,9 @@ pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, Pa
             Token::Word(s, l) => (s, l),
         };
 
-        let start_char = byte_to_char_index(input, loc.start.index);
-        let end_char = byte_to_char_index(input, loc.end.index);
+        // Token locations are character indices already; only the cursor is a byte index
+        let start_char = loc.start.index;
+        let end_char = loc.end.index;
 
         if start_char > last_end_char
             && !found_cursor
@@ -89,6 +156,14 @@ pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, Pa
         if !found_cursor && cursor_char_pos >= start_char && cursor_char_pos <= end_char {
             current_word_index = words.len() - 1;
             found_cursor = true;
+            if cursor_char_pos < end_char {
+                let raw_prefix: String = input
+                    .chars()
+                    .skip(start_char)
+                    .take(cursor_char_pos - start_char)
+                    .collect();
+                current_word_prefix = Some(unquote_string(&raw_prefix));
+            }
         }
 
         last_end_char = end_char;
@@ -118,12 +193,11 @@ pub fn parse_shell_line(input: &str, cursor_pos: usize) -> Result<ParsedLine, Pa
         }
     }
 
-    Ok(ParsedLine::new(
-        words,
-        raw_words,
-        cursor_pos,
-        current_word_index,
-    ))
+    let mut parsed = ParsedLine::new(words, raw_words, cursor_pos, current_word_index);
+    if let Some(prefix) = current_word_prefix {
+        parsed.current_word_prefix = prefix;
+    }
+    Ok(parsed)
 }
 
 fn fallback_parse(input: &str, cursor_pos: usize) -> ParsedLine {
@@ -239,6 +313,30 @@ pub fn find_last_pipe_index(words: &[String]) -> Option<usize> {
     words.iter().rposition(|w| w == "|")
 }
 
+/// Operators after which a new command starts
+pub const COMMAND_SEPARATORS: &[&str] = &["|", "&&", "||", ";"];
+
+/// Find the index of the last command separator (`|`, `&&`, `||`, `;`)
+/// Returns None if there is none
+pub fn find_last_separator_index(words: &[String]) -> Option<usize> {
+    words
+        .iter()
+        .rposition(|w| COMMAND_SEPARATORS.contains(&w.as_str()))
+}
+
+/// Word range `start..end` of the statement containing word `index`: from
+/// after the separator before it up to the separator after it
+pub fn statement_bounds(words: &[String], index: usize) -> (usize, usize) {
+    let index = index.min(words.len());
+    let start = find_last_separator_index(&words[..index]).map_or(0, |i| i + 1);
+    let end = words
+        .iter()
+        .skip(index + 1)
+        .position(|w| COMMAND_SEPARATORS.contains(&w.as_str()))
+        .map_or(words.len(), |p| index + 1 + p);
+    (start, end)
+}
+
 /// Get the command after the last pipe operator
 /// Returns (command_name, args_after_pipe) if found
 pub fn get_command_after_pipe(words: &[String]) -> Option<(String, Vec<String>)> {
@@ -254,6 +352,216 @@ pub fn get_command_after_pipe(words: &[String]) -> Option<(String, Vec<String>)>
     Some((command, args))
 }
 
+/// Return the variable name if `word` is a `NAME=value` assignment
+pub fn assignment_name(word: &str) -> Option<&str> {
+    let (name, _) = word.split_once('=')?;
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if (first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Detect a cursor inside an unclosed array literal (`NAME=(a b ` or `NAME+=(`).
+///
+/// `before_cursor` is the line up to the cursor. Returns the array name and
+/// the (raw) element being typed.
+pub fn open_array_assignment(before_cursor: &str) -> Option<(&str, &str)> {
+    let mut open: Option<(&str, usize)> = None;
+    let mut token_start = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for (i, c) in before_cursor.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => escaped = true,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => {
+                let token = &before_cursor[token_start..i];
+                open = token
+                    .strip_suffix('=')
+                    .map(|t| t.strip_suffix('+').unwrap_or(t))
+                    .filter(|name| assignment_name(&format!("{}=", name)).is_some())
+                    .map(|name| (name, i + 1));
+            }
+            (None, ')') => open = None,
+            (None, c) if c.is_whitespace() => {
+                token_start = i + 1;
+                if let Some((_, elem_start)) = open.as_mut() {
+                    *elem_start = i + 1;
+                }
+            }
+            (None, ';' | '&' | '|') => {
+                token_start = i + 1;
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    open.map(|(name, elem_start)| (name, &before_cursor[elem_start..]))
+}
+
+/// Detect a cursor inside an unclosed arithmetic expansion (`$(( a + b`).
+///
+/// `before_cursor` is the line up to the cursor. Returns the variable name
+/// being typed, with its `$` if one was written (it is optional there).
+pub fn open_arithmetic(before_cursor: &str) -> Option<&str> {
+    let start = before_cursor.rfind("$((")? + 3;
+    let mut depth = 2;
+    for c in before_cursor[start..].chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return None;
+        }
+    }
+
+    let name_start = before_cursor
+        .trim_end_matches(|c: char| c.is_ascii_alphanumeric() || c == '_')
+        .len()
+        .max(start);
+    let name_start = if before_cursor[..name_start].ends_with('$') && name_start > start {
+        name_start - 1
+    } else {
+        name_start
+    };
+    Some(&before_cursor[name_start..])
+}
+
+/// Byte range of the innermost `$(...)` command substitution around `cursor`
+/// (up to the end of the input when it is not closed yet), excluding the
+/// `$(` and `)` themselves. `$((` arithmetic does not count.
+pub fn command_substitution_at(input: &str, cursor: usize) -> Option<(usize, usize)> {
+    // Open parentheses before the cursor: `Some(inner start)` for `$(`
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut in_single_quote = false;
+    let mut in_double_quote = false;
+    let mut escaped = false;
+    let bytes = input.as_bytes();
+
+    for (i, c) in input[..cursor].char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+        match c {
+            '\\' if !in_single_quote => escaped = true,
+            '\'' if !in_double_quote => in_single_quote = !in_single_quote,
+            _ if in_single_quote => {}
+            '"' => in_double_quote = !in_double_quote,
+            '(' if i > 0 && bytes[i - 1] == b'$' && bytes.get(i + 1) != Some(&b'(') => {
+                open.push(Some(i + 1))
+            }
+            '(' => open.push(None),
+            ')' => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    let innermost = open.iter().rposition(Option::is_some)?;
+    let start = open[innermost]?;
+    let mut depth = open.len() - innermost;
+    let mut end = input.len();
+    for (i, c) in input[cursor..].char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            end = cursor + i;
+            break;
+        }
+    }
+    Some((start, end))
+}
+
+/// Index of the first word after a leading `env` and its assignments/flags
+pub fn skip_env_prefix(words: &[String]) -> usize {
+    let mut idx = 1;
+    while let Some(word) = words.get(idx) {
+        if matches!(word.as_str(), "-u" | "--unset" | "-C" | "--chdir") {
+            idx += 2;
+        } else if word.starts_with('-') || assignment_name(word).is_some() {
+            idx += 1;
+        } else {
+            break;
+        }
+    }
+    idx.min(words.len())
+}
+
+/// Options of wrapper commands that consume the following word
+const WRAPPER_VALUE_FLAGS: &[(&str, &[&str])] = &[
+    (
+        "sudo",
+        &[
+            "-u", "--user", "-g", "--group", "-C", "-D", "-h", "-p", "-R", "-r", "-t", "-U", "-T",
+        ],
+    ),
+    ("doas", &["-u", "-C"]),
+    ("time", &["-f", "--format", "-o", "--output"]),
+    (
+        "xargs",
+        &[
+            "-a",
+            "-d",
+            "-E",
+            "-I",
+            "-L",
+            "-n",
+            "-P",
+            "-s",
+            "--arg-file",
+            "--delimiter",
+        ],
+    ),
+];
+
+/// Index of the first word after a leading wrapper command (`sudo`, `nohup`,
+/// ...) and its options; `env` also skips its assignments
+pub fn skip_wrapper_prefix(words: &[String]) -> usize {
+    let Some(wrapper) = words.first() else {
+        return 0;
+    };
+    if wrapper == "env" {
+        return skip_env_prefix(words);
+    }
+    let value_flags = WRAPPER_VALUE_FLAGS
+        .iter()
+        .find(|(name, _)| name == wrapper)
+        .map_or(&[][..], |(_, flags)| *flags);
+
+    let mut idx = 1;
+    while let Some(word) = words.get(idx) {
+        if value_flags.contains(&word.as_str()) {
+            idx += 2;
+        } else if word.starts_with('-') {
+            idx += 1;
+        } else {
+            break;
+        }
+    }
+    idx.min(words.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -288,6 +596,17 @@ mod tests {
         assert_eq!(parsed.current_word_index, 1);
     }
 
+    #[test]
+    fn test_parse_cursor_mid_word_prefix() {
+        let input = "ls fileX.txt";
+        let parsed = parse_shell_line(input, 7).unwrap();
+        assert_eq!(parsed.words[1], "fileX.txt");
+        assert_eq!(parsed.current_word_prefix, "file");
+
+        let parsed = parse_shell_line(input, input.len()).unwrap();
+        assert_eq!(parsed.current_word_prefix, "fileX.txt");
+    }
+
     #[test]
     fn test_parse_trailing_space() {
         let input = "ls ";
@@ -333,6 +652,30 @@ mod tests {
         assert_eq!(find_last_pipe_index(&words_no_pipe), None);
     }
 
+    #[test]
+    fn test_find_last_separator_index() {
+        let words = |ws: &[&str]| -> Vec<String> { ws.iter().map(|w| w.to_string()).collect() };
+        assert_eq!(
+            find_last_separator_index(&words(&["make", "build", "&&", "gi"])),
+            Some(2)
+        );
+        assert_eq!(
+            find_last_separator_index(&words(&["a", "||", "b", "|", "c"])),
+            Some(3)
+        );
+        assert_eq!(find_last_separator_index(&words(&["ls", "-la"])), None);
+    }
+
+    #[test]
+    fn test_statement_bounds() {
+        let words = |ws: &[&str]| -> Vec<String> { ws.iter().map(|w| w.to_string()).collect() };
+        let line = words(&["cd", "/tmp", ";", "ls", "-l", "&&", "pwd"]);
+        assert_eq!(statement_bounds(&line, 1), (0, 2));
+        assert_eq!(statement_bounds(&line, 4), (3, 5));
+        assert_eq!(statement_bounds(&line, 6), (6, 7));
+        assert_eq!(statement_bounds(&words(&["ls", "-l"]), 2), (0, 2));
+    }
+
     #[test]
     fn test_get_command_after_pipe() {
         let words = vec![
@@ -353,6 +696,36 @@ mod tests {
         assert_eq!(get_command_after_pipe(&words_empty_after_pipe), None);
     }
 
+    #[test]
+    fn test_assignment_name() {
+        assert_eq!(assignment_name("FOO=bar"), Some("FOO"));
+        assert_eq!(assignment_name("_x1="), Some("_x1"));
+        assert_eq!(assignment_name("--color=auto"), None);
+        assert_eq!(assignment_name("1A=b"), None);
+        assert_eq!(assignment_name("git"), None);
+    }
+
+    #[test]
+    fn test_skip_env_prefix() {
+        let words: Vec<String> = ["env", "-u", "HOME", "A=1", "git", "status"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        assert_eq!(skip_env_prefix(&words), 4);
+    }
+
+    #[test]
+    fn test_skip_wrapper_prefix() {
+        let words = |ws: &[&str]| -> Vec<String> { ws.iter().map(|w| w.to_string()).collect() };
+        assert_eq!(
+            skip_wrapper_prefix(&words(&["sudo", "-u", "www", "-E", "systemctl", "st"])),
+            4
+        );
+        assert_eq!(skip_wrapper_prefix(&words(&["nohup", "./run"])), 1);
+        assert_eq!(skip_wrapper_prefix(&words(&["env", "A=1", "git"])), 2);
+        assert_eq!(skip_wrapper_prefix(&words(&["xargs", "-n", "1"])), 3);
+    }
+
     #[test]
     fn test_fallback_unclosed_quote() {
         let input = "ls 'file na";
@@ -365,11 +738,98 @@ mod tests {
         assert_eq!(parsed.current_word_index, 1);
     }
 
+    #[test]
+    fn test_parse_no_tokens_uses_fallback() {
+        let input = "# comment";
+        let parsed = parse_shell_line(input, input.len()).unwrap();
+        assert!(!parsed.words.is_empty());
+        assert!(parsed.current_word_index < parsed.words.len());
+        assert_eq!(parsed.words, vec!["#", "comment"]);
+    }
+
+    #[test]
+    fn test_parse_words() {
+        let words = vec!["git".to_string(), "commit message".to_string()];
+        let (line, point, parsed) = parse_words(words.clone(), 1);
+        assert_eq!(line, "git 'commit message'");
+        assert_eq!(point, line.len());
+        assert_eq!(parsed.words, words);
+        assert_eq!(parsed.current_word_prefix, "commit message");
+
+        let (line, point, parsed) = parse_words(vec!["ls".to_string()], 1);
+        assert_eq!(line, "ls ");
+        assert_eq!(point, 3);
+        assert_eq!(parsed.current_word_index, 1);
+        assert_eq!(parsed.words, vec!["ls", ""]);
+    }
+
+    #[test]
+    fn test_open_array_assignment() {
+        assert_eq!(open_array_assignment("arr=(a.t"), Some(("arr", "a.t")));
+        assert_eq!(
+            open_array_assignment("files=(a.txt b.txt "),
+            Some(("files", ""))
+        );
+        assert_eq!(
+            open_array_assignment("x=1 arr+=(\"a b\" c"),
+            Some(("arr", "c"))
+        );
+        assert_eq!(open_array_assignment("arr=(a b) ls "), None);
+        assert_eq!(open_array_assignment("echo (a"), None);
+        assert_eq!(open_array_assignment("echo 'arr=(a"), None);
+    }
+
+    #[test]
+    fn test_open_arithmetic() {
+        assert_eq!(open_arithmetic("echo $(( VA"), Some("VA"));
+        assert_eq!(open_arithmetic("echo $(( $VAR + "), Some(""));
+        assert_eq!(open_arithmetic("echo $((count*$st"), Some("$st"));
+        assert_eq!(open_arithmetic("x=$(( (a + b) * c"), Some("c"));
+        assert_eq!(open_arithmetic("echo $(( 1 + 2 )) VA"), None);
+        assert_eq!(open_arithmetic("echo $( VA"), None);
+    }
+
+    #[test]
+    fn test_command_substitution() {
+        let parsed = parse_shell_line("echo $(gi", 9).unwrap();
+        assert_eq!(parsed.words, vec!["gi"]);
+        assert_eq!(parsed.current_word_index, 0);
+
+        let line = "echo $(git che) done";
+        let parsed = parse_shell_line(line, 14).unwrap();
+        assert_eq!(parsed.words, vec!["git", "che"]);
+        assert_eq!(parsed.current_word_index, 1);
+
+        let line = "ls $(dirname $(which py";
+        let parsed = parse_shell_line(line, line.len()).unwrap();
+        assert_eq!(parsed.words, vec!["which", "py"]);
+
+        assert_eq!(command_substitution_at("echo $(ls) x", 12), None);
+        assert_eq!(command_substitution_at("echo $(( 1 + x", 14), None);
+        assert_eq!(command_substitution_at("echo '$(gi", 10), None);
+    }
+
+    #[test]
+    fn test_clamp_to_char_boundary() {
+        assert_eq!(clamp_to_char_boundary("ls é", 4), 3);
+        assert_eq!(clamp_to_char_boundary("ls é", 5), 5);
+        assert_eq!(clamp_to_char_boundary("ls é", 99), 5);
+
+        let parsed = parse_shell_line("cat 日本 x", 7).unwrap();
+        assert_eq!(parsed.current_word_index, 1);
+        assert_eq!(parsed.current_word_prefix, "日");
+    }
+
     #[test]
     fn test_fallback_parse() {
         let input = "ls $(cat ";
-        let parsed = parse_shell_line(input, 9).unwrap();
+        let parsed = fallback_parse(input, 9);
         assert_eq!(parsed.words, vec!["ls", "$(cat", ""]);
         assert_eq!(parsed.current_word_index, 2);
+
+        // The line itself completes inside the substitution
+        let parsed = parse_shell_line(input, 9).unwrap();
+        assert_eq!(parsed.words, vec!["cat", ""]);
+        assert_eq!(parsed.current_word_index, 1);
     }
 }
@@ -1,8 +1,9 @@
-use crate::completion::CompletionEntry;
+use crate::completion::{CandidateType, CompletionEntry, files};
+use crate::config::SortOrder;
 use glob::Pattern;
 use shellexpand;
 use shlex;
-use std::path::Path;
+use std::collections::HashSet;
 
 pub fn quote_filename(path: &str, is_filename: bool) -> String {
     if !is_filename {
@@ -34,8 +35,141 @@ pub fn mark_directories(candidates: Vec<CompletionEntry>) -> Vec<CompletionEntry
             let expanded = shellexpand::tilde(&entry.value);
             let unescaped = unescape_filename(&expanded);
 
-            if Path::new(&unescaped).is_dir() && !entry.value.ends_with('/') {
-                entry.value = format!("{}/", entry.value);
+            let path = files::resolve_path(&unescaped);
+            if path.is_dir() {
+                entry.candidate_type = CandidateType::Dir;
+                if !entry.value.ends_with('/') {
+                    entry.value = format!("{}/", entry.value);
+                }
+            } else if path.exists() {
+                entry.candidate_type = CandidateType::File;
+            }
+            entry
+        })
+        .collect()
+}
+
+/// Drop the leading `./` from candidates, unless `current_word` starts with `./` itself.
+/// Candidates that collapse onto an existing value are removed.
+pub fn strip_leading_dot_slash(
+    candidates: Vec<CompletionEntry>,
+    current_word: &str,
+) -> Vec<CompletionEntry> {
+    if current_word.starts_with("./") {
+        return candidates;
+    }
+
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter_map(|mut entry| {
+            if let Some(stripped) = entry.value.strip_prefix("./")
+                && !stripped.is_empty()
+            {
+                entry.value = stripped.to_string();
+            }
+            seen.insert(entry.value.clone()).then_some(entry)
+        })
+        .collect()
+}
+
+/// Stable-sort candidates so files ending in one of `extensions` come first
+pub fn sort_by_extensions(
+    mut candidates: Vec<CompletionEntry>,
+    extensions: &[String],
+) -> Vec<CompletionEntry> {
+    if extensions.is_empty() {
+        return candidates;
+    }
+    candidates.sort_by_key(|entry| {
+        !extensions
+            .iter()
+            .any(|ext| entry.value.ends_with(&format!(".{}", ext)))
+    });
+    candidates
+}
+
+/// Path components kept on each side of the `…` in a shortened path
+const PRETTY_PATH_KEEP_HEAD: usize = 1;
+const PRETTY_PATH_KEEP_TAIL: usize = 2;
+
+/// Shorten a path for display: `home` becomes `~` and the middle directories
+/// of a deep path collapse into `…` (`~/src/…/components/button.tsx`)
+pub fn pretty_path(path: &str, home: Option<&str>) -> String {
+    let (root, rest) = match home {
+        Some(home) if !home.is_empty() && path.starts_with(&format!("{}/", home)) => {
+            ("~/", &path[home.len() + 1..])
+        }
+        _ => match path.strip_prefix('/') {
+            Some(rest) => ("/", rest),
+            None => ("", path),
+        },
+    };
+    let (rest, trailing) = match rest.strip_suffix('/') {
+        Some(rest) => (rest, "/"),
+        None => (rest, ""),
+    };
+
+    let components: Vec<&str> = rest.split('/').collect();
+    let body = if components.len() > PRETTY_PATH_KEEP_HEAD + PRETTY_PATH_KEEP_TAIL + 1 {
+        let head = &components[..PRETTY_PATH_KEEP_HEAD];
+        let tail = &components[components.len() - PRETTY_PATH_KEEP_TAIL..];
+        format!("{}/…/{}", head.join("/"), tail.join("/"))
+    } else {
+        rest.to_string()
+    };
+    format!("{}{}{}", root, body, trailing)
+}
+
+/// Give path candidates a shortened display (see `pretty_path`); values are untouched
+pub fn apply_pretty_paths(
+    candidates: Vec<CompletionEntry>,
+    home: Option<&str>,
+) -> Vec<CompletionEntry> {
+    candidates
+        .into_iter()
+        .map(|entry| {
+            if !entry.value.contains('/') || entry.display.is_some() {
+                return entry;
+            }
+            let display = pretty_path(&entry.value, home);
+            if display == entry.value {
+                entry
+            } else {
+                entry.with_display(display)
+            }
+        })
+        .collect()
+}
+
+/// Stable-sort candidates into `order`
+pub fn sort_candidates(
+    mut candidates: Vec<CompletionEntry>,
+    order: SortOrder,
+) -> Vec<CompletionEntry> {
+    match order {
+        SortOrder::ProviderOrder => {}
+        SortOrder::Alphabetical => candidates.sort_by(|a, b| a.value.cmp(&b.value)),
+        SortOrder::Length => candidates.sort_by_key(|entry| entry.value.chars().count()),
+    }
+    candidates
+}
+
+/// Wrap each candidate in the compspec's `-P` prefix and `-S` suffix
+pub fn apply_affixes(
+    candidates: Vec<CompletionEntry>,
+    prefix: &str,
+    suffix: &str,
+) -> Vec<CompletionEntry> {
+    if prefix.is_empty() && suffix.is_empty() {
+        return candidates;
+    }
+    candidates
+        .into_iter()
+        .map(|mut entry| {
+            entry.value = format!("{}{}{}", prefix, entry.value, suffix);
+            if let Some(display) = entry.display.take() {
+                entry.display = Some(format!("{}{}{}", prefix, display, suffix));
             }
             entry
         })
@@ -46,9 +180,11 @@ fn unescape_filename(s: &str) -> String {
     brush_parser::unquote_str(s).to_string()
 }
 
+/// Collapse `candidates` into their longest common prefix when it extends the
+/// input; `input_chars` is the length of the typed word in `char`s
 pub fn find_common_prefix(
     candidates: &[CompletionEntry],
-    input_len: usize,
+    input_chars: usize,
     auto_common_prefix_part: bool,
 ) -> (Vec<CompletionEntry>, bool, String) {
     if candidates.is_empty() {
@@ -57,10 +193,12 @@ pub fn find_common_prefix(
 
     let values: Vec<String> = candidates.iter().map(|c| c.value.clone()).collect();
     let prefix = find_longest_common_prefix(&values);
-    let prefix_len = prefix.len();
+    let prefix_chars = prefix.chars().count();
 
-    if prefix_len > input_len {
-        let all_match = candidates.iter().all(|c| c.value.len() == prefix_len);
+    if prefix_chars > input_chars {
+        let all_match = candidates
+            .iter()
+            .all(|c| c.value.chars().count() == prefix_chars);
 
         if all_match || auto_common_prefix_part {
             let nospace = candidates.len() > 1;
@@ -70,7 +208,10 @@ pub fn find_common_prefix(
             // but for a common prefix it often doesn't matter as much.
             let kind = candidates[0].kind;
             return (
-                vec![CompletionEntry::new(prefix.clone(), kind)],
+                vec![
+                    CompletionEntry::new(prefix.clone(), kind)
+                        .with_type(candidates[0].candidate_type),
+                ],
                 nospace,
                 prefix,
             );
@@ -80,23 +221,23 @@ pub fn find_common_prefix(
     (candidates.to_vec(), false, String::new())
 }
 
+/// Longest common prefix, compared `char` by `char` so it never ends inside
+/// a multi-byte code point
 fn find_longest_common_prefix(strings: &[String]) -> String {
-    if strings.is_empty() {
+    let Some((first, rest)) = strings.split_first() else {
         return String::new();
-    }
-
-    let mut prefix = strings[0].clone();
+    };
 
-    for s in &strings[1..] {
-        while !s.starts_with(&prefix) {
-            prefix.pop();
-            if prefix.is_empty() {
-                return String::new();
-            }
-        }
+    let mut prefix_chars = first.chars().count();
+    for s in rest {
+        prefix_chars = first
+            .chars()
+            .zip(s.chars())
+            .take(prefix_chars)
+            .take_while(|(a, b)| a == b)
+            .count();
     }
-
-    prefix
+    first.chars().take(prefix_chars).collect()
 }
 
 pub fn apply_filter(
@@ -137,6 +278,78 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_sort_by_extensions() {
+        let candidates = ["notes.txt", "a.gz", "dir/", "b.tar.gz", "c.gzip"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+        let config = crate::config::Config::default();
+        let sorted = sort_by_extensions(candidates, &config.preferred_extensions["gunzip"]);
+        let values: Vec<&str> = sorted.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(
+            values,
+            vec!["a.gz", "b.tar.gz", "notes.txt", "dir/", "c.gzip"]
+        );
+    }
+
+    #[test]
+    fn test_sort_candidates() {
+        let candidates: Vec<CompletionEntry> = ["status", "add", "cherry-pick", "log", "am"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+        let values = |order| -> Vec<String> {
+            sort_candidates(candidates.clone(), order)
+                .into_iter()
+                .map(|e| e.value)
+                .collect()
+        };
+        assert_eq!(
+            values(SortOrder::ProviderOrder),
+            vec!["status", "add", "cherry-pick", "log", "am"]
+        );
+        assert_eq!(
+            values(SortOrder::Alphabetical),
+            vec!["add", "am", "cherry-pick", "log", "status"]
+        );
+        // Ties keep the provider order
+        assert_eq!(
+            values(SortOrder::Length),
+            vec!["am", "add", "log", "status", "cherry-pick"]
+        );
+    }
+
+    #[test]
+    fn test_apply_affixes() {
+        let candidates = vec![
+            CompletionEntry::new("main".to_string(), ProviderKind::Bash),
+            CompletionEntry::new("/home/ann/a/b/c/d".to_string(), ProviderKind::Bash)
+                .with_display("~/a/…/c/d".to_string()),
+        ];
+        let wrapped = apply_affixes(candidates.clone(), "ref=", ",");
+        assert_eq!(wrapped[0].value, "ref=main,");
+        assert_eq!(wrapped[1].display.as_deref(), Some("ref=~/a/…/c/d,"));
+        assert_eq!(apply_affixes(candidates.clone(), "", ""), candidates);
+    }
+
+    #[test]
+    fn test_pretty_path() {
+        let home = Some("/home/ann");
+        assert_eq!(
+            pretty_path("/home/ann/src/app/web/components/button.tsx", home),
+            "~/src/…/components/button.tsx"
+        );
+        assert_eq!(pretty_path("/home/ann/notes.md", home), "~/notes.md");
+        assert_eq!(
+            pretty_path("/usr/share/doc/bash/examples/", home),
+            "/usr/…/bash/examples/"
+        );
+        assert_eq!(pretty_path("src/lib.rs", home), "src/lib.rs");
+        // Only a whole home directory is abbreviated
+        assert_eq!(pretty_path("/home/anna/x", home), "/home/anna/x");
+    }
+
     #[test]
     fn test_quote_filename() {
         assert_eq!(quote_filename("foo bar", true), "'foo bar'");
@@ -164,6 +377,54 @@ mod tests {
         assert!(nospace);
     }
 
+    #[test]
+    fn test_common_prefix_multibyte() {
+        let candidates = [
+            CompletionEntry::new("中文件1".to_string(), ProviderKind::Bash),
+            CompletionEntry::new("中文件2".to_string(), ProviderKind::Bash),
+        ];
+        // "中" typed: one char, though three bytes
+        let (res, nospace, prefix) = find_common_prefix(&candidates, 1, true);
+        assert_eq!(prefix, "中文件");
+        assert_eq!(res[0].value, "中文件");
+        assert!(nospace);
+
+        // Candidates differing inside the bytes of their second char
+        let candidates = [
+            CompletionEntry::new("中文".to_string(), ProviderKind::Bash),
+            CompletionEntry::new("中斤".to_string(), ProviderKind::Bash),
+        ];
+        let (res, _nospace, prefix) = find_common_prefix(&candidates, 0, true);
+        assert_eq!(prefix, "中");
+        assert_eq!(res[0].value, "中");
+
+        // The typed word already covers the prefix
+        let (res, _nospace, prefix) = find_common_prefix(&candidates, 1, true);
+        assert_eq!(prefix, "");
+        assert_eq!(res.len(), 2);
+    }
+
+    #[test]
+    fn test_mark_directories_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("notes.txt");
+        std::fs::write(&file_path, "").unwrap();
+        let sub_path = dir.path().join("sub");
+        std::fs::create_dir(&sub_path).unwrap();
+
+        let candidates = vec![
+            CompletionEntry::new(file_path.display().to_string(), ProviderKind::Bash),
+            CompletionEntry::new(sub_path.display().to_string(), ProviderKind::Bash),
+            CompletionEntry::new("--missing".to_string(), ProviderKind::Bash),
+        ];
+        let marked = mark_directories(candidates);
+
+        assert_eq!(marked[0].candidate_type, CandidateType::File);
+        assert_eq!(marked[1].candidate_type, CandidateType::Dir);
+        assert!(marked[1].value.ends_with('/'));
+        assert_eq!(marked[2].candidate_type, CandidateType::Other);
+    }
+
     #[test]
     fn test_filter() {
         let candidates = [
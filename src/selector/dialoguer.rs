@@ -1,5 +1,6 @@
 use crate::completion::CompletionEntry;
-use crate::selector::{Selector, SelectorConfig, SelectorError, theme};
+use crate::config::MatcherType;
+use crate::selector::{Selector, SelectorConfig, SelectorError, lone_candidate, matcher, theme};
 use dialoguer::console::Term;
 use log::{debug, warn};
 
@@ -25,14 +26,23 @@ impl Selector for DialoguerSelector {
             config.fuzzy
         );
 
+        // FuzzySelect scores every keystroke with skim, so a stricter matcher
+        // would only apply to the initial word
+        if config.matcher != MatcherType::Skim {
+            return Err(SelectorError::UnsupportedMatcher(config.matcher));
+        }
+
+        let filtered = prefilter(candidates, current_word, config);
+        let candidates = filtered.as_deref().unwrap_or(candidates);
+
         if candidates.is_empty() {
             debug!("No candidates, returning None");
             return Ok(None);
         }
 
-        if candidates.len() == 1 {
-            debug!("Single candidate, returning: {}", candidates[0].value);
-            return Ok(Some(candidates[0].clone()));
+        if let Some(only) = lone_candidate(candidates, config) {
+            debug!("Single candidate, returning: {}", only.value);
+            return Ok(Some(only.clone()));
         }
 
         let prompt = config
@@ -43,15 +53,52 @@ impl Selector for DialoguerSelector {
 
         ctrlc::set_handler(|| {})?;
 
-        let theme = &theme::CustomColorfulTheme::new();
+        let theme = theme::CustomColorfulTheme::new()
+            .with_descriptions(candidates)
+            .with_max_description_len(config.max_description_len);
+        let theme = if config.show_indices {
+            theme.with_indices(candidates)
+        } else {
+            theme
+        };
+        let theme = if config.show_types {
+            theme.with_types(candidates)
+        } else {
+            theme
+        };
+        let theme = if config.wrap_candidates {
+            theme.with_wrap_width(Term::stderr().size().1 as usize)
+        } else {
+            theme
+        };
+        let theme = &theme;
+
+        // Shortened displays (`~/…/file`) need not contain the typed word, so
+        // the search starts empty rather than filtering them all out. So it does
+        // for `nosort`: the matches are already narrowed to the word, and an
+        // empty search lists them in provider order until the user types
+        let initial_text = if config.nosort || candidates.iter().any(|c| c.display.is_some()) {
+            ""
+        } else {
+            current_word
+        };
 
-        let select_result = dialoguer::FuzzySelect::with_theme(theme)
-            .report(false)
-            .with_initial_text(current_word)
-            .with_prompt(prompt)
-            .default(0)
-            .items(candidates)
-            .interact_opt();
+        let select_result = if config.fuzzy {
+            dialoguer::FuzzySelect::with_theme(theme)
+                .report(false)
+                .with_initial_text(initial_text)
+                .with_prompt(prompt)
+                .default(0)
+                .items(candidates)
+                .interact_opt()
+        } else {
+            dialoguer::Select::with_theme(theme)
+                .report(false)
+                .with_prompt(prompt)
+                .default(0)
+                .items(candidates)
+                .interact_opt()
+        };
 
         if select_result.is_err() {
             let _ = Term::stderr().show_cursor();
@@ -83,3 +130,70 @@ impl Selector for DialoguerSelector {
         }
     }
 }
+
+/// Candidates narrowed up front against the current word, or `None` when
+/// FuzzySelect does the matching itself. FuzzySelect lists the best matches of
+/// a search first, so `nosort`, which keeps the matches in provider order,
+/// filters here, and so does the plain Select, which has no search.
+fn prefilter(
+    candidates: &[CompletionEntry],
+    current_word: &str,
+    config: &SelectorConfig,
+) -> Option<Vec<CompletionEntry>> {
+    if config.fuzzy && !config.nosort {
+        return None;
+    }
+    Some(matcher::filter_candidates(
+        MatcherType::Skim,
+        candidates,
+        current_word,
+        true,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ProviderKind;
+
+    #[test]
+    fn test_nosort_keeps_candidate_order() {
+        let candidates: Vec<CompletionEntry> = ["show-branch", "stash", "switch"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+        let values = |entries: Vec<CompletionEntry>| -> Vec<String> {
+            entries.into_iter().map(|e| e.value).collect()
+        };
+
+        // Still a filterable prompt, opened on the matches in provider order
+        let config = SelectorConfig {
+            nosort: true,
+            ..Default::default()
+        };
+        assert!(config.fuzzy);
+        let filtered = prefilter(&candidates, "sw", &config).unwrap();
+        assert_eq!(values(filtered), vec!["show-branch", "switch"]);
+
+        // Without nosort the fuzzy prompt ranks "switch" first on its own
+        let config = SelectorConfig::default();
+        assert!(prefilter(&candidates, "sw", &config).is_none());
+    }
+
+    #[test]
+    fn test_rejects_fzf_only_matchers() {
+        let candidates: Vec<CompletionEntry> = ["stash", "switch"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+        let config = SelectorConfig {
+            matcher: MatcherType::Prefix,
+            ..Default::default()
+        };
+        let result = DialoguerSelector::new().select_one(&candidates, "s", &config);
+        assert!(matches!(
+            result,
+            Err(SelectorError::UnsupportedMatcher(MatcherType::Prefix))
+        ));
+    }
+}
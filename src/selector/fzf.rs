@@ -0,0 +1,226 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::completion::CompletionEntry;
+use crate::config::MatcherType;
+use crate::selector::{Selector, SelectorConfig, SelectorError, lone_candidate};
+use log::debug;
+
+const FZF: &str = "fzf";
+
+/// Exit statuses of fzf that mean nothing was picked: no match, and Esc/Ctrl-C
+const FZF_NO_SELECTION: &[i32] = &[1, 130];
+
+/// Runs the external `fzf` binary, which draws on the terminal itself
+#[derive(Default)]
+pub struct FzfSelector;
+
+impl FzfSelector {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl Selector for FzfSelector {
+    fn select_one(
+        &self,
+        candidates: &[CompletionEntry],
+        current_word: &str,
+        config: &SelectorConfig,
+    ) -> Result<Option<CompletionEntry>, SelectorError> {
+        debug!(
+            "FzfSelector::select_one called with {} candidates (fuzzy={})",
+            candidates.len(),
+            config.fuzzy
+        );
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        if let Some(only) = lone_candidate(candidates, config) {
+            return Ok(Some(only.clone()));
+        }
+
+        let mut child = Command::new(FZF)
+            .args(fzf_args(candidates, current_word, config))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            write_input(&mut stdin, &fzf_input(candidates, config))?;
+        }
+        let output = child.wait_with_output()?;
+
+        match output.status.code() {
+            Some(0) => {}
+            Some(code) if FZF_NO_SELECTION.contains(&code) => {
+                debug!("fzf exited with {}, nothing selected", code);
+                return Ok(None);
+            }
+            _ => {
+                return Err(SelectorError::ExecutionError(format!(
+                    "fzf failed: {}",
+                    output.status
+                )));
+            }
+        }
+
+        let selected = String::from_utf8_lossy(&output.stdout);
+        Ok(selected_index(&selected).and_then(|i| candidates.get(i).cloned()))
+    }
+}
+
+/// One line per candidate: `index\tdisplay\tdescription`; only the display is
+/// shown and matched. Position and type markers are part of the display.
+fn fzf_input(candidates: &[CompletionEntry], config: &SelectorConfig) -> String {
+    candidates
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            let display = c.display.as_deref().unwrap_or(&c.value).replace('\t', " ");
+            let index = if config.show_indices {
+                format!("{}) ", i + 1)
+            } else {
+                String::new()
+            };
+            let icon = if config.show_types {
+                format!("{} ", c.candidate_type.icon())
+            } else {
+                String::new()
+            };
+            let description = c.description.as_deref().unwrap_or_default();
+            format!(
+                "{}\t{}{}{}\t{}\n",
+                i,
+                index,
+                icon,
+                display,
+                description.replace(['\t', '\n'], " ")
+            )
+        })
+        .collect()
+}
+
+fn fzf_args(
+    candidates: &[CompletionEntry],
+    current_word: &str,
+    config: &SelectorConfig,
+) -> Vec<String> {
+    let query = match config.matcher {
+        MatcherType::Prefix => format!("^{}", current_word),
+        _ => current_word.to_string(),
+    };
+    let mut args = vec![
+        "--delimiter=\t".to_string(),
+        "--with-nth=2".to_string(),
+        format!("--height={}", config.height),
+        format!("--prompt={}", config.prompt),
+        format!("--query={}", query),
+    ];
+    if let Some(header) = &config.header {
+        args.push(format!("--header={}", header));
+    }
+    if !config.fuzzy || config.nosort {
+        args.push("--no-sort".to_string());
+    }
+    if config.matcher != MatcherType::Skim {
+        args.push("--exact".to_string());
+    }
+    // The full description of the highlighted item
+    if candidates.iter().any(|c| c.description.is_some()) {
+        args.push("--preview=printf '%s' {3}".to_string());
+        args.push("--preview-window=down,1,wrap".to_string());
+    }
+    if config.wrap_candidates {
+        args.push("--wrap".to_string());
+    }
+    args
+}
+
+/// Feed the candidates to fzf. Esc before fzf read them all closes the pipe,
+/// which is a cancel (fzf then exits with 130), not an error
+fn write_input(stdin: &mut impl Write, input: &str) -> io::Result<()> {
+    match stdin.write_all(input.as_bytes()) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+            debug!("fzf closed its input early");
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+/// Candidate index from the line fzf printed
+fn selected_index(line: &str) -> Option<usize> {
+    line.split('\t').next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::{CandidateType, ProviderKind};
+
+    #[test]
+    fn test_no_candidates_skips_fzf() {
+        let selected = FzfSelector::new()
+            .select_one(&[], "x", &SelectorConfig::default())
+            .unwrap();
+        assert!(selected.is_none());
+    }
+
+    /// A pipe whose reader has gone away
+    struct ClosedPipe;
+
+    impl Write for ClosedPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_broken_pipe_is_a_cancel() {
+        assert!(write_input(&mut ClosedPipe, "0\tbuild\t\n").is_ok());
+        let mut buf = Vec::new();
+        write_input(&mut buf, "0\tbuild\t\n").unwrap();
+        assert_eq!(buf, b"0\tbuild\t\n");
+    }
+
+    #[test]
+    fn test_fzf_input_and_args() {
+        let candidates = vec![
+            CompletionEntry::new("checkout".to_string(), ProviderKind::Bash)
+                .with_description("Switch branches".to_string()),
+            CompletionEntry::new("cherry-pick".to_string(), ProviderKind::Bash),
+        ];
+        let mut config = SelectorConfig {
+            show_indices: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            fzf_input(&candidates, &config),
+            "0\t1) checkout\tSwitch branches\n1\t2) cherry-pick\t\n"
+        );
+        assert_eq!(selected_index("1\t2) cherry-pick\t\n"), Some(1));
+
+        config.show_types = true;
+        let dirs = vec![
+            CompletionEntry::new("src/".to_string(), ProviderKind::Bash)
+                .with_type(CandidateType::Dir),
+        ];
+        assert_eq!(fzf_input(&dirs, &config), "0\t1) d src/\t\n");
+
+        let config = SelectorConfig {
+            fuzzy: false,
+            matcher: MatcherType::Prefix,
+            ..Default::default()
+        };
+        let args = fzf_args(&candidates, "che", &config);
+        assert!(args.contains(&"--query=^che".to_string()));
+        assert!(args.contains(&"--no-sort".to_string()));
+        assert!(args.contains(&"--exact".to_string()));
+        assert!(args.iter().any(|a| a.starts_with("--preview=")));
+        assert!(!args.contains(&"--wrap".to_string()));
+    }
+}
@@ -0,0 +1,110 @@
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::completion::CompletionEntry;
+use crate::config::MatcherType;
+
+/// Filter candidates against `query` with the configured matching algorithm.
+///
+/// `Skim` keeps fuzzy matches ordered best-first unless `keep_order` is set
+/// (compspecs with `-o nosort`); `Substring` and `Prefix` are case-insensitive
+/// and always keep the original candidate order.
+pub fn filter_candidates(
+    matcher: MatcherType,
+    candidates: &[CompletionEntry],
+    query: &str,
+    keep_order: bool,
+) -> Vec<CompletionEntry> {
+    if query.is_empty() {
+        return candidates.to_vec();
+    }
+
+    match matcher {
+        MatcherType::Skim => {
+            let skim = SkimMatcherV2::default();
+            let mut scored: Vec<(i64, &CompletionEntry)> = candidates
+                .iter()
+                .filter_map(|c| skim.fuzzy_match(&c.value, query).map(|s| (s, c)))
+                .collect();
+            // Stable sort keeps provider order among equal scores
+            if !keep_order {
+                scored.sort_by(|(s1, _), (s2, _)| s2.cmp(s1));
+            }
+            scored.into_iter().map(|(_, c)| c.clone()).collect()
+        }
+        MatcherType::Substring => {
+            let query = query.to_lowercase();
+            candidates
+                .iter()
+                .filter(|c| c.value.to_lowercase().contains(&query))
+                .cloned()
+                .collect()
+        }
+        MatcherType::Prefix => {
+            let query = query.to_lowercase();
+            candidates
+                .iter()
+                .filter(|c| c.value.to_lowercase().starts_with(&query))
+                .cloned()
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ProviderKind;
+
+    fn sample() -> Vec<CompletionEntry> {
+        ["checkout", "cherry-pick", "fetch", "rebase", "switch"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect()
+    }
+
+    fn values(entries: &[CompletionEntry]) -> Vec<&str> {
+        entries.iter().map(|e| e.value.as_str()).collect()
+    }
+
+    #[test]
+    fn test_skim_matcher() {
+        let filtered = filter_candidates(MatcherType::Skim, &sample(), "ch", false);
+        let values = values(&filtered);
+        assert!(values.contains(&"checkout"));
+        assert!(values.contains(&"cherry-pick"));
+        assert!(values.contains(&"fetch"));
+        assert!(values.contains(&"switch"));
+        assert!(!values.contains(&"rebase"));
+    }
+
+    #[test]
+    fn test_substring_matcher() {
+        let filtered = filter_candidates(MatcherType::Substring, &sample(), "TCH", false);
+        assert_eq!(values(&filtered), vec!["fetch", "switch"]);
+    }
+
+    #[test]
+    fn test_prefix_matcher() {
+        let filtered = filter_candidates(MatcherType::Prefix, &sample(), "che", false);
+        assert_eq!(values(&filtered), vec!["checkout", "cherry-pick"]);
+
+        let filtered = filter_candidates(MatcherType::Prefix, &sample(), "", false);
+        assert_eq!(filtered.len(), 5);
+    }
+
+    #[test]
+    fn test_skim_matcher_keep_order() {
+        // "sw" scores "switch" far above "show-branch"; nosort keeps provider order
+        let candidates: Vec<CompletionEntry> = ["show-branch", "status", "switch"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+
+        let sorted = filter_candidates(MatcherType::Skim, &candidates, "sw", false);
+        assert_eq!(values(&sorted)[0], "switch");
+
+        let kept = filter_candidates(MatcherType::Skim, &candidates, "sw", true);
+        assert_eq!(values(&kept), vec!["show-branch", "switch"]);
+    }
+}
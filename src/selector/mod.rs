@@ -3,6 +3,7 @@ use std::rc::Rc;
 use thiserror::Error;
 
 use crate::completion::{CompletionContext, CompletionEntry};
+use crate::config::MatcherType;
 
 #[derive(Error, Debug)]
 pub enum SelectorError {
@@ -14,6 +15,10 @@ pub enum SelectorError {
     Cancelled,
     #[error("No candidates")]
     NoCandidates,
+    #[cfg(feature = "dialoguer")]
+    #[error("The {0:?} matcher needs the fzf selector")]
+    UnsupportedMatcher(MatcherType),
+    #[cfg(feature = "dialoguer")]
     #[error("Error setting Ctrl-C handler")]
     SettingCtrlCHandler(#[from] ctrlc::Error),
 }
@@ -26,6 +31,21 @@ pub struct SelectorConfig {
     pub header: Option<String>,
     /// If true, use fuzzy matching. If false, preserve input order.
     pub fuzzy: bool,
+    /// The compspec set `nosort`: filter, but keep the candidates in the order
+    /// the completion produced them
+    pub nosort: bool,
+    /// Algorithm used to filter candidates against the current word
+    pub matcher: MatcherType,
+    /// Prefix each item with its position (display only)
+    pub show_indices: bool,
+    /// Prefix each item with a marker for its candidate type (display only)
+    pub show_types: bool,
+    /// Descriptions longer than this many chars are cut with an ellipsis
+    pub max_description_len: usize,
+    /// Wrap items wider than the terminal instead of letting them overflow
+    pub wrap_candidates: bool,
+    /// Prompt even for a single candidate instead of taking it
+    pub confirm_single: bool,
 }
 
 impl Default for SelectorConfig {
@@ -40,6 +60,13 @@ impl Default for SelectorConfig {
             height: "40%".to_string(),
             header: None,
             fuzzy: true,
+            nosort: false,
+            matcher: MatcherType::Skim,
+            show_indices: false,
+            show_types: false,
+            max_description_len: 120,
+            wrap_candidates: false,
+            confirm_single: false,
         }
     }
 }
@@ -53,6 +80,87 @@ pub trait Selector {
     ) -> Result<Option<CompletionEntry>, SelectorError>;
 }
 
+/// The candidate taken without prompting: the only one, unless the caller
+/// asked to confirm it
+pub fn lone_candidate<'a>(
+    candidates: &'a [CompletionEntry],
+    config: &SelectorConfig,
+) -> Option<&'a CompletionEntry> {
+    match candidates {
+        [only] if !config.confirm_single => Some(only),
+        _ => None,
+    }
+}
+
 // Re-export implementations
+#[cfg(feature = "dialoguer")]
 pub mod dialoguer;
+#[cfg(feature = "fzf")]
+pub mod fzf;
+pub mod matcher;
+#[cfg(feature = "dialoguer")]
 mod theme;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ProviderKind;
+
+    /// Picks the last candidate, standing in for an embedder's own selector
+    struct LastSelector;
+
+    impl Selector for LastSelector {
+        fn select_one(
+            &self,
+            candidates: &[CompletionEntry],
+            _current_word: &str,
+            _config: &SelectorConfig,
+        ) -> Result<Option<CompletionEntry>, SelectorError> {
+            candidates
+                .last()
+                .cloned()
+                .map(Some)
+                .ok_or(SelectorError::NoCandidates)
+        }
+    }
+
+    #[test]
+    fn test_custom_selector() {
+        let config = SelectorConfig {
+            fuzzy: false,
+            ..Default::default()
+        };
+        let candidates: Vec<CompletionEntry> = ["build", "check"]
+            .iter()
+            .map(|v| CompletionEntry::new(v.to_string(), ProviderKind::Bash))
+            .collect();
+        let selector: Box<dyn Selector> = Box::new(LastSelector);
+
+        let selected = selector.select_one(&candidates, "", &config).unwrap();
+        assert_eq!(selected.map(|e| e.value).as_deref(), Some("check"));
+        assert!(matches!(
+            selector.select_one(&[], "", &config),
+            Err(SelectorError::NoCandidates)
+        ));
+    }
+
+    #[test]
+    fn test_confirm_single_skips_shortcut() {
+        let only = vec![CompletionEntry::new(
+            "build".to_string(),
+            ProviderKind::Bash,
+        )];
+        let config = SelectorConfig::default();
+        assert_eq!(
+            lone_candidate(&only, &config).map(|e| e.value.as_str()),
+            Some("build")
+        );
+
+        let config = SelectorConfig {
+            confirm_single: true,
+            ..Default::default()
+        };
+        assert!(lone_candidate(&only, &config).is_none());
+        assert!(lone_candidate(&[], &SelectorConfig::default()).is_none());
+    }
+}
@@ -1,8 +1,57 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
+use std::mem;
 
+use dialoguer::console::measure_text_width;
 use dialoguer::theme::{ColorfulTheme, Theme};
 use fuzzy_matcher::skim::SkimMatcherV2;
 
+use crate::completion::{CandidateType, CompletionEntry};
+
+/// Detail line for the highlighted item; newlines are flattened so the
+/// description always takes exactly one line
+pub fn format_description_footer(description: &str) -> String {
+    let flat: Vec<&str> = description.split_whitespace().collect();
+    format!("    ╰ {}", flat.join(" "))
+}
+
+/// Cut `description` to at most `max_len` chars, ending in `…` when shortened
+pub fn truncate_description(description: &str, max_len: usize) -> String {
+    if description.chars().count() <= max_len {
+        return description.to_string();
+    }
+    let mut truncated: String = description
+        .chars()
+        .take(max_len.saturating_sub(1))
+        .collect();
+    truncated.push('…');
+    truncated
+}
+
+/// Columns taken by the `❯ ` marker in front of each item
+const ITEM_PREFIX_WIDTH: usize = 2;
+
+/// Split `text` into lines of at most `width` columns. Splits fall between
+/// chars, so multibyte and double-width characters are never cut.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut columns = 0;
+    for c in text.chars() {
+        let char_width = measure_text_width(c.encode_utf8(&mut [0; 4]));
+        if columns + char_width > width && !line.is_empty() {
+            lines.push(mem::take(&mut line));
+            columns = 0;
+        }
+        line.push(c);
+        columns += char_width;
+    }
+    lines.push(line);
+    lines
+}
+
 #[allow(dead_code)]
 pub struct CustomSimpleTheme;
 impl Theme for CustomSimpleTheme {
@@ -22,21 +71,151 @@ impl Theme for CustomSimpleTheme {
     }
 }
 
-pub struct CustomColorfulTheme(ColorfulTheme);
+pub struct CustomColorfulTheme {
+    inner: ColorfulTheme,
+    /// 1-based item positions by item text, when indices are shown
+    indices: Option<HashMap<String, usize>>,
+    /// Candidate types by item text, when type markers are shown
+    types: Option<HashMap<String, CandidateType>>,
+    /// Item descriptions by item text, shown below the highlighted item
+    descriptions: HashMap<String, String>,
+    max_description_len: Option<usize>,
+    /// Terminal width long items are wrapped at
+    wrap_width: Option<usize>,
+}
 
 impl CustomColorfulTheme {
     pub fn new() -> Self {
-        Self(Default::default())
+        Self {
+            inner: Default::default(),
+            indices: None,
+            types: None,
+            descriptions: HashMap::new(),
+            max_description_len: None,
+            wrap_width: None,
+        }
+    }
+
+    /// Wrap items wider than `width` columns onto indented continuation lines
+    pub fn with_wrap_width(mut self, width: usize) -> Self {
+        self.wrap_width = Some(width);
+        self
+    }
+
+    fn wrap_item<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let Some(width) = self.wrap_width else {
+            return Cow::Borrowed(text);
+        };
+        let indent = ITEM_PREFIX_WIDTH + self.index_width() + self.type_width();
+        let available = width.saturating_sub(indent);
+        if measure_text_width(text) <= available {
+            return Cow::Borrowed(text);
+        }
+        let separator = format!("\n{}", " ".repeat(indent));
+        Cow::Owned(wrap_text(text, available).join(&separator))
+    }
+
+    /// Truncate displayed descriptions to `max_len` chars
+    pub fn with_max_description_len(mut self, max_len: usize) -> Self {
+        self.max_description_len = Some(max_len);
+        self
+    }
+
+    /// Show the full description of the highlighted entry on a line below it
+    pub fn with_descriptions(mut self, entries: &[CompletionEntry]) -> Self {
+        self.descriptions = entries
+            .iter()
+            .filter_map(|e| Some((e.to_string(), e.description.clone()?)))
+            .collect();
+        self
+    }
+
+    fn write_description(&self, f: &mut dyn fmt::Write, text: &str, active: bool) -> fmt::Result {
+        match self.descriptions.get(text) {
+            Some(description) if active => {
+                let description = match self.max_description_len {
+                    Some(max_len) => truncate_description(description, max_len),
+                    None => description.clone(),
+                };
+                write!(
+                    f,
+                    "\n{}",
+                    self.inner
+                        .hint_style
+                        .apply_to(format_description_footer(&description))
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Prefix each item with its 1-based position in `items`. The index is only
+    /// drawn by the theme, so matching and the selected value are unaffected.
+    pub fn with_indices<T: fmt::Display>(mut self, items: &[T]) -> Self {
+        self.indices = Some(
+            items
+                .iter()
+                .enumerate()
+                .map(|(idx, item)| (item.to_string(), idx + 1))
+                .collect(),
+        );
+        self
+    }
+
+    /// Columns taken by the `N) ` index in front of items, if shown
+    fn index_width(&self) -> usize {
+        self.indices
+            .as_ref()
+            .map_or(0, |indices| indices.len().to_string().len() + 2)
+    }
+
+    fn write_index(&self, f: &mut dyn fmt::Write, text: &str) -> fmt::Result {
+        let Some(indices) = &self.indices else {
+            return Ok(());
+        };
+        if let Some(idx) = indices.get(text) {
+            let width = indices.len().to_string().len();
+            write!(
+                f,
+                "{} ",
+                self.inner.hint_style.apply_to(format!("{:>width$})", idx))
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Prefix each item with the `icon()` of its candidate type (display only)
+    pub fn with_types(mut self, entries: &[CompletionEntry]) -> Self {
+        self.types = Some(
+            entries
+                .iter()
+                .map(|e| (e.to_string(), e.candidate_type))
+                .collect(),
+        );
+        self
+    }
+
+    /// Columns taken by the type marker in front of items, if shown
+    fn type_width(&self) -> usize {
+        if self.types.is_some() { 2 } else { 0 }
+    }
+
+    fn write_type(&self, f: &mut dyn fmt::Write, text: &str) -> fmt::Result {
+        let Some(types) = &self.types else {
+            return Ok(());
+        };
+        let icon = types.get(text).map_or(" ", CandidateType::icon);
+        write!(f, "{} ", self.inner.hint_style.apply_to(icon))
     }
 }
 
 impl Theme for CustomColorfulTheme {
     fn format_prompt(&self, f: &mut dyn fmt::Write, prompt: &str) -> fmt::Result {
-        self.0.format_prompt(f, prompt)
+        self.inner.format_prompt(f, prompt)
     }
 
     fn format_error(&self, f: &mut dyn fmt::Write, err: &str) -> fmt::Result {
-        self.0.format_error(f, err)
+        self.inner.format_error(f, err)
     }
 
     fn format_confirm_prompt(
@@ -45,7 +224,7 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         default: Option<bool>,
     ) -> fmt::Result {
-        self.0.format_confirm_prompt(f, prompt, default)
+        self.inner.format_confirm_prompt(f, prompt, default)
     }
 
     fn format_confirm_prompt_selection(
@@ -54,7 +233,8 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         selection: Option<bool>,
     ) -> fmt::Result {
-        self.0.format_confirm_prompt_selection(f, prompt, selection)
+        self.inner
+            .format_confirm_prompt_selection(f, prompt, selection)
     }
 
     fn format_input_prompt(
@@ -63,7 +243,7 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         default: Option<&str>,
     ) -> fmt::Result {
-        self.0.format_input_prompt(f, prompt, default)
+        self.inner.format_input_prompt(f, prompt, default)
     }
 
     fn format_input_prompt_selection(
@@ -72,11 +252,11 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         sel: &str,
     ) -> fmt::Result {
-        self.0.format_input_prompt_selection(f, prompt, sel)
+        self.inner.format_input_prompt_selection(f, prompt, sel)
     }
 
     fn format_password_prompt(&self, f: &mut dyn fmt::Write, prompt: &str) -> fmt::Result {
-        self.0.format_password_prompt(f, prompt)
+        self.inner.format_password_prompt(f, prompt)
     }
 
     fn format_password_prompt_selection(
@@ -84,11 +264,11 @@ impl Theme for CustomColorfulTheme {
         f: &mut dyn fmt::Write,
         prompt: &str,
     ) -> fmt::Result {
-        self.0.format_password_prompt_selection(f, prompt)
+        self.inner.format_password_prompt_selection(f, prompt)
     }
 
     fn format_select_prompt(&self, f: &mut dyn fmt::Write, prompt: &str) -> fmt::Result {
-        self.0.format_select_prompt(f, prompt)
+        self.inner.format_select_prompt(f, prompt)
     }
 
     fn format_select_prompt_selection(
@@ -97,15 +277,15 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         sel: &str,
     ) -> fmt::Result {
-        self.0.format_select_prompt_selection(f, prompt, sel)
+        self.inner.format_select_prompt_selection(f, prompt, sel)
     }
 
     fn format_multi_select_prompt(&self, f: &mut dyn fmt::Write, prompt: &str) -> fmt::Result {
-        self.0.format_multi_select_prompt(f, prompt)
+        self.inner.format_multi_select_prompt(f, prompt)
     }
 
     fn format_sort_prompt(&self, f: &mut dyn fmt::Write, prompt: &str) -> fmt::Result {
-        self.0.format_sort_prompt(f, prompt)
+        self.inner.format_sort_prompt(f, prompt)
     }
 
     fn format_multi_select_prompt_selection(
@@ -114,7 +294,7 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         selections: &[&str],
     ) -> fmt::Result {
-        self.0
+        self.inner
             .format_multi_select_prompt_selection(f, prompt, selections)
     }
 
@@ -124,7 +304,8 @@ impl Theme for CustomColorfulTheme {
         prompt: &str,
         selections: &[&str],
     ) -> fmt::Result {
-        self.0.format_sort_prompt_selection(f, prompt, selections)
+        self.inner
+            .format_sort_prompt_selection(f, prompt, selections)
     }
 
     fn format_select_prompt_item(
@@ -133,7 +314,11 @@ impl Theme for CustomColorfulTheme {
         text: &str,
         active: bool,
     ) -> fmt::Result {
-        self.0.format_select_prompt_item(f, text, active)
+        self.write_index(f, text)?;
+        self.write_type(f, text)?;
+        self.inner
+            .format_select_prompt_item(f, &self.wrap_item(text), active)?;
+        self.write_description(f, text, active)
     }
 
     fn format_multi_select_prompt_item(
@@ -143,7 +328,7 @@ impl Theme for CustomColorfulTheme {
         checked: bool,
         active: bool,
     ) -> fmt::Result {
-        self.0
+        self.inner
             .format_multi_select_prompt_item(f, text, checked, active)
     }
 
@@ -154,7 +339,7 @@ impl Theme for CustomColorfulTheme {
         picked: bool,
         active: bool,
     ) -> fmt::Result {
-        self.0.format_sort_prompt_item(f, text, picked, active)
+        self.inner.format_sort_prompt_item(f, text, picked, active)
     }
 
     fn format_fuzzy_select_prompt_item(
@@ -166,14 +351,17 @@ impl Theme for CustomColorfulTheme {
         matcher: &SkimMatcherV2,
         search_term: &str,
     ) -> fmt::Result {
-        self.0.format_fuzzy_select_prompt_item(
+        self.write_index(f, text)?;
+        self.write_type(f, text)?;
+        self.inner.format_fuzzy_select_prompt_item(
             f,
-            text,
+            &self.wrap_item(text),
             active,
             highlight_matches,
             matcher,
             search_term,
-        )
+        )?;
+        self.write_description(f, text, active)
     }
 
     fn format_fuzzy_select_prompt(
@@ -187,17 +375,129 @@ impl Theme for CustomColorfulTheme {
             write!(
                 f,
                 "{} {}",
-                self.0.prompt_prefix,
-                self.0.prompt_style.apply_to(prompt)
+                self.inner.prompt_prefix,
+                self.inner.prompt_style.apply_to(prompt)
             )?;
         }
 
         let (st_head, remaining) = search_term.split_at(bytes_pos);
         let mut chars = remaining.chars();
         let chr = chars.next().unwrap_or(' ');
-        let st_cursor = self.0.fuzzy_cursor_style.apply_to(chr);
+        let st_cursor = self.inner.fuzzy_cursor_style.apply_to(chr);
         let st_tail = chars.as_str();
 
         write!(f, "{st_head}{st_cursor}{st_tail}",)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::completion::ProviderKind;
+    use dialoguer::console::strip_ansi_codes;
+
+    fn render(theme: &CustomColorfulTheme, text: &str) -> String {
+        let mut out = String::new();
+        theme
+            .format_fuzzy_select_prompt_item(
+                &mut out,
+                text,
+                false,
+                false,
+                &SkimMatcherV2::default(),
+                "",
+            )
+            .unwrap();
+        strip_ansi_codes(&out).to_string()
+    }
+
+    #[test]
+    fn test_format_description_footer() {
+        assert_eq!(
+            format_description_footer("Switch branches\n  or restore files"),
+            "    ╰ Switch branches or restore files"
+        );
+    }
+
+    #[test]
+    fn test_truncate_description_multibyte() {
+        let description = "日本語の説明文です";
+        assert_eq!(truncate_description(description, 5), "日本語の…");
+        assert_eq!(truncate_description(description, 9), description);
+        assert_eq!(truncate_description("short", 80), "short");
+    }
+
+    #[test]
+    fn test_description_only_under_active_item() {
+        let entries = vec![
+            CompletionEntry::new("checkout".to_string(), ProviderKind::Carapace)
+                .with_description("Switch branches".to_string()),
+        ];
+        let theme = CustomColorfulTheme::new().with_descriptions(&entries);
+
+        let mut active = String::new();
+        theme
+            .format_select_prompt_item(&mut active, "checkout", true)
+            .unwrap();
+        let active = strip_ansi_codes(&active).to_string();
+        assert_eq!(active.lines().count(), 2);
+        assert!(active.ends_with("╰ Switch branches"));
+
+        assert!(!render(&theme, "checkout").contains('\n'));
+    }
+
+    #[test]
+    fn test_wrap_long_candidate() {
+        assert_eq!(wrap_text("short", 10), vec!["short"]);
+        assert_eq!(
+            wrap_text("src/components/button.tsx", 10),
+            vec!["src/compon", "ents/butto", "n.tsx"]
+        );
+        // Double-width characters take two columns and are never split
+        assert_eq!(wrap_text("日本語の説明", 5), vec!["日本", "語の", "説明"]);
+
+        let long = "a".repeat(30);
+        let theme = CustomColorfulTheme::new().with_wrap_width(12);
+        let rendered = render(&theme, &long);
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines.iter().all(|l| measure_text_width(l) <= 12));
+        assert_eq!(rendered.replace(['\n', ' '], ""), long);
+
+        assert!(!render(&CustomColorfulTheme::new(), &long).contains('\n'));
+    }
+
+    #[test]
+    fn test_item_indices_are_display_only() {
+        let entries: Vec<CompletionEntry> = (1..=10)
+            .map(|i| CompletionEntry::new(format!("item{}", i), ProviderKind::Bash))
+            .collect();
+
+        let plain = CustomColorfulTheme::new();
+        assert!(render(&plain, "item2").trim_start().starts_with("item2"));
+
+        let indexed = CustomColorfulTheme::new().with_indices(&entries);
+        let rendered = render(&indexed, "item2");
+        assert!(rendered.starts_with(" 2) "), "got {:?}", rendered);
+        assert!(rendered.ends_with("item2"));
+        // The value handed back to the caller is untouched
+        assert_eq!(entries[1].value, "item2");
+        assert_eq!(entries[1].to_string(), "item2");
+    }
+
+    #[test]
+    fn test_item_type_markers() {
+        let entries = vec![
+            CompletionEntry::new("src/".to_string(), ProviderKind::Bash)
+                .with_type(CandidateType::Dir),
+            CompletionEntry::new("--verbose".to_string(), ProviderKind::Bash)
+                .with_type(CandidateType::Flag),
+        ];
+        let theme = CustomColorfulTheme::new()
+            .with_indices(&entries)
+            .with_types(&entries);
+        assert!(render(&theme, "src/").starts_with("1) d "));
+        assert!(render(&theme, "--verbose").starts_with("2) - "));
+        assert!(!render(&CustomColorfulTheme::new(), "src/").starts_with("d "));
+    }
+}